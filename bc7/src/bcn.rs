@@ -0,0 +1,322 @@
+// BC1–BC5 (DXT1, DXT3, DXT5, and their one- and two-channel siblings)
+//
+// BC1 blocks hold two RGB565 endpoints and a 2-bit index per pixel. If the first endpoint is the
+// larger one the palette has four colors, otherwise it has three and a transparent black. BC2 and
+// BC3 prepend 64 bits of alpha to a BC1 block, which then always has four colors: BC2 stores it
+// directly as 4 bits per pixel, while BC3 uses a BC4 block. BC4 blocks are like BC1 blocks for a
+// single 8-bit channel, with 3-bit indices; BC5 is two of them, for red and green.
+//
+// The encoders fit endpoints to the principal axis of the colors and refine them by least squares,
+// which is good enough for formats this small.
+//
+// Specification: https://registry.khronos.org/DataFormat/specs/1.3/dataformat.1.3.html#s3tc
+
+use crate::encode::{least_squares_weights, principal_endpoints, round, sq};
+use crate::Rgba;
+
+const REFINE: usize = 2;
+
+pub fn decode_bc1(block: u64) -> [[Rgba; 4]; 4] {
+	let c0 = block as u16;
+	let c1 = (block >> 16) as u16;
+	decode_color(block, c0 > c1)
+}
+
+pub fn decode_bc2(block: u128) -> [[Rgba; 4]; 4] {
+	let mut out = decode_color((block >> 64) as u64, true);
+	for (i, px) in out.as_flattened_mut().iter_mut().enumerate() {
+		px[3] = ((block >> (4 * i)) & 15) as u8 * 17;
+	}
+	out
+}
+
+pub fn decode_bc3(block: u128) -> [[Rgba; 4]; 4] {
+	let mut out = decode_color((block >> 64) as u64, true);
+	let alpha = decode_channel(block as u64);
+	for (px, a) in out.as_flattened_mut().iter_mut().zip(alpha) {
+		px[3] = a;
+	}
+	out
+}
+
+/// Decodes into the red channel, like Direct3D does.
+pub fn decode_bc4(block: u64) -> [[Rgba; 4]; 4] {
+	let r = decode_channel(block);
+	core::array::from_fn(|y| core::array::from_fn(|x| [r[y * 4 + x], 0, 0, 255]))
+}
+
+/// Decodes into the red and green channels, like Direct3D does.
+pub fn decode_bc5(block: u128) -> [[Rgba; 4]; 4] {
+	let r = decode_channel(block as u64);
+	let g = decode_channel((block >> 64) as u64);
+	core::array::from_fn(|y| core::array::from_fn(|x| [r[y * 4 + x], g[y * 4 + x], 0, 255]))
+}
+
+/// Pixels with alpha below 128 are made transparent, and the rest opaque.
+pub fn encode_bc1(block: &[[Rgba; 4]; 4]) -> u64 {
+	encode_color(block.as_flattened(), true)
+}
+
+pub fn encode_bc2(block: &[[Rgba; 4]; 4]) -> u128 {
+	let px = block.as_flattened();
+	let mut alpha = 0u64;
+	for (i, p) in px.iter().enumerate() {
+		alpha |= (round(p[3] as f32 / 17.) as u64) << (4 * i);
+	}
+	alpha as u128 | (encode_color(px, false) as u128) << 64
+}
+
+pub fn encode_bc3(block: &[[Rgba; 4]; 4]) -> u128 {
+	let px = block.as_flattened();
+	let alpha = encode_channel(&core::array::from_fn(|i| px[i][3]));
+	alpha as u128 | (encode_color(px, false) as u128) << 64
+}
+
+/// Encodes the red channel.
+pub fn encode_bc4(block: &[[Rgba; 4]; 4]) -> u64 {
+	let px = block.as_flattened();
+	encode_channel(&core::array::from_fn(|i| px[i][0]))
+}
+
+/// Encodes the red and green channels.
+pub fn encode_bc5(block: &[[Rgba; 4]; 4]) -> u128 {
+	let px = block.as_flattened();
+	let r = encode_channel(&core::array::from_fn(|i| px[i][0]));
+	let g = encode_channel(&core::array::from_fn(|i| px[i][1]));
+	r as u128 | (g as u128) << 64
+}
+
+fn decode_color(block: u64, four: bool) -> [[Rgba; 4]; 4] {
+	let pal = color_palette(block as u16, (block >> 16) as u16, four);
+	core::array::from_fn(|y| core::array::from_fn(|x| {
+		pal[(block >> (32 + 2 * (y * 4 + x))) as usize & 3]
+	}))
+}
+
+fn color_palette(c0: u16, c1: u16, four: bool) -> [Rgba; 4] {
+	let a = unpack565(c0);
+	let b = unpack565(c1);
+	let mix = |wa: u32, wb: u32| -> Rgba {
+		let n = wa + wb;
+		core::array::from_fn(|k| ((a[k] as u32 * wa + b[k] as u32 * wb + n / 2) / n) as u8)
+	};
+	if four {
+		[a, b, mix(2, 1), mix(1, 2)]
+	} else {
+		[a, b, mix(1, 1), [0, 0, 0, 0]]
+	}
+}
+
+fn unpack565(c: u16) -> Rgba {
+	let r = (c >> 11) as u8 & 31;
+	let g = (c >> 5) as u8 & 63;
+	let b = c as u8 & 31;
+	[r << 3 | r >> 2, g << 2 | g >> 4, b << 3 | b >> 2, 255]
+}
+
+fn pack565(e: &[f32; 4]) -> u16 {
+	let q = |v: f32, max: i32| round(v * max as f32 / 255.).clamp(0, max) as u16;
+	q(e[0], 31) << 11 | q(e[1], 63) << 5 | q(e[2], 31)
+}
+
+/// With `punchthrough`, pixels with low alpha are encoded as transparent, which requires the
+/// three-color palette. Otherwise alpha is ignored, and the block must be decoded as four-color.
+fn encode_color(px: &[Rgba], punchthrough: bool) -> u64 {
+	let transparent = |p: &Rgba| punchthrough && p[3] < 128;
+	let mut sub = [Rgba::default(); 16];
+	let mut n = 0;
+	for p in px.iter().filter(|p| !transparent(p)) {
+		sub[n] = *p;
+		n += 1;
+	}
+	let sub = &sub[..n];
+	let has_transparent = n < px.len();
+
+	let mut best = (u32::MAX, 0);
+	let palettes: &[bool] = match (punchthrough, has_transparent) {
+		(false, _) => &[true],
+		(true, false) => &[true, false],
+		(true, true) => &[false],
+	};
+	for &want_four in palettes {
+		let (mut e0, mut e1) = principal_endpoints(sub, 0..3);
+		for round in 0..=REFINE {
+			let (mut c0, mut c1) = (pack565(&e0), pack565(&e1));
+			// In BC1, the order of the endpoints selects the palette.
+			if punchthrough && (c0 > c1) != want_four {
+				(c0, c1) = (c1, c0);
+			}
+			let four = !punchthrough || c0 > c1;
+			let pal = color_palette(c0, c1, four);
+			let usable = if four { 4 } else { 3 };
+			let mut idx = [0u8; 16];
+			let mut err = 0;
+			for (i, p) in px.iter().enumerate() {
+				if transparent(p) {
+					idx[i] = 3;
+					continue
+				}
+				let (d, j) = (0..usable)
+					.map(|j| ((0..3).map(|k| sq(pal[j][k] as i32 - p[k] as i32)).sum::<u32>(), j))
+					.min().unwrap();
+				idx[i] = j as u8;
+				err += d;
+			}
+			let mut block = c0 as u64 | (c1 as u64) << 16;
+			for (i, &j) in idx.iter().enumerate() {
+				block |= (j as u64) << (32 + 2 * i);
+			}
+			if err < best.0 {
+				best = (err, block);
+			}
+			if round == REFINE || err == 0 {
+				break
+			}
+
+			let weight = |j: u8| match (j, four) {
+				(0, _) => 0.,
+				(1, _) => 1.,
+				(2, true) => 1. / 3.,
+				(3, true) => 2. / 3.,
+				(_, false) => 0.5,
+				_ => unreachable!(),
+			};
+			let weights = px.iter().zip(idx).filter(|(p, _)| !transparent(p)).map(|(_, j)| weight(j));
+			match least_squares_weights(sub, 0..3, weights) {
+				Some((a, b)) => (e0, e1) = (a, b),
+				None => break,
+			}
+		}
+	}
+	best.1
+}
+
+fn decode_channel(block: u64) -> [u8; 16] {
+	let pal = channel_palette(block as u8, (block >> 8) as u8);
+	core::array::from_fn(|i| pal[(block >> (16 + 3 * i)) as usize & 7])
+}
+
+fn channel_palette(a: u8, b: u8) -> [u8; 8] {
+	let (a, b) = (a as u32, b as u32);
+	let mix = |wa: u32, wb: u32| {
+		let n = wa + wb;
+		((a * wa + b * wb + n / 2) / n) as u8
+	};
+	if a > b {
+		[a as u8, b as u8, mix(6, 1), mix(5, 2), mix(4, 3), mix(3, 4), mix(2, 5), mix(1, 6)]
+	} else {
+		[a as u8, b as u8, mix(4, 1), mix(3, 2), mix(2, 3), mix(1, 4), 0, 255]
+	}
+}
+
+fn encode_channel(v: &[u8; 16]) -> u64 {
+	let pack = |a: u8, b: u8| -> (u32, u64) {
+		let pal = channel_palette(a, b);
+		let mut block = a as u64 | (b as u64) << 8;
+		let mut err = 0;
+		for (i, &x) in v.iter().enumerate() {
+			let (d, j) = (0..8).map(|j| (sq(pal[j] as i32 - x as i32), j)).min().unwrap();
+			block |= (j as u64) << (16 + 3 * i);
+			err += d;
+		}
+		(err, block)
+	};
+	let weight = |a: u8, b: u8, j: u64| -> Option<f32> {
+		let n = if a > b { 7. } else { 5. };
+		match j {
+			0 => Some(0.),
+			1 => Some(1.),
+			6 | 7 if a <= b => None,
+			_ => Some((j - 1) as f32 / n),
+		}
+	};
+
+	let lo = *v.iter().min().unwrap();
+	let hi = *v.iter().max().unwrap();
+	if lo == hi {
+		return pack(lo, hi).1
+	}
+	// The six-value palette also has 0 and 255, so those don't need to be covered by the endpoints.
+	let inner = v.iter().filter(|&&x| x != 0 && x != 255);
+	let lo6 = inner.clone().min().copied().unwrap_or(lo);
+	let hi6 = inner.max().copied().unwrap_or(hi);
+
+	let mut best = (u32::MAX, 0);
+	for (mut a, mut b) in [(hi, lo), (lo6, hi6)] {
+		let eight = a > b;
+		for round in 0..=REFINE {
+			let c = pack(a, b);
+			if c.0 < best.0 {
+				best = c;
+			}
+			if round == REFINE || c.0 == 0 {
+				break
+			}
+			let mut sub = [Rgba::default(); 16];
+			let mut weights = [0.; 16];
+			let mut n = 0;
+			for (i, &x) in v.iter().enumerate() {
+				if let Some(w) = weight(a, b, (c.1 >> (16 + 3 * i)) & 7) {
+					sub[n][0] = x;
+					weights[n] = w;
+					n += 1;
+				}
+			}
+			let Some((e0, e1)) = least_squares_weights(&sub[..n], 0..1, weights[..n].iter().copied()) else { break };
+			let (na, nb) = (round_u8(e0[0]), round_u8(e1[0]));
+			// Keep the same palette, or the weights no longer apply.
+			(a, b) = match (eight, na.cmp(&nb)) {
+				(true, core::cmp::Ordering::Greater) | (false, core::cmp::Ordering::Less | core::cmp::Ordering::Equal) => (na, nb),
+				_ => break,
+			};
+		}
+	}
+	best.1
+}
+
+fn round_u8(x: f32) -> u8 {
+	round(x).clamp(0, 255) as u8
+}
+
+#[test]
+fn roundtrip() {
+	let mut seed = 7u32;
+	let mut rand = || {
+		seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+		(seed >> 16) as u8
+	};
+	let gradient: [[Rgba; 4]; 4] = core::array::from_fn(|_| core::array::from_fn(|x| {
+		let t = x as u8 * 40;
+		[20 + t, 200 - t, 100, 60 + t]
+	}));
+	let noise: [[Rgba; 4]; 4] = core::array::from_fn(|_| core::array::from_fn(|_| [rand(), rand(), rand(), rand()]));
+	let max_err = |a: &[[Rgba; 4]; 4], b: &[[Rgba; 4]; 4], chans: core::ops::Range<usize>| {
+		a.as_flattened().iter().zip(b.as_flattened())
+			.flat_map(|(a, b)| chans.clone().map(move |k| a[k].abs_diff(b[k])))
+			.max().unwrap()
+	};
+
+	// Four evenly spaced colors survive all of them nearly intact.
+	let g = &gradient;
+	assert!(max_err(g, &decode_bc1(encode_bc1(&g.map(|r| r.map(|p| [p[0], p[1], p[2], 255])))), 0..3) <= 8);
+	assert!(max_err(g, &decode_bc2(encode_bc2(g)), 0..4) <= 8);
+	assert!(max_err(g, &decode_bc3(encode_bc3(g)), 0..4) <= 8);
+	assert!(max_err(g, &decode_bc4(encode_bc4(g)), 0..1) <= 6);
+	assert!(max_err(g, &decode_bc5(encode_bc5(g)), 0..2) <= 6);
+
+	// BC1 keeps transparency.
+	let mut b = gradient.map(|r| r.map(|p| [p[0], p[1], p[2], 255]));
+	b[1][2][3] = 0;
+	let d = decode_bc1(encode_bc1(&b));
+	assert_eq!(d[1][2], [0, 0, 0, 0]);
+	assert!(d.as_flattened().iter().filter(|p| p[3] == 0).count() == 1);
+
+	// Noise can't be reproduced, but BC4 should at least hit the extremes with its six-value mode.
+	let mut v = noise;
+	v[0][0][0] = 0;
+	v[3][3][0] = 255;
+	let d = decode_bc4(encode_bc4(&v));
+	assert_eq!((d[0][0][0], d[3][3][0]), (0, 255));
+	let flat = [[[77, 0, 0, 255]; 4]; 4];
+	assert_eq!(decode_bc4(encode_bc4(&flat)), flat);
+}
@@ -136,7 +136,7 @@ fn decode_with_mode<
 	output
 }
 
-fn subset_index<const NS: usize>(partition: usize, x: usize, y: usize) -> (usize, bool) {
+pub(crate) fn subset_index<const NS: usize>(partition: usize, x: usize, y: usize) -> (usize, bool) {
 	// Table 114. Partition table for 2-subset BPTC, with the 4×4 block of values for each partition number
 	const P2: [[[u8; 4]; 4]; 64] = [
 		[[0, 0, 1, 1], [0, 0, 1, 1], [0, 0, 1, 1], [0, 0, 1, 1]],
@@ -326,7 +326,7 @@ fn subset_index<const NS: usize>(partition: usize, x: usize, y: usize) -> (usize
 	(index, is_anchor)
 }
 
-fn interpolate<const IB: usize>(e0: u8, e1: u8, i: usize) -> u8 {
+pub(crate) fn interpolate<const IB: usize>(e0: u8, e1: u8, i: usize) -> u8 {
 	let weight = match IB {
 		// Table 119. BPTC interpolation factors
 		2 => [       0,             21,             43,             64      ][i],
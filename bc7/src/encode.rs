@@ -0,0 +1,584 @@
+// BC7 encoder
+//
+// Each candidate encoding (mode, partition, rotation and index selection) is made by fitting the
+// endpoints of each subset to the principal axis of its colors, quantizing them with each possible
+// combination of p-bits, and picking the closest palette entry for each pixel. Optionally, the
+// endpoints are then refined by least squares on the chosen indices, and the process repeated.
+// The candidate with the lowest squared error is kept.
+//
+// Since fully encoding all 64 partitions of each mode is slow, partitions are first ranked by how
+// well each of their subsets fit on a line, and only the best few are tried.
+//
+// Specification: https://registry.khronos.org/DataFormat/specs/1.3/dataformat.1.3.html#bptc_bc7
+
+use core::ops::Range;
+
+use crate::decode::{interpolate, subset_index};
+use crate::Rgba;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Quality {
+	/// Only modes 1 and 6, with a single partition guess and no refinement.
+	Fast,
+	/// All modes, the four most promising partitions, and one round of refinement.
+	#[default]
+	Normal,
+	/// All modes and partitions, and several rounds of refinement.
+	Slow,
+}
+
+struct Settings {
+	modes: &'static [usize],
+	partitions: usize,
+	refine: usize,
+	rotations: bool,
+}
+
+impl Quality {
+	fn settings(self) -> Settings {
+		match self {
+			Quality::Fast   => Settings { modes: &[1, 6],                   partitions: 1,  refine: 0, rotations: false },
+			Quality::Normal => Settings { modes: &[0, 1, 2, 3, 4, 5, 6, 7], partitions: 4,  refine: 1, rotations: true },
+			Quality::Slow   => Settings { modes: &[0, 1, 2, 3, 4, 5, 6, 7], partitions: 64, refine: 3, rotations: true },
+		}
+	}
+}
+
+// Same columns as in decode_with_mode.
+struct Mode {
+	ns: usize,
+	pb: usize,
+	rb: usize,
+	isb: usize,
+	cb: usize,
+	ab: usize,
+	epb: usize,
+	spb: usize,
+	ib: usize,
+	ib2: usize,
+}
+
+macro_rules! modes {
+	($($ns:literal $pb:literal $rb:literal $isb:literal $cb:literal $ab:literal $epb:literal $spb:literal $ib:literal $ib2:literal),* $(,)?) => {
+		[$(Mode { ns: $ns, pb: $pb, rb: $rb, isb: $isb, cb: $cb, ab: $ab, epb: $epb, spb: $spb, ib: $ib, ib2: $ib2 }),*]
+	}
+}
+
+// Table 109. Mode-dependent BPTC parameters
+const MODES: [Mode; 8] = modes![
+//  NS PB RS ISB CB AB EPB SPB IB IB₂
+	3  4  0  0   4  0  1   0   3  0,
+	2  6  0  0   6  0  0   1   3  0,
+	3  6  0  0   5  0  0   0   2  0,
+	2  6  0  0   7  0  1   0   2  0,
+	1  0  2  1   5  6  0   0   2  3,
+	1  0  2  0   7  8  0   0   2  2,
+	1  0  0  0   7  7  1   0   4  0,
+	2  6  0  0   5  5  1   0   2  0,
+];
+
+pub fn encode(block: &[[Rgba; 4]; 4], quality: Quality) -> u128 {
+	let settings = quality.settings();
+	let px = flatten(block);
+	let mut best = (u32::MAX, 0);
+	for &m in settings.modes {
+		let c = encode_mode(&px, m, &settings);
+		if c.0 < best.0 {
+			best = c;
+		}
+	}
+	best.1
+}
+
+fn flatten(block: &[[Rgba; 4]; 4]) -> [Rgba; 16] {
+	let mut px = [Rgba::default(); 16];
+	for (i, p) in px.iter_mut().enumerate() {
+		*p = block[i / 4][i % 4];
+	}
+	px
+}
+
+/// Returns the best block for the mode, and its squared error.
+fn encode_mode(px: &[Rgba; 16], m: usize, s: &Settings) -> (u32, u128) {
+	let mode = &MODES[m];
+	let mut best = (u32::MAX, 0);
+	let mut consider = |c: (u32, u128)| if c.0 < best.0 { best = c };
+	if mode.ns == 1 {
+		let rotations = if s.rotations { 1 << mode.rb } else { 1 };
+		for rotation in 0..rotations {
+			for isel in 0..1 << mode.isb {
+				consider(encode_with(px, m, 0, rotation, isel, s.refine));
+			}
+		}
+	} else {
+		let n = 1 << mode.pb;
+		let mut ranked = [(0., 0); 64];
+		for (p, r) in ranked[..n].iter_mut().enumerate() {
+			*r = (partition_error(px, mode, p), p);
+		}
+		let ranked = &mut ranked[..n];
+		ranked.sort_unstable_by(|a, b| a.0.total_cmp(&b.0));
+		for &(_, p) in ranked.iter().take(s.partitions) {
+			consider(encode_with(px, m, p, 0, 0, s.refine));
+		}
+	}
+	best
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PBits {
+	None,
+	Each,
+	Shared,
+}
+
+impl PBits {
+	fn combos(self) -> &'static [(u8, u8)] {
+		match self {
+			PBits::None => &[(0, 0)],
+			PBits::Each => &[(0, 0), (0, 1), (1, 0), (1, 1)],
+			PBits::Shared => &[(0, 0), (1, 1)],
+		}
+	}
+
+	fn bits(self) -> usize {
+		usize::from(self != PBits::None)
+	}
+}
+
+struct Fit {
+	/// Quantized endpoints, before adding p-bits.
+	q: [Rgba; 2],
+	p: [u8; 2],
+	idx: [u8; 16],
+	err: u32,
+}
+
+impl Fit {
+	/// Makes sure the anchor index has its top bit unset, as required by the format.
+	fn fix_anchor(&mut self, anchor: usize, ib: usize, n: usize) {
+		if self.idx[anchor] >> (ib - 1) != 0 {
+			self.q.swap(0, 1);
+			self.p.swap(0, 1);
+			let max = (1 << ib) - 1;
+			for i in &mut self.idx[..n] {
+				*i = max - *i;
+			}
+		}
+	}
+}
+
+fn encode_with(px: &[Rgba; 16], m: usize, partition: usize, rotation: usize, isel: usize, refine: usize) -> (u32, u128) {
+	let mode = &MODES[m];
+	let mut px = *px;
+	if rotation != 0 {
+		for p in &mut px {
+			p.swap(3, rotation - 1);
+		}
+	}
+
+	// Index bits for color and, if separate, alpha.
+	let (cib, aib) = match (mode.ib2, isel) {
+		(0, _) => (mode.ib, 0),
+		(_, 0) => (mode.ib, mode.ib2),
+		(_, _) => (mode.ib2, mode.ib),
+	};
+	let cchans = if mode.ib2 == 0 && mode.ab > 0 { 0..4 } else { 0..3 };
+	let pbits = match (mode.epb, mode.spb) {
+		(0, 0) => PBits::None,
+		(_, 0) => PBits::Each,
+		(_, _) => PBits::Shared,
+	};
+	let bits = [mode.cb, mode.cb, mode.cb, mode.ab];
+
+	let mut q = [[Rgba::default(); 2]; 3];
+	let mut p = [[0; 2]; 3];
+	let mut cidx = [0; 16];
+	let mut aidx = [0; 16];
+	let mut err = 0;
+	for s in 0..mode.ns {
+		let mut sub = [Rgba::default(); 16];
+		let mut pos = [0; 16];
+		let mut n = 0;
+		let mut anchor = 0;
+		for (i, &c) in px.iter().enumerate() {
+			let (si, is_anchor) = subset(mode.ns, partition, i);
+			if si == s {
+				if is_anchor {
+					anchor = n;
+				}
+				sub[n] = c;
+				pos[n] = i;
+				n += 1;
+			}
+		}
+		let sub = &sub[..n];
+
+		let mut f = fit(sub, cchans.clone(), bits, pbits, cib, refine);
+		f.fix_anchor(anchor, cib, n);
+		for c in cchans.clone() {
+			q[s][0][c] = f.q[0][c];
+			q[s][1][c] = f.q[1][c];
+		}
+		p[s] = f.p;
+		for (j, &i) in pos[..n].iter().enumerate() {
+			cidx[i] = f.idx[j];
+		}
+		err += f.err;
+
+		if mode.ib2 != 0 {
+			let mut f = fit(sub, 3..4, bits, PBits::None, aib, refine);
+			f.fix_anchor(anchor, aib, n);
+			q[s][0][3] = f.q[0][3];
+			q[s][1][3] = f.q[1][3];
+			aidx = f.idx;
+			err += f.err;
+		} else if mode.ab == 0 {
+			// The decoder will make these opaque.
+			err += sub.iter().map(|c| sq(255 - c[3] as i32)).sum::<u32>();
+		}
+	}
+
+	let (primary, secondary) = if isel == 0 { (&cidx, &aidx) } else { (&aidx, &cidx) };
+
+	let mut b = Bits { v: 0, n: 0 };
+	b.put(1 << m, m + 1);
+	b.put(partition, mode.pb);
+	b.put(rotation, mode.rb);
+	b.put(isel, mode.isb);
+	for c in 0..4 {
+		for e in &q[..mode.ns] {
+			b.put(e[0][c] as usize, bits[c]);
+			b.put(e[1][c] as usize, bits[c]);
+		}
+	}
+	if mode.epb != 0 {
+		for e in &p[..mode.ns] {
+			b.put(e[0] as usize, 1);
+			b.put(e[1] as usize, 1);
+		}
+	}
+	if mode.spb != 0 {
+		for e in &p[..mode.ns] {
+			b.put(e[0] as usize, 1);
+		}
+	}
+	for (i, &v) in primary.iter().enumerate() {
+		let (_, is_anchor) = subset(mode.ns, partition, i);
+		b.put(v as usize, mode.ib - usize::from(is_anchor));
+	}
+	if mode.ib2 != 0 {
+		for (i, &v) in secondary.iter().enumerate() {
+			b.put(v as usize, mode.ib2 - usize::from(i == 0));
+		}
+	}
+	debug_assert_eq!(b.n, 128);
+
+	(err, b.v)
+}
+
+struct Bits {
+	v: u128,
+	n: usize,
+}
+
+impl Bits {
+	fn put(&mut self, v: usize, n: usize) {
+		debug_assert!(v < 1 << n || (v == 0 && n == 0));
+		self.v |= (v as u128) << self.n;
+		self.n += n;
+	}
+}
+
+fn subset(ns: usize, partition: usize, i: usize) -> (usize, bool) {
+	match ns {
+		1 => subset_index::<1>(partition, i % 4, i / 4),
+		2 => subset_index::<2>(partition, i % 4, i / 4),
+		3 => subset_index::<3>(partition, i % 4, i / 4),
+		_ => unreachable!(),
+	}
+}
+
+fn interp(e0: u8, e1: u8, i: usize, ib: usize) -> u8 {
+	match ib {
+		2 => interpolate::<2>(e0, e1, i),
+		3 => interpolate::<3>(e0, e1, i),
+		4 => interpolate::<4>(e0, e1, i),
+		_ => unreachable!(),
+	}
+}
+
+fn fit(sub: &[Rgba], chans: Range<usize>, bits: [usize; 4], pbits: PBits, ib: usize, refine: usize) -> Fit {
+	let (mut e0, mut e1) = principal_endpoints(sub, chans.clone());
+	let mut best = Fit { q: [Rgba::default(); 2], p: [0; 2], idx: [0; 16], err: u32::MAX };
+	for round in 0..=refine {
+		for &(p0, p1) in pbits.combos() {
+			let (q0, v0) = quantize(&e0, chans.clone(), bits, p0, pbits.bits());
+			let (q1, v1) = quantize(&e1, chans.clone(), bits, p1, pbits.bits());
+			let (idx, err) = assign(sub, chans.clone(), v0, v1, ib);
+			if err < best.err {
+				best = Fit { q: [q0, q1], p: [p0, p1], idx, err };
+			}
+		}
+		if round == refine || best.err == 0 {
+			break
+		}
+		match least_squares(sub, chans.clone(), &best.idx, ib) {
+			Some((a, b)) => (e0, e1) = (a, b),
+			None => break,
+		}
+	}
+	best
+}
+
+/// Quantizes an endpoint with the given p-bit, returning both the stored and the decoded value.
+fn quantize(e: &[f32; 4], chans: Range<usize>, bits: [usize; 4], p: u8, np: usize) -> (Rgba, Rgba) {
+	let mut q = Rgba::default();
+	let mut v = Rgba::default();
+	for c in chans {
+		let b = bits[c];
+		let t = b + np;
+		let max = (1 << b) - 1;
+		let est = (e[c] * ((1 << t) - 1) as f32 / 255. - p as f32) / (1 << np) as f32;
+		let est = round(est).clamp(0, max);
+		let mut best = (u32::MAX, 0, 0);
+		for qi in (est - 1).max(0)..=(est + 1).min(max) {
+			let x = expand(qi as u8, p, b, np);
+			let d = abs(x as f32 - e[c]) as u32;
+			if d < best.0 {
+				best = (d, qi as u8, x);
+			}
+		}
+		q[c] = best.1;
+		v[c] = best.2;
+	}
+	(q, v)
+}
+
+// Same as in decode_with_mode.
+fn expand(q: u8, p: u8, b: usize, np: usize) -> u8 {
+	let t = b + np;
+	let mut e = (q << np) | p;
+	if 0 < t && t < 8 {
+		e <<= 8 - t;
+		e |= e >> t;
+	}
+	e
+}
+
+fn assign(sub: &[Rgba], chans: Range<usize>, v0: Rgba, v1: Rgba, ib: usize) -> ([u8; 16], u32) {
+	let mut pal = [Rgba::default(); 16];
+	for (i, c) in pal[..1 << ib].iter_mut().enumerate() {
+		for k in chans.clone() {
+			c[k] = interp(v0[k], v1[k], i, ib);
+		}
+	}
+	let mut idx = [0; 16];
+	let mut total = 0;
+	for (j, px) in sub.iter().enumerate() {
+		let mut best = (u32::MAX, 0);
+		for (i, c) in pal[..1 << ib].iter().enumerate() {
+			let d = chans.clone().map(|k| sq(c[k] as i32 - px[k] as i32)).sum::<u32>();
+			if d < best.0 {
+				best = (d, i as u8);
+			}
+		}
+		idx[j] = best.1;
+		total += best.0;
+	}
+	(idx, total)
+}
+
+/// Finds the endpoints that best reproduce the colors with the given indices.
+fn least_squares(sub: &[Rgba], chans: Range<usize>, idx: &[u8; 16], ib: usize) -> Option<([f32; 4], [f32; 4])> {
+	let weights = idx.iter().map(|&i| interp(0, 64, i as usize, ib) as f32 / 64.);
+	least_squares_weights(sub, chans, weights)
+}
+
+/// Finds the endpoints that best reproduce the colors, given how far along the line each one
+/// should be.
+pub(crate) fn least_squares_weights(sub: &[Rgba], chans: Range<usize>, weights: impl Iterator<Item=f32>) -> Option<([f32; 4], [f32; 4])> {
+	let (mut a, mut b, mut c) = (0., 0., 0.);
+	let mut r0 = [0.; 4];
+	let mut r1 = [0.; 4];
+	for (px, w) in sub.iter().zip(weights) {
+		a += (1. - w) * (1. - w);
+		b += (1. - w) * w;
+		c += w * w;
+		for k in chans.clone() {
+			r0[k] += (1. - w) * px[k] as f32;
+			r1[k] += w * px[k] as f32;
+		}
+	}
+	let det = a * c - b * b;
+	if abs(det) < 1e-6 {
+		return None
+	}
+	let mut e0 = [0.; 4];
+	let mut e1 = [0.; 4];
+	for k in chans {
+		e0[k] = ((c * r0[k] - b * r1[k]) / det).clamp(0., 255.);
+		e1[k] = ((a * r1[k] - b * r0[k]) / det).clamp(0., 255.);
+	}
+	Some((e0, e1))
+}
+
+/// Mean and scatter matrix of the colors.
+fn stats(sub: &[Rgba], chans: Range<usize>) -> ([f32; 4], [[f32; 4]; 4]) {
+	let mut mean = [0.; 4];
+	for px in sub {
+		for k in chans.clone() {
+			mean[k] += px[k] as f32;
+		}
+	}
+	for k in chans.clone() {
+		mean[k] /= sub.len().max(1) as f32;
+	}
+	let mut cov = [[0.; 4]; 4];
+	for px in sub {
+		for i in chans.clone() {
+			for j in chans.clone() {
+				cov[i][j] += (px[i] as f32 - mean[i]) * (px[j] as f32 - mean[j]);
+			}
+		}
+	}
+	(mean, cov)
+}
+
+/// Direction of greatest variance, by power iteration. Not normalized.
+fn principal_axis(cov: &[[f32; 4]; 4], chans: Range<usize>) -> [f32; 4] {
+	let Some(start) = chans.clone().max_by(|&a, &b| cov[a][a].total_cmp(&cov[b][b])) else {
+		return [0.; 4]
+	};
+	let mut v = cov[start];
+	for _ in 0..8 {
+		let mut w = [0.; 4];
+		for i in chans.clone() {
+			for j in chans.clone() {
+				w[i] += cov[i][j] * v[j];
+			}
+		}
+		let m = w.iter().fold(0., |a: f32, b| a.max(abs(*b)));
+		if m == 0. {
+			return [0.; 4]
+		}
+		v = w.map(|a| a / m);
+	}
+	v
+}
+
+fn dot(a: &[f32; 4], b: &[f32; 4]) -> f32 {
+	a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+pub(crate) fn principal_endpoints(sub: &[Rgba], chans: Range<usize>) -> ([f32; 4], [f32; 4]) {
+	let (mean, cov) = stats(sub, chans.clone());
+	let axis = principal_axis(&cov, chans.clone());
+	let len = dot(&axis, &axis);
+	if len == 0. {
+		return (mean, mean)
+	}
+	let (mut lo, mut hi) = (f32::INFINITY, f32::NEG_INFINITY);
+	for px in sub {
+		let mut d = [0.; 4];
+		for k in chans.clone() {
+			d[k] = px[k] as f32 - mean[k];
+		}
+		let t = dot(&d, &axis) / len;
+		lo = lo.min(t);
+		hi = hi.max(t);
+	}
+	let mut e0 = mean;
+	let mut e1 = mean;
+	for k in chans {
+		e0[k] = (mean[k] + lo * axis[k]).clamp(0., 255.);
+		e1[k] = (mean[k] + hi * axis[k]).clamp(0., 255.);
+	}
+	(e0, e1)
+}
+
+/// How far the subsets are from lying on lines, as an estimate of how well the partition will do.
+fn partition_error(px: &[Rgba; 16], mode: &Mode, partition: usize) -> f32 {
+	let chans = if mode.ab > 0 { 0..4 } else { 0..3 };
+	let mut total = 0.;
+	for s in 0..mode.ns {
+		let mut sub = [Rgba::default(); 16];
+		let mut n = 0;
+		for (i, &c) in px.iter().enumerate() {
+			if subset(mode.ns, partition, i).0 == s {
+				sub[n] = c;
+				n += 1;
+			}
+		}
+		let (_, cov) = stats(&sub[..n], chans.clone());
+		let trace = chans.clone().map(|k| cov[k][k]).sum::<f32>();
+		let axis = principal_axis(&cov, chans.clone());
+		let len = dot(&axis, &axis);
+		let mut along = 0.;
+		if len != 0. {
+			for i in chans.clone() {
+				for j in chans.clone() {
+					along += axis[i] * cov[i][j] * axis[j];
+				}
+			}
+			along /= len;
+		}
+		total += trace - along;
+	}
+	total
+}
+
+pub(crate) fn sq(x: i32) -> u32 {
+	(x * x) as u32
+}
+
+// These are not available in core.
+fn abs(x: f32) -> f32 {
+	if x < 0. { -x } else { x }
+}
+
+pub(crate) fn round(x: f32) -> i32 {
+	if x < 0. { (x - 0.5) as i32 } else { (x + 0.5) as i32 }
+}
+
+#[test]
+fn roundtrip() {
+	let error = |a: &[Rgba; 16], b: &[[Rgba; 4]; 4]| -> u32 {
+		a.iter().zip(flatten(b).iter())
+			.map(|(a, b)| (0..4).map(|k| sq(a[k] as i32 - b[k] as i32)).sum::<u32>())
+			.sum()
+	};
+
+	let mut seed = 1u32;
+	let mut rand = || {
+		seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+		(seed >> 16) as u8
+	};
+	let mut blocks = [[[Rgba::default(); 4]; 4]; 4];
+	for (i, b) in blocks.iter_mut().enumerate() {
+		for (y, row) in b.iter_mut().enumerate() {
+			for (x, px) in row.iter_mut().enumerate() {
+				*px = match i {
+					0 => [10, 200, 30, 255],
+					1 => [x as u8 * 60, y as u8 * 60, 128, 255],
+					2 => if x < 2 { [255, 0, 0, 40 * y as u8] } else { [0, 0, 255, 255] },
+					_ => [rand(), rand(), rand(), rand()],
+				};
+			}
+		}
+	}
+
+	for block in &blocks {
+		let px = flatten(block);
+		for m in 0..8 {
+			let (err, bits) = encode_mode(&px, m, &Quality::Slow.settings());
+			assert_eq!(bits.trailing_zeros() as usize, m);
+			assert_eq!(error(&px, &crate::decode(bits)), err, "mode {m}");
+		}
+		let errs = [Quality::Fast, Quality::Normal, Quality::Slow]
+			.map(|q| error(&px, &crate::decode(encode(block, q))));
+		assert!(errs[0] >= errs[1] && errs[1] >= errs[2], "{errs:?}");
+	}
+
+	assert_eq!(crate::decode(encode(&blocks[0], Quality::Normal)), blocks[0]);
+}
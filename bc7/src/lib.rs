@@ -3,3 +3,11 @@
 mod decode;
 pub use decode::Rgba;
 pub use decode::decode;
+
+mod encode;
+pub use encode::Quality;
+pub use encode::encode;
+
+mod bcn;
+pub use bcn::{decode_bc1, decode_bc2, decode_bc3, decode_bc4, decode_bc5};
+pub use bcn::{encode_bc1, encode_bc2, encode_bc3, encode_bc4, encode_bc5};
@@ -5,7 +5,7 @@ use std::path::{PathBuf, Path};
 use calmare::parse::diag::Level;
 use clap::{Parser, ValueHint};
 use themelios::lookup::Lookup;
-use themelios::types::Game;
+use themelios::types::{FileId, Game};
 use themelios::scena::ed6::Scena as ED6Scena;
 use themelios::scena::ed7::Scena as ED7Scena;
 
@@ -36,6 +36,20 @@ struct Cli {
 	#[clap(long, short, hide_possible_values = true)]
 	game: Option<CliGame>,
 
+	/// The game's data directory, to annotate ids with the names of what they refer to.
+	///
+	/// This reads the name, town, quest, item, magic and BGM tables, from `02` or `22` for ED6, or
+	/// `text` for ED7. In FC, battles are annotated with their monsters, from `t_btlset._dt` and
+	/// the `ms*._dt` files. Has no effect for compilation.
+	#[clap(long, value_hint = ValueHint::DirPath)]
+	data: Option<PathBuf>,
+
+	/// Warn about dialogue that does not fit in the text box.
+	///
+	/// Has no effect for decompilation.
+	#[clap(long)]
+	check_width: bool,
+
 	/// The file to process.
 	///
 	/// Can be `-` to read from stdin.
@@ -110,7 +124,13 @@ fn main_inner() -> eyre::Result<()> {
 
 	if let Some(src) = src {
 		let src = src?;
-		let (val, diags) = calmare::parse(src, lookup);
+		let dir = (cli.file.as_os_str() != "-").then(|| cli.file.parent()).flatten();
+		let includes = |game, file| include_functions(dir?, game, lookup, file);
+		let (val, mut diags) = calmare::parse_with_includes(src, lookup, &includes);
+		if let (true, Some((game, _))) = (cli.check_width, &val) {
+			let metrics = themelios::text::layout::Metrics::for_game(*game);
+			diags.extend(calmare::parse::lint::text_width(src, &metrics));
+		}
 		let filename = if cli.file.as_os_str() == "-" {
 			"<stdin>".into()
 		} else {
@@ -143,7 +163,7 @@ fn main_inner() -> eyre::Result<()> {
 			windows_wait();
 		}
 	} else {
-		let src = write_scena(cli.game, &buf, lookup)?;
+		let src = write_scena(cli.game, cli.data.as_deref(), &buf, lookup)?;
 		get_output(cli.output.as_deref(), &cli.file, "clm")?
 			.write_all(src.as_bytes())?;
 	}
@@ -151,7 +171,25 @@ fn main_inner() -> eyre::Result<()> {
 	Ok(())
 }
 
-fn write_scena(game: Option<CliGame>, buf: &[u8], lookup: Option<&dyn Lookup>) -> eyre::Result<String> {
+/// Counts the functions in an included file, if its compiled form is next to the file being
+/// compiled, so that calls into it can be checked.
+fn include_functions(dir: &Path, game: Game, lookup: Option<&dyn Lookup>, file: FileId) -> Option<usize> {
+	let name = lookup.unwrap_or_else(|| themelios::lookup::default_for(game)).name(file.0)?;
+	let name = name.rsplit(['/', '\\']).next()?;
+	let data = std::fs::read(dir.join(name)).ok()?;
+	if game.is_ed7() {
+		Some(ED7Scena::read(game, &data).ok()?.functions.len())
+	} else {
+		Some(ED6Scena::read(game, &data).ok()?.functions.len())
+	}
+}
+
+fn write_scena(game: Option<CliGame>, data: Option<&Path>, buf: &[u8], lookup: Option<&dyn Lookup>) -> eyre::Result<String> {
+	let annotations = |game| -> eyre::Result<_> {
+		data.map(|dir| calmare::Annotations::load(game, dir))
+			.transpose()
+			.map_err(|e| eyre::eyre!(e))
+	};
 	match game {
 		Some(game) => {
 			let game = cli_game(game);
@@ -160,7 +198,10 @@ fn write_scena(game: Option<CliGame>, buf: &[u8], lookup: Option<&dyn Lookup>) -
 			} else {
 				calmare::Content::ED6Scena(ED6Scena::read(game, buf)?)
 			};
-			Ok(calmare::to_string(game, &c, lookup))
+			match annotations(game)? {
+				Some(a) => Ok(calmare::to_string_annotated(game, &c, lookup, &a)),
+				None => Ok(calmare::to_string(game, &c, lookup)),
+			}
 		},
 		None => {
 			for game in [
@@ -168,16 +209,24 @@ fn write_scena(game: Option<CliGame>, buf: &[u8], lookup: Option<&dyn Lookup>) -
 				Game::FcEvo, Game::ScEvo, Game::TcEvo, Game::ZeroEvo, Game::AoEvo, // Evo
 				Game::Zero, Game::Ao, // Geofront
 			] {
-				let mut ctx = calmare::Context::new(game, lookup);
-				if game.is_ed7() {
+				let c = if game.is_ed7() {
 					let Ok(scena) = ED7Scena::read(game, buf) else { continue };
-					calmare::ed7::write(&mut ctx, &scena);
+					calmare::Content::ED7Scena(scena)
 				} else {
 					let Ok(scena) = ED6Scena::read(game, buf) else { continue };
-					calmare::ed6::write(&mut ctx, &scena);
+					calmare::Content::ED6Scena(scena)
+				};
+				let mut ctx = calmare::Context::new(game, lookup);
+				match &c {
+					calmare::Content::ED6Scena(scena) => calmare::ed6::write(&mut ctx, scena),
+					calmare::Content::ED7Scena(scena) => calmare::ed7::write(&mut ctx, scena),
 				}
 				if !ctx.has_warn {
-					return Ok(ctx.finish());
+					// Annotations are only comments, so they don't affect the guess.
+					return match annotations(game)? {
+						Some(a) => Ok(calmare::to_string_annotated(game, &c, lookup, &a)),
+						None => Ok(ctx.finish()),
+					};
 				}
 			}
 			eyre::bail!("could not parse script; specify --game for more details")
@@ -0,0 +1,194 @@
+//! Names of the things scripts refer to by id, taken from the game's tables.
+//!
+//! When given to a [`Context`](crate::Context), these are written as comments at the end of the
+//! line, like `bgm[12] // "ed6012"`. Since they are only comments, they have no effect on
+//! recompilation.
+
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+use std::path::Path;
+
+use themelios::types::{BaseGame, FileId, Game};
+use themelios::tables::{name, bgm, town, quest, item, magic, btlset, monster};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Annotations {
+	pub names: BTreeMap<u16, String>,
+	/// BGM file names. The games do not contain the tracks' titles.
+	pub bgm: BTreeMap<u16, String>,
+	pub towns: BTreeMap<u16, String>,
+	pub quests: BTreeMap<u16, String>,
+	pub items: BTreeMap<u16, String>,
+	pub magic: BTreeMap<u16, String>,
+	/// The monsters in each battle. Only in FC, since in SC and 3rd it is not clear which of the
+	/// two battle tables an id refers to, and in ED7 battles are in the scena itself.
+	pub battles: BTreeMap<u32, String>,
+}
+
+impl Annotations {
+	/// Reads whichever tables can be found in `dir`.
+	///
+	/// `dir` can be the directory holding the tables, or the one above it: the extracted archives
+	/// for ED6 (where the tables are in `02` or `22`), or the `data` directory for ED7.
+	/// Tables that are missing are skipped.
+	pub fn load(game: Game, dir: &Path) -> Result<Annotations, Error> {
+		let find = |file: &str| -> Result<Option<Vec<u8>>, Error> {
+			for sub in ["", "text", "02", "22"] {
+				let path = dir.join(sub).join(file);
+				match std::fs::read(&path) {
+					Ok(data) => return Ok(Some(data)),
+					Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+					Err(e) => return Err(format!("{}: {e}", path.display()).into()),
+				}
+			}
+			Ok(None)
+		};
+		let ctx = |file: &'static str| move |e: themelios::ReadError| -> Error {
+			format!("{file}: {e}").into()
+		};
+
+		let mut a = Annotations::default();
+
+		if let Some(data) = find("t_name._dt")? {
+			if game.is_ed7() {
+				for n in name::ED7Name::read(&data).map_err(ctx("t_name._dt"))? {
+					a.names.insert(n.id.0, n.name.0);
+				}
+			} else {
+				let (t1, t2) = name::ED6Name::read(game, &data).map_err(ctx("t_name._dt"))?;
+				for n in t1.into_iter().chain(t2) {
+					a.names.insert(n.id.0, n.name.0);
+				}
+			}
+		}
+
+		if game.is_ed7() {
+			if let Some(data) = find("t_bgm._dt")? {
+				for b in bgm::ED7Bgm::read(&data).map_err(ctx("t_bgm._dt"))? {
+					a.bgm.insert(b.id.0, format!("ed{}", b.file_num));
+				}
+			}
+		} else if let Some(data) = find("t_bgmtbl._dt")? {
+			for b in bgm::ED6Bgm::read(&data).map_err(ctx("t_bgmtbl._dt"))? {
+				a.bgm.insert(b.id.0, b.file);
+			}
+		}
+
+		if let Some(data) = find("t_town._dt")? {
+			for t in town::Town::read(game, &data).map_err(ctx("t_town._dt"))? {
+				a.towns.insert(t.id.0, t.name.0);
+			}
+		}
+
+		if let Some(data) = find("t_quest._dt")? {
+			if game.is_ed7() {
+				for q in quest::ED7Quest::read(&data).map_err(ctx("t_quest._dt"))? {
+					a.quests.insert(q.id.0, q.name.0);
+				}
+			} else {
+				for q in quest::ED6Quest::read(&data).map_err(ctx("t_quest._dt"))? {
+					a.quests.insert(q.id.0, q.name.0);
+				}
+			}
+		}
+
+		let ittxt = if game.is_ed7() { "t_ittxt._dt" } else { "t_item2._dt" };
+		if let Some(data) = find(ittxt)? {
+			for i in item::ItemText::read(&data).map_err(ctx(ittxt))? {
+				a.items.insert(i.id.0, i.name.0);
+			}
+		}
+
+		if let Some(data) = find("t_magic._dt")? {
+			for m in magic::Magic::read(game, &data).map_err(ctx("t_magic._dt"))? {
+				a.magic.insert(m.id.0, m.name.0);
+			}
+		}
+
+		if game.base() == BaseGame::Fc {
+			if let Some(data) = find("t_btlset._dt")? {
+				let table = btlset::BattleTable::read(&data).map_err(ctx("t_btlset._dt"))?;
+				let mut monsters = BTreeMap::new();
+				for (i, battle) in table.battles.iter().enumerate() {
+					let mut names = Vec::new();
+					for &ms in battle.setups.iter().flat_map(|s| &s.enemies) {
+						if ms == FileId::NONE {
+							continue
+						}
+						let name = match monsters.entry(ms) {
+							Entry::Occupied(e) => e.into_mut(),
+							Entry::Vacant(e) => e.insert(monster_name(game, dir, ms)?),
+						};
+						if !names.contains(name) {
+							names.push(name.clone());
+						}
+					}
+					a.battles.insert(i as u32, names.join(", "));
+				}
+			}
+		}
+
+		for map in [&mut a.names, &mut a.bgm, &mut a.towns, &mut a.quests, &mut a.items, &mut a.magic] {
+			map.retain(|_, v| !v.is_empty());
+		}
+		a.battles.retain(|_, v| !v.is_empty());
+
+		Ok(a)
+	}
+}
+
+/// The name of the monster in an `ms*._dt` file, which is in the archive's directory, or if that
+/// is not there, the file's name.
+fn monster_name(game: Game, dir: &Path, ms: FileId) -> Result<String, Error> {
+	let Some(file) = themelios::lookup::default_for(game).name(ms.0) else {
+		return Ok(format!("0x{:08X}", ms.0))
+	};
+	let path = dir.join(format!("{:02x}", ms.0 >> 16)).join(&file);
+	let name = match std::fs::read(&path) {
+		Ok(data) => monster::Monster::read(&data).map_err(|e| format!("{file}: {e}"))?.name.0,
+		Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+		Err(e) => return Err(format!("{}: {e}", path.display()).into()),
+	};
+	if name.is_empty() {
+		Ok(file.split('.').next().unwrap_or(&file).to_owned())
+	} else {
+		Ok(name)
+	}
+}
+
+#[test]
+fn roundtrip() {
+	let src = "calmare fc scena
+scena:
+	name \"t\" \"m\"
+	town town[0]
+	bgm bgm[0]
+	item_use fn[0,0]
+
+fn[0]:
+	BgmPlay bgm[0] 0
+	ItemAdd item[3] 1
+	TextMessage null {
+		You got {item[3]}!
+	}
+	ED6Battle battle[1] null 0 0 null
+";
+	let (Some((game, content)), _) = crate::parse(src, None) else { panic!("parse failed") };
+	let crate::Content::ED6Scena(scena) = &content else { panic!("not ED6") };
+	let data = themelios::scena::ed6::Scena::write(game, scena).unwrap();
+
+	let mut a = Annotations::default();
+	a.bgm.insert(0, "Rolent".to_owned());
+	a.items.insert(3, "Tear Balm".to_owned());
+	a.battles.insert(1, "Rabbit, Bat".to_owned());
+	let out = crate::to_string_annotated(game, &content, None, &a);
+	assert!(out.contains("// \"Rolent\""), "{out}");
+	assert!(out.contains("// \"Tear Balm\""), "{out}");
+	assert!(out.contains("// \"Rabbit, Bat\""), "{out}");
+
+	let (Some((_, content2)), _) = crate::parse(&out, None) else { panic!("reparse failed:\n{out}") };
+	let crate::Content::ED6Scena(scena2) = &content2 else { panic!("not ED6") };
+	assert_eq!(themelios::scena::ed6::Scena::write(game, scena2).unwrap(), data);
+}
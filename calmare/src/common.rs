@@ -217,6 +217,15 @@ macro nt_arg($t:ty, $fmt:literal) {
 	}
 }
 
+macro table_arg($t:ty, $fmt:literal, $table:ident) {
+	impl Val for $t {
+		fn write(&self, f: &mut Context) {
+			write!(f, $fmt, self.0);
+			f.note(|a| a.$table.get(&self.0));
+		}
+	}
+}
+
 impl<T: Val> Val for Option<T> {
 	fn write(&self, f: &mut Context) {
 		if let Some(a) = self {
@@ -318,15 +327,15 @@ impl Val for QuestTask {
 	}
 }
 
-nt_arg!(NameId,   "name[{}]");
-nt_arg!(BgmId,    "bgm[{}]");
-nt_arg!(MagicId,  "magic[{}]");
-nt_arg!(QuestId,  "quest[{}]");
+table_arg!(NameId,  "name[{}]",  names);
+table_arg!(BgmId,   "bgm[{}]",   bgm);
+table_arg!(MagicId, "magic[{}]", magic);
+table_arg!(QuestId, "quest[{}]", quests);
+table_arg!(TownId,  "town[{}]",  towns);
+table_arg!(ItemId,  "item[{}]",  items);
 nt_arg!(ShopId,   "shop[{}]");
 nt_arg!(SoundId,  "sound[{}]");
-nt_arg!(TownId,   "town[{}]");
-nt_arg!(BattleId, "battle[{}]");
-nt_arg!(ItemId,   "item[{}]");
+table_arg!(BattleId, "battle[{}]", battles);
 
 nt_arg!(LocalCharId, "char[{}]");
 nt_arg!(LookPointId, "look_point[{}]");
@@ -532,49 +541,51 @@ fn expr(f: &mut Context, e: &Expr) {
 }
 
 fn text(f: &mut Context, v: &Text) {
-	for page in &v.0 {
-		let mut it = page.iter().peekable();
-		f.kw("{").line();
-		f.indent(|f| {
-			loop {
-				let Some(next) = it.next() else { break };
-				match next {
-					TextSegment::String(s) => {
-						if f.is_line() && s.starts_with(' ') {
-							write!(f, "{{}}")
+	f.text_block(|f| {
+		for page in &v.0 {
+			let mut it = page.iter().peekable();
+			f.kw("{").line();
+			f.indent(|f| {
+				loop {
+					let Some(next) = it.next() else { break };
+					match next {
+						TextSegment::String(s) => {
+							if f.is_line() && s.starts_with(' ') {
+								write!(f, "{{}}")
+							}
+							let s = s
+								.replace('\\', "\\\\")
+								.replace('{', "\\{")
+								.replace('}', "\\}");
+							write!(f, "{s}")
 						}
-						let s = s
-							.replace('\\', "\\\\")
-							.replace('{', "\\{")
-							.replace('}', "\\}");
-						write!(f, "{s}")
-					}
-					TextSegment::Line => {
-						f.line();
-					}
-					TextSegment::Wait => {
-						write!(f, "{{wait}}")
-					}
-					TextSegment::Color(n) => {
-						write!(f, "{{color {n}}}");
-					}
-					TextSegment::Item(n) => {
-						write!(f, "{{");
-						f.val(n).no_space();
-						write!(f, "}}");
-					}
-					TextSegment::Byte(n) => {
-						write!(f, "{{0x{n:02X}}}");
-						if *n == 0x0D && !matches!(it.peek(), None|Some(TextSegment::Line)) {
-							write!(f, "\\");
+						TextSegment::Line => {
 							f.line();
 						}
+						TextSegment::Wait => {
+							write!(f, "{{wait}}")
+						}
+						TextSegment::Color(n) => {
+							write!(f, "{{color {n}}}");
+						}
+						TextSegment::Item(n) => {
+							write!(f, "{{");
+							f.val(n).no_space();
+							write!(f, "}}");
+						}
+						TextSegment::Byte(n) => {
+							write!(f, "{{0x{n:02X}}}");
+							if *n == 0x0D && !matches!(it.peek(), None|Some(TextSegment::Line)) {
+								write!(f, "\\");
+								f.line();
+							}
+						}
 					}
 				}
-			}
-		});
-		f.line().kw("}");
-	}
+			});
+			f.line().kw("}");
+		}
+	});
 }
 
 pub(crate) fn game(game: Game) -> &'static str {
@@ -8,9 +8,11 @@ pub mod ed6;
 pub mod ed7;
 mod writer;
 pub mod common;
+pub mod annotate;
 
 use themelios::{types::Game, lookup::Lookup};
 pub use writer::Context;
+pub use annotate::Annotations;
 
 pub mod span;
 pub mod parse;
@@ -22,7 +24,15 @@ pub enum Content {
 }
 
 pub fn to_string(game: Game, c: &Content, lookup: Option<&dyn Lookup>) -> String {
-	let mut ctx = Context::new(game, lookup);
+	write(Context::new(game, lookup), c)
+}
+
+/// Like [`to_string`], but ids are followed by comments naming what they refer to.
+pub fn to_string_annotated(game: Game, c: &Content, lookup: Option<&dyn Lookup>, annotations: &Annotations) -> String {
+	write(Context::new(game, lookup).annotate(annotations), c)
+}
+
+fn write(mut ctx: Context, c: &Content) -> String {
 	match c {
 		Content::ED6Scena(scena) => ed6::write(&mut ctx, scena),
 		Content::ED7Scena(scena) => ed7::write(&mut ctx, scena),
@@ -31,9 +41,19 @@ pub fn to_string(game: Game, c: &Content, lookup: Option<&dyn Lookup>) -> String
 }
 
 pub fn parse(src: &str, lookup: Option<&dyn Lookup>) -> (Option<(Game, crate::Content)>, Vec<parse::Diag>) {
+	parse_inner(src, lookup, None)
+}
+
+/// Like [`parse`], but also checks calls into included files, using `includes` to find how many
+/// functions each of them has.
+pub fn parse_with_includes(src: &str, lookup: Option<&dyn Lookup>, includes: parse::lower::Includes) -> (Option<(Game, crate::Content)>, Vec<parse::Diag>) {
+	parse_inner(src, lookup, Some(includes))
+}
+
+fn parse_inner(src: &str, lookup: Option<&dyn Lookup>, includes: Option<parse::lower::Includes>) -> (Option<(Game, crate::Content)>, Vec<parse::Diag>) {
 	let (v, diag) = parse::diag::diagnose(|| {
 		let tok = parse::lex::lex(src);
-		parse::lower::parse(&tok, lookup)
+		parse::lower::parse_with_includes(&tok, lookup, includes)
 	});
 	if diag.iter().any(|a| a.is_fatal()) {
 		(None, diag)
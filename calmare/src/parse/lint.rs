@@ -0,0 +1,76 @@
+//! Checks that have no bearing on whether a file compiles.
+
+use themelios::text::layout::Metrics;
+
+use super::diag::{diagnose, Diag};
+use super::lex::{Delimited, Line, Token, TextToken};
+use crate::span::{Spanned as S, Span};
+
+/// Warns about dialogue lines that are too wide and pages with too many lines.
+pub fn text_width(src: &str, metrics: &Metrics) -> Vec<Diag> {
+	// Any errors in lexing are reported by the compiler anyway.
+	let (lines, _) = diagnose(|| super::lex::lex(src));
+	let ((), diags) = diagnose(|| text_width_lines(&lines, metrics));
+	diags
+}
+
+fn text_width_lines(lines: &[Line], m: &Metrics) {
+	for line in lines {
+		text_width_tokens(&line.head, m);
+		if let Some(body) = &line.body {
+			text_width_lines(body, m);
+		}
+	}
+}
+
+fn text_width_tokens(tokens: &[S<Token>], m: &Metrics) {
+	for S(_, t) in tokens {
+		match t {
+			Token::Brace(d) => text_width_page(d, m),
+			Token::Paren(d) | Token::Bracket(d) => text_width_tokens(&d.tokens, m),
+			_ => {}
+		}
+	}
+}
+
+fn text_width_page(d: &Delimited<TextToken>, m: &Metrics) {
+	let mut lines = vec![(None::<Span>, 0)];
+	for S(s, t) in &d.tokens {
+		match t {
+			TextToken::Text(text) => {
+				let (span, width) = lines.last_mut().unwrap();
+				*span = Some(span.map_or(*s, |a| a | *s));
+				*width += m.str_width(text);
+			}
+			TextToken::Newline => lines.push((None, 0)),
+			TextToken::Brace(_) => {}
+		}
+	}
+	while lines.len() > 1 && lines.last().unwrap().1 == 0 {
+		lines.pop();
+	}
+
+	for &(span, width) in &lines {
+		if let (Some(span), true) = (span, width > m.line_width) {
+			Diag::warn(span, format_args!("line is {width} pixels wide, only {} fit", m.line_width)).emit();
+		}
+	}
+
+	if lines.len() > m.page_lines {
+		let mut diag = Diag::warn(d.open | d.close, format_args!("page has {} lines, only {} fit", lines.len(), m.page_lines));
+		if let Some(span) = lines[m.page_lines..].iter().find_map(|a| a.0) {
+			diag = diag.note(span, "this line is outside the text box");
+		}
+		diag.emit();
+	}
+}
+
+#[test]
+fn overflow() {
+	let m = Metrics { line_width: 80, page_lines: 2, ..Metrics::for_game(themelios::types::Game::Fc) };
+	let src = "calmare fc scena\nfn[0]:\n\tTextTalk char[0] {\n\t\t#0040FShort{wait}\n\t\tA bit too long\n\t\tx\n\t}\n";
+	let diags = text_width(src, &m);
+	assert_eq!(diags.len(), 2);
+	assert_eq!(&src[diags[0].text.0.as_range()], "A bit too long");
+	assert_eq!(&src[diags[1].notes[0].0.as_range()], "x");
+}
@@ -28,11 +28,15 @@ pub enum Unit {
 	MDeg,
 }
 
+/// Number of functions in an included file, if it can be found. Used to check calls into it.
+pub type Includes<'a> = &'a dyn Fn(Game, FileId) -> Option<usize>;
+
 #[derive(Clone, Copy)]
 pub struct Context<'a> {
 	pub game: Game,
 	pub ty: FileType,
 	pub lookup: &'a dyn Lookup,
+	pub includes: Option<Includes<'a>>,
 }
 
 impl<'a> std::fmt::Debug for Context<'a> {
@@ -41,6 +45,7 @@ impl<'a> std::fmt::Debug for Context<'a> {
 			.field("game", &self.game)
 			.field("ty", &self.ty)
 			.field("lookup", &format_args!("_"))
+			.field("includes", &format_args!("_"))
 			.finish()
 	}
 }
@@ -815,6 +820,7 @@ fn parse_type(line: &Line) -> Result<(Game, FileType)> {
 		game: Game::Fc,
 		ty: FileType::Scena,
 		lookup: &themelios::lookup::NullLookup,
+		includes: None,
 	};
 	Parse::new(line, dummy_ctx).parse_with(|p| {
 		if !p.word("calmare") {
@@ -934,6 +940,11 @@ impl<K: Ord, V> Many<K, V> {
 }
 
 pub fn parse(lines: &[Line], lookup: Option<&dyn Lookup>) -> Result<(Game, crate::Content)> {
+	parse_with_includes(lines, lookup, None)
+}
+
+/// Like [`parse`], but if `includes` is given, calls into included files are checked against it.
+pub fn parse_with_includes(lines: &[Line], lookup: Option<&dyn Lookup>, includes: Option<Includes>) -> Result<(Game, crate::Content)> {
 	if lines.is_empty() {
 		Diag::error(Span::new_at(0), "no type declaration").emit();
 		return Err(Error);
@@ -944,6 +955,7 @@ pub fn parse(lines: &[Line], lookup: Option<&dyn Lookup>) -> Result<(Game, crate
 		game,
 		ty,
 		lookup: lookup.unwrap_or_else(|| themelios::lookup::default_for(game)),
+		includes,
 	};
 
 	match ty {
@@ -1,5 +1,8 @@
-use themelios::scena::code::{Code, Insn, Expr, ExprTerm, ExprOp};
+use std::cell::RefCell;
+
+use themelios::scena::code::{Code, FlatInsn, Insn, Expr, ExprTerm, ExprOp};
 use themelios::scena::decompile::{recompile, TreeInsn};
+use themelios::scena::validate::{Issue, Location, Problem};
 
 use super::*;
 use crate::span::{Spanned as S, Span};
@@ -40,6 +43,101 @@ fn chars<A, B>(items: Many<LocalCharId, NpcOrMonster<A, B>>) -> (Vec<A>, Vec<B>)
 	(npcs, monsters)
 }
 
+thread_local! {
+	/// Span of each instruction in the function being parsed, in source order.
+	static INSN_SPANS: RefCell<Vec<Span>> = RefCell::default();
+}
+
+/// Spans of everything that [`validate`](themelios::scena::validate) can complain about.
+#[derive(Debug, Clone, Default)]
+struct Spans {
+	header: Option<Span>,
+	entries: Vec<Span>,
+	npcs: Vec<Span>,
+	monsters: Vec<Span>,
+	triggers: Vec<Span>,
+	look_points: Vec<Span>,
+	/// The function head, and its instructions as recorded by [`parse_func_spans`].
+	functions: Vec<(Span, Vec<Span>)>,
+}
+
+impl Spans {
+	fn chars<A, B>(&mut self, items: &Many<LocalCharId, NpcOrMonster<A, B>>) {
+		for S(s, v) in items.0.values() {
+			match v {
+				Some(NpcOrMonster::Npc(_)) => self.npcs.push(*s),
+				Some(NpcOrMonster::Monster(_)) => self.monsters.push(*s),
+				None => {}
+			}
+		}
+	}
+
+	fn functions(&mut self, functions: &Many<FuncDefId, Code>, insns: BTreeMap<FuncDefId, Vec<Span>>) {
+		let mut insns = insns;
+		for (k, S(s, _)) in &functions.0 {
+			self.functions.push((*s, insns.remove(k).unwrap_or_default()));
+		}
+	}
+}
+
+fn report(issues: Vec<Issue>, functions: &[Code], spans: &Spans) {
+	for Issue { location, problem } in issues {
+		// parse_tree already warns about these, with better spans.
+		if let Problem::DuplicateCase(_) = problem {
+			continue
+		}
+		let span = match &location {
+			Location::Header => spans.header,
+			Location::Entry(n) => spans.entries.get(*n).copied(),
+			Location::Npc(n) => spans.npcs.get(*n).copied(),
+			Location::Monster(n) => spans.monsters.get(*n).copied(),
+			Location::Trigger(n) => spans.triggers.get(*n).copied(),
+			Location::LookPoint(n) => spans.look_points.get(*n).copied(),
+			Location::Function(n, path) => spans.functions.get(*n).map(|(head, insns)| {
+				let i = insn_index(&functions[*n], path);
+				i.and_then(|i| insns.get(i).copied()).unwrap_or(*head)
+			}),
+		};
+		// Without a span to point at, say where it is instead.
+		match span {
+			Some(span) => Diag::warn(span, problem).emit(),
+			None => Diag::warn(Span::new_at(0), Issue { location, problem }).emit(),
+		}
+	}
+}
+
+/// Turns a path from the validator into an index into [`INSN_SPANS`].
+fn insn_index(code: &Code, path: &[usize]) -> Option<usize> {
+	fn nested(i: &FlatInsn) -> Option<&Code> {
+		match i {
+			FlatInsn::Insn(Insn::Fork(_, _, c) | Insn::ForkLoop(_, _, c)) => Some(c),
+			_ => None,
+		}
+	}
+	fn count(code: &[FlatInsn]) -> usize {
+		code.iter().map(|i| match i {
+			FlatInsn::Insn(_) => 1 + nested(i).map_or(0, |c| count(c)),
+			_ => 0,
+		}).sum()
+	}
+
+	let (&i, rest) = path.split_first()?;
+	let insn = code.get(i)?;
+	let n = count(&code[..i]);
+	if rest.is_empty() {
+		matches!(insn, FlatInsn::Insn(_)).then_some(n)
+	} else {
+		Some(n + 1 + insn_index(nested(insn)?, rest)?)
+	}
+}
+
+/// Like [`parse_func`], but also returns the span of each instruction, including nested ones.
+fn parse_func_spans(p: &mut Parse) -> (Code, Vec<Span>) {
+	let prev = INSN_SPANS.take();
+	let f = parse_func(p);
+	(f, INSN_SPANS.replace(prev))
+}
+
 fn parse_func(p: &mut Parse) -> Code {
 	let tree = parse_tree(p, false, false);
 	recompile(&tree).map_err(|e| {
@@ -154,6 +252,7 @@ fn parse_tree(p: &mut Parse, can_break: bool, can_continue: bool) -> Vec<TreeIns
 					p.pos -= 1;
 				}
 				last_if = None;
+				INSN_SPANS.with(|a| a.borrow_mut().push(p.head_span()));
 				out.push(TreeInsn::Insn(parse_insn(p)));
 			}
 		}
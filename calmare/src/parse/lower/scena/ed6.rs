@@ -1,6 +1,6 @@
 use super::*;
 
-use themelios::scena::{ed6::*, code::Code};
+use themelios::scena::{ed6::*, code::Code, validate};
 
 #[derive(Debug, Clone)]
 pub struct Header {
@@ -14,13 +14,14 @@ pub struct Header {
 #[derive(Debug, Clone, Default)]
 struct ScenaBuild {
 	header: One<Header>,
-	entries: Vec<Entry>,
+	entries: Vec<S<Entry>>,
 	ch: Many<ChipId, FileId>,
 	cp: Many<ChipId, FileId>,
 	chars: Many<LocalCharId, NpcOrMonster<Npc, Monster>>,
 	triggers: Many<TriggerId, Trigger>,
 	look_points: Many<LookPointId, LookPoint>,
 	functions: Many<FuncDefId, Code>,
+	insn_spans: BTreeMap<FuncDefId, Vec<Span>>,
 }
 
 pub fn parse(lines: &[Line], ctx: &Context) -> Result<Scena> {
@@ -33,6 +34,16 @@ pub fn parse(lines: &[Line], ctx: &Context) -> Result<Scena> {
 		Diag::error(Span::new_at(0), "missing 'scena' block").emit();
 	}
 
+	let mut spans = Spans {
+		header: scena.header.0.as_ref().map(|a| a.0),
+		entries: scena.entries.iter().map(|a| a.0).collect(),
+		triggers: scena.triggers.0.values().map(|a| a.0).collect(),
+		look_points: scena.look_points.0.values().map(|a| a.0).collect(),
+		..Spans::default()
+	};
+	spans.chars(&scena.chars);
+	spans.functions(&scena.functions, scena.insn_spans);
+
 	let ch = scena.ch.get(|a| a.0 as usize);
 	let cp = scena.cp.get(|a| a.0 as usize);
 	let (npcs, monsters) = chars(scena.chars);
@@ -42,7 +53,7 @@ pub fn parse(lines: &[Line], ctx: &Context) -> Result<Scena> {
 
 	let h = scena.header.get().ok_or(Error)?;
 
-	Ok(Scena {
+	let scena = Scena {
 		path: h.name.0,
 		map: h.name.1,
 		town: h.town,
@@ -55,9 +66,11 @@ pub fn parse(lines: &[Line], ctx: &Context) -> Result<Scena> {
 		monsters,
 		triggers,
 		look_points,
-		entries: scena.entries,
+		entries: scena.entries.into_iter().map(|a| a.1).collect(),
 		functions,
-	})
+	};
+	report(validate::ed6(&scena, &|file| ctx.includes.and_then(|f| f(ctx.game, file))), &scena.functions, &spans);
+	Ok(scena)
 }
 
 fn parse_line(scena: &mut ScenaBuild, p: &mut Parse) -> Result<()> {
@@ -73,8 +86,9 @@ fn parse_line(scena: &mut ScenaBuild, p: &mut Parse) -> Result<()> {
 		"fn" => {
 			let S(s, n) = Val::parse(p)?;
 			scena.functions.mark(p.tokens[0].0 | s, n);
-			let f = parse_func(p);
+			let (f, spans) = parse_func_spans(p);
 			scena.functions.insert(n, f);
+			scena.insn_spans.insert(n, spans);
 		}
 		"scena" => {
 			scena.header.mark(p.head_span());
@@ -98,16 +112,17 @@ fn parse_line(scena: &mut ScenaBuild, p: &mut Parse) -> Result<()> {
 			scena.header.set(Header { name, town, bgm, item_use, scp });
 		}
 		"entry" => {
+			let s = p.tokens[0].0;
 			parse_data!(p => {
 				pos, chr, angle,
 				cam_from, cam_at, cam_zoom, cam_pers, cam_deg, cam_limit, north,
 				flags, town, init, reinit,
 			});
-			scena.entries.push(Entry {
+			scena.entries.push(S(s, Entry {
 				pos, chr, angle,
 				cam_from, cam_at, cam_zoom, cam_pers, cam_deg, cam_limit, north,
 				flags, town, init, reinit,
-			});
+			}));
 		}
 		"chip" => {
 			let S(s, n) = Val::parse(p)?;
@@ -1,6 +1,6 @@
 use super::*;
 
-use themelios::scena::{ed7::*, code::Code};
+use themelios::scena::{ed7::*, code::Code, validate};
 
 newtype!(SepithId, "sepith");
 newtype!(AtRollId, "at_roll");
@@ -33,6 +33,7 @@ struct ScenaBuild {
 	placements: Many<PlacementId, [(u8, u8, Angle); 8]>,
 	battles: Many<BattleId, Battle>,
 	functions: Many<FuncDefId, Code>,
+	insn_spans: BTreeMap<FuncDefId, Vec<Span>>,
 }
 
 pub fn parse(lines: &[Line], ctx: &Context) -> Result<Scena> {
@@ -45,6 +46,16 @@ pub fn parse(lines: &[Line], ctx: &Context) -> Result<Scena> {
 		Diag::error(Span::new_at(0), "missing 'scena' block").emit();
 	}
 
+	let mut spans = Spans {
+		header: scena.header.0.as_ref().map(|a| a.0),
+		entries: scena.entry.0.iter().map(|a| a.0).collect(),
+		triggers: scena.triggers.0.values().map(|a| a.0).collect(),
+		look_points: scena.look_points.0.values().map(|a| a.0).collect(),
+		..Spans::default()
+	};
+	spans.chars(&scena.chars);
+	spans.functions(&scena.functions, scena.insn_spans);
+
 	let chips = scena.chips.get(|a| a.0 as usize);
 	let (npcs, monsters) = chars(scena.chars);
 	let labels = scena.labels.get(|a| a.0 as usize);
@@ -68,7 +79,7 @@ pub fn parse(lines: &[Line], ctx: &Context) -> Result<Scena> {
 
 	let h = scena.header.get().ok_or(Error)?;
 
-	Ok(Scena {
+	let scena = Scena {
 		name1: h.name.0,
 		name2: h.name.1,
 		filename: h.name.2,
@@ -92,7 +103,9 @@ pub fn parse(lines: &[Line], ctx: &Context) -> Result<Scena> {
 		battles,
 		unk2: h.unk.0,
 		unk3: h.unk.1,
-	})
+	};
+	report(validate::ed7(&scena, &|file| ctx.includes.and_then(|f| f(ctx.game, file))), &scena.functions, &spans);
+	Ok(scena)
 }
 
 fn parse_line(scena: &mut ScenaBuild, p: &mut Parse) -> Result<()> {
@@ -108,8 +121,9 @@ fn parse_line(scena: &mut ScenaBuild, p: &mut Parse) -> Result<()> {
 		"fn" => {
 			let S(s, n) = Val::parse(p)?;
 			scena.functions.mark(p.tokens[0].0 | s, n);
-			let f = parse_func(p);
+			let (f, spans) = parse_func_spans(p);
 			scena.functions.insert(n, f);
+			scena.insn_spans.insert(n, spans);
 		}
 		"scena" => {
 			scena.header.mark(p.head_span());
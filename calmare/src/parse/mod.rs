@@ -1,6 +1,7 @@
 pub mod diag;
 pub mod lex;
 pub mod lower;
+pub mod lint;
 
 pub use diag::Diag;
 use themelios::types::Game;
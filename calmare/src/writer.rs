@@ -1,5 +1,6 @@
 use themelios::types::Game;
 use themelios::lookup::Lookup;
+use crate::annotate::Annotations;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum Space {
@@ -15,6 +16,9 @@ pub struct Context<'a> {
 	indent: usize,
 	space: Space,
 	pub lookup: &'a dyn Lookup,
+	pub annotations: Option<&'a Annotations>,
+	notes: Vec<String>,
+	in_text: bool,
 	out: String,
 }
 
@@ -27,6 +31,9 @@ impl<'a> Context<'a> {
 			indent: 0,
 			space: Space::None,
 			lookup: lookup.unwrap_or_else(|| themelios::lookup::default_for(game)),
+			annotations: None,
+			notes: Vec::new(),
+			in_text: false,
 			out: String::new(),
 		}
 	}
@@ -36,6 +43,11 @@ impl<'a> Context<'a> {
 		self
 	}
 
+	pub fn annotate(mut self, annotations: &'a Annotations) -> Self {
+		self.annotations = Some(annotations);
+		self
+	}
+
 	pub fn warn(&mut self) {
 		self.has_warn = true;
 	}
@@ -91,6 +103,11 @@ impl<'a> Context<'a> {
 	}
 
 	pub fn line(&mut self) -> &mut Self {
+		if !self.in_text && !self.notes.is_empty() {
+			self.out.push_str(" // ");
+			self.out.push_str(&self.notes.join(", "));
+			self.notes.clear();
+		}
 		self.out.push('\n');
 		self.space = Space::Newline;
 		self
@@ -105,6 +122,24 @@ impl<'a> Context<'a> {
 		std::fmt::Write::write_fmt(&mut self.out, args).unwrap();
 	}
 
+	/// Adds a comment with the name of the thing an id refers to, if known, to the end of the line.
+	pub fn note(&mut self, get: impl FnOnce(&Annotations) -> Option<&String>) {
+		if let Some(name) = self.annotations.and_then(get) {
+			let name = format!("{name:?}");
+			if !self.notes.contains(&name) {
+				self.notes.push(name);
+			}
+		}
+	}
+
+	/// Holds back notes until after `f`, for text blocks where comments are not allowed.
+	pub fn text_block<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+		let prev = std::mem::replace(&mut self.in_text, true);
+		let v = f(self);
+		self.in_text = prev;
+		v
+	}
+
 	pub fn indent<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
 		self.indent += 1;
 		let v = f(self);
@@ -0,0 +1,479 @@
+//! The command line interface shared by `cradle`, `cradle-ed6`, and `cradle-ed7`. The latter two
+//! only differ in which game's formats ordinary files are converted to by default.
+
+use std::path::{PathBuf, Path};
+
+use clap::{Parser, ValueHint};
+use cradle::{ch, dds, mip, quant};
+use cradle::convert::{self, Format};
+use anyhow::{Result, Context};
+
+mod scena;
+
+#[derive(Debug, Clone, Parser)]
+struct Cli {
+	/// Where to place the output.
+	///
+	/// If unspecified, output will be placed next to the input file. When converting several files
+	/// or directories, this is instead a directory that the output is placed in, in the same
+	/// structure as the input.
+	#[clap(long, short, value_hint = ValueHint::AnyPath)]
+	output: Option<PathBuf>,
+
+	/// The files to be processed.
+	///
+	/// Should be ._ch, ._cp, ._ds, .itp, .itc, .png, .dds, .json, or .atlas.json files, or
+	/// directories containing a chip.json. Other directories are searched for such files.
+	///
+	/// An ED7 .bin scena file is instead visualized as previews of the animations of its monsters.
+	#[clap(required = true, value_hint = ValueHint::AnyPath)]
+	files: Vec<PathBuf>,
+
+	/// What to convert to.
+	///
+	/// By default, the games' formats are converted to ordinary ones, and ordinary ones to the
+	/// format of the game given with --game, or the one suggested by --output.
+	#[clap(long, value_enum)]
+	to: Option<Target>,
+
+	/// Which game's formats to convert ordinary files to.
+	#[clap(long, value_enum)]
+	game: Option<Family>,
+
+	/// The game's data directory, where chips referenced from ED7 scena files are looked up.
+	///
+	/// If unspecified, this is the parent of the directory the scena file is in.
+	#[clap(long, value_hint = ValueHint::DirPath)]
+	data: Option<PathBuf>,
+
+	#[command(flatten)]
+	mode: Mode,
+
+	/// Read ch as the specified width, rather than guessing.
+	///
+	/// Has no effect when writing.
+	#[clap(long, short)]
+	width: Option<usize>,
+
+	/// Write png files as ._ds in this format, rather than as ._ch.
+	///
+	/// If the output file ends in ._ds, this defaults to bc1 for opaque images and bc3 otherwise.
+	#[clap(long, value_enum)]
+	ds: Option<DsFormat>,
+
+	/// Generate mipmaps when writing ._ds or 32-bit itp files, using this filter.
+	#[clap(long, value_enum)]
+	mipmaps: Option<MipFilter>,
+
+	/// Extract chcp and itc files as a single sprite sheet and a .atlas.json, rather than a directory of frames.
+	///
+	/// The sheet has a row for each group of eight frames, which are usually the eight directions of a pose.
+	#[clap(long)]
+	atlas: bool,
+
+	#[command(flatten)]
+	indexed: Indexed,
+
+	/// How much effort to spend on compressing 32-bit itp files.
+	#[clap(long, value_enum, default_value = "normal")]
+	bc7: Bc7Quality,
+
+	/// When extracting 32-bit itp files with mipmaps, write each level as a separate png
+	/// (name.0.png, name.1.png, ...) instead of a dds. Such files are read back as levels when
+	/// converting name.0.png.
+	#[clap(long)]
+	split_levels: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Family {
+	/// The Liberl games: *Trails in the Sky FC*, *SC*, and *the 3rd*.
+	Ed6,
+	/// The Crossbell games: *Trails from Zero* and *to Azure*, as well as the *Evolution* versions of all five.
+	Ed7,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Target {
+	Png,
+	Dds,
+	Ch,
+	Chcp,
+	Ds,
+	Itp,
+	Itc,
+	Frames,
+	Atlas,
+}
+
+impl From<Target> for Format {
+	fn from(t: Target) -> Self {
+		match t {
+			Target::Png => Format::Png,
+			Target::Dds => Format::Dds,
+			Target::Ch => Format::Ch,
+			Target::Chcp => Format::Chcp,
+			Target::Ds => Format::Ds,
+			Target::Itp => Format::Itp,
+			Target::Itc => Format::Itc,
+			Target::Frames => Format::Frames,
+			Target::Atlas => Format::Atlas,
+		}
+	}
+}
+
+#[derive(Debug, Clone, clap::Args)]
+#[group(multiple = false)]
+struct Mode {
+	/// Read/write ch in argb1555 format, rather than guessing.
+	#[clap(long="1555", short='1')]
+	argb1555: bool,
+	/// Read/write ch in argb4444 format, rather than guessing.
+	#[clap(long="4444", short='4')]
+	argb4444: bool,
+	/// Read/write ch in argb8888 format, rather than guessing.
+	#[clap(long="8888", short='8')]
+	argb8888: bool,
+}
+
+impl Mode {
+	fn get(&self) -> Option<ch::Mode> {
+		match self {
+			Mode { argb1555: true, .. } => Some(ch::Mode::Argb1555),
+			Mode { argb4444: true, .. } => Some(ch::Mode::Argb4444),
+			Mode { argb8888: true, .. } => Some(ch::Mode::Argb8888),
+			_ => None
+		}
+	}
+}
+
+#[derive(Debug, Clone, clap::Args)]
+struct Indexed {
+	/// Reduce truecolor images to an indexed itp with at most this many colors, rather than writing a 32-bit itp.
+	#[clap(long, value_parser = clap::value_parser!(u16).range(1..=256))]
+	colors: Option<u16>,
+
+	/// Use Floyd–Steinberg dithering when reducing colors.
+	#[clap(long)]
+	dither: bool,
+
+	/// Reduce truecolor images to the palette of this itp file.
+	#[clap(long, value_hint = ValueHint::FilePath)]
+	palette: Option<PathBuf>,
+
+	/// Add new colors after the ones in --palette, up to --colors, rather than only using existing ones.
+	#[clap(long, requires = "palette")]
+	extend_palette: bool,
+
+	/// Which format to write indexed itp files in.
+	#[clap(long, value_enum, default_value = "1004")]
+	itp: ItpVersion,
+}
+
+impl Indexed {
+	fn options(&self) -> Result<Option<quant::Options>> {
+		let palette = match &self.palette {
+			Some(path) => {
+				let pal = cradle::itp::read(&std::fs::read(path)?)?.palette;
+				if self.extend_palette {
+					quant::Palette::Extend(pal)
+				} else {
+					quant::Palette::Reuse(pal)
+				}
+			}
+			None if self.colors.is_some() => quant::Palette::New,
+			None => return Ok(None),
+		};
+		Ok(Some(quant::Options {
+			colors: self.colors.map_or(256, |a| a as usize),
+			dither: self.dither,
+			palette,
+		}))
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum ItpVersion {
+	#[value(name = "1000")] V1000,
+	#[value(name = "1002")] V1002,
+	#[value(name = "1004")] V1004,
+}
+
+impl From<ItpVersion> for convert::ItpVersion {
+	fn from(v: ItpVersion) -> Self {
+		match v {
+			ItpVersion::V1000 => convert::ItpVersion::V1000,
+			ItpVersion::V1002 => convert::ItpVersion::V1002,
+			ItpVersion::V1004 => convert::ItpVersion::V1004,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum DsFormat {
+	Bc1,
+	Bc2,
+	Bc3,
+	Bc4,
+	Bc5,
+	Bc7,
+}
+
+impl From<DsFormat> for dds::Format {
+	fn from(f: DsFormat) -> Self {
+		match f {
+			DsFormat::Bc1 => dds::Format::Bc1,
+			DsFormat::Bc2 => dds::Format::Bc2,
+			DsFormat::Bc3 => dds::Format::Bc3,
+			DsFormat::Bc4 => dds::Format::Bc4,
+			DsFormat::Bc5 => dds::Format::Bc5,
+			DsFormat::Bc7 => dds::Format::Bc7,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Bc7Quality {
+	Fast,
+	Normal,
+	Slow,
+}
+
+impl From<Bc7Quality> for bc7::Quality {
+	fn from(q: Bc7Quality) -> Self {
+		match q {
+			Bc7Quality::Fast => bc7::Quality::Fast,
+			Bc7Quality::Normal => bc7::Quality::Normal,
+			Bc7Quality::Slow => bc7::Quality::Slow,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum MipFilter {
+	Box,
+	Lanczos,
+}
+
+impl From<MipFilter> for mip::Filter {
+	fn from(f: MipFilter) -> Self {
+		match f {
+			MipFilter::Box => mip::Filter::Box,
+			MipFilter::Lanczos => mip::Filter::Lanczos,
+		}
+	}
+}
+
+/// A file to convert, and where it is relative to the path it was found through.
+struct Job {
+	input: PathBuf,
+	relative: PathBuf,
+	/// Whether the file was given directly, rather than found in a directory.
+	explicit: bool,
+}
+
+pub fn main(family: Option<Family>) -> Result<()> {
+	let cli = Cli::parse();
+	let family = cli.game.or(family);
+
+	let opts = convert::Options {
+		ch_mode: cli.mode.get(),
+		ch_width: cli.width,
+		ds: cli.ds.map(Into::into),
+		mipmaps: cli.mipmaps.map(Into::into),
+		itp: cli.indexed.itp.into(),
+		quant: cli.indexed.options()?,
+		bc7: cli.bc7.into(),
+	};
+
+	let mut jobs = Vec::new();
+	for path in &cli.files {
+		if path.is_dir() && Format::of(path).is_none() {
+			find_files(path, Path::new(""), &mut jobs)?;
+		} else {
+			let relative = PathBuf::from(path.file_name().context("file has no name")?);
+			jobs.push(Job { input: path.clone(), relative, explicit: true });
+		}
+	}
+
+	// With a single file, the output path is the output file itself.
+	let single = matches!(&jobs[..], [job] if job.explicit);
+	let mut n_errors = 0;
+	for job in &jobs {
+		let output = match &cli.output {
+			Some(out) if single => Some(out.clone()),
+			Some(out) => Some(out.join(&job.relative)),
+			None => None,
+		};
+		let result = run(&cli, family, &opts, &job.input, output.as_deref(), single);
+		if single {
+			return result
+		}
+		if let Err(e) = result {
+			eprintln!("{}: {e:#}", job.input.display());
+			n_errors += 1;
+		}
+	}
+	if n_errors > 0 {
+		anyhow::bail!("{n_errors} files could not be converted");
+	}
+	Ok(())
+}
+
+fn find_files(dir: &Path, relative: &Path, jobs: &mut Vec<Job>) -> Result<()> {
+	let mut entries = std::fs::read_dir(dir)?
+		.map(|e| Ok(e?.path()))
+		.collect::<std::io::Result<Vec<_>>>()?;
+	entries.sort();
+	for path in entries {
+		let relative = relative.join(path.file_name().unwrap());
+		let format = Format::of(&path);
+		if path.is_dir() && format.is_none() {
+			find_files(&path, &relative, jobs)?;
+		} else if format.is_some() && !is_secondary(&path) {
+			jobs.push(Job { input: path, relative, explicit: false });
+		}
+	}
+	Ok(())
+}
+
+/// Files that are converted as part of another one: chcp tilesets, mipmap levels other than the
+/// first, and atlas sheets.
+fn is_secondary(path: &Path) -> bool {
+	let Some(name) = path.file_name().and_then(|a| a.to_str()) else { return false };
+	let name = name.to_lowercase();
+	if let Some(base) = name.strip_suffix("._ch") {
+		return path.with_file_name(format!("{base}p._cp")).is_file()
+	}
+	if let Some(base) = name.strip_suffix(".png") {
+		if path.with_file_name(format!("{base}.atlas.json")).is_file() {
+			return true
+		}
+		if let Some((base, n)) = base.rsplit_once('.') {
+			return n.parse::<usize>().is_ok_and(|n| n > 0) && path.with_file_name(format!("{base}.0.png")).is_file()
+		}
+	}
+	false
+}
+
+/// Converts a single file. The output is either the exact path to write to, or, when converting
+/// several files, the path of the input mirrored into the output directory.
+fn run(cli: &Cli, family: Option<Family>, opts: &convert::Options, input: &Path, output: Option<&Path>, exact: bool) -> Result<()> {
+	let name = input.file_name().and_then(|a| a.to_str()).context("file has no name")?.to_lowercase();
+	let exact_output = output.filter(|_| exact);
+	let base = output.unwrap_or(input);
+
+	if name.ends_with(".bin") {
+		let Some(datadir) = cli.data.clone().or_else(|| Some(input.parent()?.parent()?.to_path_buf())) else {
+			anyhow::bail!("could not find data directory, specify --data");
+		};
+		let outdir = exact_output.map_or_else(|| base.with_extension(""), Path::to_path_buf);
+		std::fs::create_dir_all(&outdir)?;
+		return scena::preview_animations(&std::fs::read(input)?, &datadir, &outdir)
+	}
+
+	let format = Format::of(input).context("could not infer file type")?;
+	let target = match cli.to {
+		Some(to) => to.into(),
+		None => default_target(cli, family, input, format, exact_output)?,
+	};
+	let output = exact_output.map_or_else(|| target.output_path(base), Path::to_path_buf);
+	if let Some(parent) = output.parent() {
+		std::fs::create_dir_all(parent)?;
+	}
+
+	let mut opts = opts.clone();
+	if format == Format::Ch {
+		ch_read_options(input, &mut opts)?;
+	}
+	if target == Format::Ch && opts.ch_mode.is_none() {
+		opts.ch_mode = ch_write_mode(input, &output)?;
+	}
+	convert::convert(input, format, &output, target, &opts)?;
+	Ok(())
+}
+
+fn default_target(cli: &Cli, family: Option<Family>, input: &Path, format: Format, output: Option<&Path>) -> Result<Format> {
+	// An output named like one of the games' formats is converted to that.
+	let ordinary = [Format::Png, Format::Dds, Format::Frames, Format::Atlas];
+	if let Some(f) = output.and_then(Format::of).filter(|f| !ordinary.contains(f)) {
+		return Ok(f)
+	}
+	Ok(match format {
+		Format::Ch | Format::Ds => Format::Png,
+		Format::Itp => {
+			let data = std::fs::read(input)?;
+			let mipmapped = data.starts_with(b"ITP\xFF") && cradle::itp32::read(&data)?.has_mipmaps();
+			if mipmapped && !cli.split_levels { Format::Dds } else { Format::Png }
+		}
+		Format::Chcp | Format::Itc => if cli.atlas { Format::Atlas } else { Format::Frames },
+		Format::Png | Format::Dds | Format::Frames | Format::Atlas => {
+			let image = format == Format::Png || format == Format::Dds;
+			match family {
+				_ if image && cli.ds.is_some() => Format::Ds,
+				Some(Family::Ed6) if format == Format::Png => Format::Ch,
+				Some(Family::Ed6) if format == Format::Dds => Format::Ds,
+				Some(Family::Ed6) => Format::Chcp,
+				Some(Family::Ed7) if image => Format::Itp,
+				Some(Family::Ed7) => Format::Itc,
+				None => anyhow::bail!("could not tell which game to convert for, specify --game or --to"),
+			}
+		}
+	})
+}
+
+/// Fills in the mode and width of a `._ch` file, from the known files if it is one of them, and
+/// otherwise from its content.
+fn ch_read_options(input: &Path, opts: &mut convert::Options) -> Result<()> {
+	if opts.ch_mode.is_some() && opts.ch_width.is_some() {
+		return Ok(())
+	}
+	let data = std::fs::read(input)?;
+	let name = input.file_name().and_then(|a| a.to_str()).unwrap_or_default().to_lowercase();
+	let basename = name.strip_suffix("._ch").unwrap_or(&name);
+	if opts.ch_mode.is_none() && opts.ch_width.is_none() {
+		if basename.starts_with("ch") && data.len() % (2*16*16) == 2 {
+			println!("this is a chcp; you likely want to convert the ._cp file instead");
+			return Ok(())
+		}
+		if let Some((mode, width, _)) = is_known_ch(basename).then(|| ch::guess_from_byte_size(basename, data.len())).flatten() {
+			opts.ch_mode = Some(mode);
+			opts.ch_width = Some(width);
+			return Ok(())
+		}
+	}
+
+	let guesses = ch::guess_from_content(&data).into_iter()
+		.filter(|g| opts.ch_mode.is_none_or(|m| m == g.mode) && opts.ch_width.is_none_or(|w| w == g.width))
+		.collect::<Vec<_>>();
+	let best = guesses.first().context("could not guess format")?;
+	println!("guessed {:?} {}×{}", best.mode, best.width, best.height);
+	for g in guesses.iter().skip(1).take(3) {
+		println!("  alternatively {:?} {}×{}", g.mode, g.width, g.height);
+	}
+	opts.ch_mode = Some(best.mode);
+	opts.ch_width = Some(best.width);
+	Ok(())
+}
+
+/// Which mode a known `._ch` file is in. Other files are left to be guessed from their content.
+fn ch_write_mode(input: &Path, output: &Path) -> Result<Option<ch::Mode>> {
+	let name = output.file_name().and_then(|a| a.to_str()).unwrap_or_default().to_lowercase();
+	let basename = name.strip_suffix("._ch").unwrap_or(&name);
+	if !is_known_ch(basename) || Format::of(input) != Some(Format::Png) {
+		return Ok(None)
+	}
+	let (w, h) = image::image_dimensions(input)?;
+	let mode = ch::guess_from_image_size(basename, w as usize, h as usize)
+		.with_context(|| format!("{name} is not normally {w}×{h}; give its mode with --1555, --4444 or --8888"))?;
+	Ok(Some(mode))
+}
+
+/// Whether the file is one of the games' own, according to themelios' file indexes. Only those
+/// can be trusted to follow the naming patterns in [`ch::guess_from_byte_size`].
+fn is_known_ch(basename: &str) -> bool {
+	use themelios::types::Game;
+	let name = format!("{}._ch", basename.to_lowercase());
+	[Game::Fc, Game::FcEvo, Game::Sc, Game::ScEvo, Game::Tc, Game::TcEvo].into_iter()
+		.any(|game| themelios::lookup::default_for(game).index(&name).is_some())
+}
@@ -0,0 +1,67 @@
+//! Scena files, which aren't converted as such, but can be visualized.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Result, Context};
+use image::RgbaImage;
+
+/// Writes an animated png, a gif and a sprite sheet for the standing and walking animations of
+/// each monster. The animations' frame numbers are used directly as itc frames.
+pub fn preview_animations(data: &[u8], datadir: &Path, outdir: &Path) -> Result<()> {
+	use themelios::lookup::{ED7Lookup, Lookup};
+	use themelios::scena::ed7::Scena;
+	use themelios::types::Game;
+
+	let scena = [Game::ZeroKai, Game::AoKai, Game::ZeroEvo, Game::AoEvo, Game::Zero, Game::Ao].into_iter()
+		.find_map(|game| Scena::read(game, data).ok())
+		.context("could not parse scena")?;
+
+	let mut chips = HashMap::new();
+	for (i, m) in scena.monsters.iter().enumerate() {
+		let Some(file) = scena.chips.get(m.chip.0 as usize) else {
+			println!("monster {i}: chip {} does not exist", m.chip.0);
+			continue
+		};
+		let Some(name) = ED7Lookup.name(file.0) else {
+			println!("monster {i}: chip file {:08X} is not an itc", file.0);
+			continue
+		};
+		if !chips.contains_key(&name) {
+			let data = std::fs::read(datadir.join(&name))
+				.with_context(|| format!("could not read {}", datadir.join(&name).display()))?;
+			chips.insert(name.clone(), data);
+		}
+		let itc = cradle::itc::read(&chips[&name])?;
+
+		for (kind, anim) in [("stand", m.stand_anim), ("walk", m.walk_anim)] {
+			let Some(anim) = scena.animations.get(anim.0 as usize).filter(|a| !a.frames.is_empty()) else {
+				continue
+			};
+			let frames = anim.frames.iter().map(|&f| f as usize).collect::<Vec<_>>();
+			let images = cradle::anim::render(&itc, &frames)?;
+			write_apng(&images, anim.speed.0, File::create(outdir.join(format!("monster{i}.{kind}.png")))?)?;
+			std::fs::write(outdir.join(format!("monster{i}.{kind}.gif")), cradle::gif::write(&images, anim.speed.0)?)?;
+			let sheet = cradle::util::tile(&images, images.len() as u32);
+			cradle::convert::write_png(&sheet.into(), File::create(outdir.join(format!("monster{i}.{kind}.sheet.png")))?)?;
+		}
+	}
+	Ok(())
+}
+
+fn write_apng(frames: &[RgbaImage], delay_ms: u32, mut w: impl Write) -> Result<()> {
+	let (width, height) = frames[0].dimensions();
+	let mut png = png::Encoder::new(&mut w, width, height);
+	png.set_color(png::ColorType::Rgba);
+	png.set_depth(png::BitDepth::Eight);
+	png.set_animated(frames.len() as u32, 0)?;
+	png.set_frame_delay(delay_ms.min(u16::MAX as u32) as u16, 1000)?;
+	let mut png = png.write_header()?;
+	for f in frames {
+		png.write_image_data(f)?;
+	}
+	png.finish()?;
+	Ok(())
+}
@@ -0,0 +1,72 @@
+//! Lining up itc frames, for previewing animations.
+
+use image::{imageops, RgbaImage};
+use crate::itc::Itc;
+use crate::util::*;
+
+/// Renders the given frames onto canvases of the same size, placed the way they would be in game:
+/// scaled, and with the center of each image displaced by its offset. Frames without an image are
+/// left blank.
+pub fn render(itc: &Itc, frames: &[usize]) -> Result<Vec<RgbaImage>, Error> {
+	let mut placed = Vec::with_capacity(frames.len());
+	for &n in frames {
+		let Some(img) = itc.image(n)? else {
+			placed.push(None);
+			continue
+		};
+		let frame = &itc.frames[n];
+		let w = (img.width() as f32 / frame.x_scale).round().max(1.) as u32;
+		let h = (img.height() as f32 / frame.y_scale).round().max(1.) as u32;
+		let img = if (w, h) == img.dimensions() {
+			img
+		} else {
+			imageops::resize(&img, w, h, imageops::FilterType::Nearest)
+		};
+		// Same as in cradle-ed7's conversion to png.
+		let x = (-frame.x_offset * w as f32 - w as f32 / 2.).round() as i32;
+		let y = (-frame.y_offset * h as f32 - h as f32 / 2.).round() as i32;
+		placed.push(Some((img, x, y)));
+	}
+
+	let bounds = placed.iter().flatten()
+		.map(|(img, x, y)| (*x, *y, x + img.width() as i32, y + img.height() as i32))
+		.reduce(|a, b| (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3)));
+	let Some((x0, y0, x1, y1)) = bounds else {
+		return Ok(frames.iter().map(|_| RgbaImage::new(1, 1)).collect())
+	};
+
+	Ok(placed.into_iter().map(|p| {
+		let mut canvas = RgbaImage::new((x1 - x0) as u32, (y1 - y0) as u32);
+		if let Some((img, x, y)) = p {
+			imageops::overlay(&mut canvas, &img, (x - x0) as i64, (y - y0) as i64);
+		}
+		canvas
+	}).collect())
+}
+
+#[test]
+fn render_offsets() -> Result<(), Box<dyn std::error::Error>> {
+	use image::{GrayImage, Luma, Rgba};
+	use crate::itp::Itp;
+
+	let mut itc = Itc::default();
+	let itp = Itp {
+		palette: vec![Rgba([0, 0, 0, 0]), Rgba([255, 0, 0, 255])],
+		image: GrayImage::from_fn(4, 2, |_, _| Luma([1])),
+	};
+	itc.content.push(crate::itp::write1000(&itp)?.into());
+	itc.frames[0].index = Some(0);
+	itc.frames[1].index = Some(0);
+	itc.frames[1].x_offset = -0.5;
+	itc.frames[2].index = Some(0);
+	itc.frames[2].y_scale = 0.5;
+
+	let out = render(&itc, &[0, 1, 2, 3])?;
+	assert!(out.iter().all(|a| a.dimensions() == (6, 4)));
+	let red = |img: &RgbaImage| img.enumerate_pixels().filter(|p| p.2.0[3] != 0).map(|p| (p.0, p.1)).collect::<Vec<_>>();
+	assert_eq!(red(&out[0]), [(0, 1), (1, 1), (2, 1), (3, 1), (0, 2), (1, 2), (2, 2), (3, 2)]);
+	assert_eq!(red(&out[1]).first(), Some(&(2, 1)));
+	assert_eq!(red(&out[2]).len(), 16);
+	assert!(red(&out[3]).is_empty());
+	Ok(())
+}
@@ -97,6 +97,83 @@ pub fn write<I>(mode: Mode, img: &I) -> Result<Vec<u8>, Error> where
 	Ok(ch.finish()?)
 }
 
+/// A possible format for a ch file, as given by [`guess_from_content`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Guess {
+	pub mode: Mode,
+	pub width: usize,
+	pub height: usize,
+	/// How unlikely this guess is; lower is better.
+	pub cost: f32,
+}
+
+/// Guesses the format of a ch file by looking at its pixels, returning the plausible candidates
+/// with the best first.
+///
+/// Each candidate is scored by how much neighboring pixels differ, which is low for the right
+/// width and mode and high when rows are misaligned or channels are scrambled, plus a penalty for
+/// semitransparent pixels, which are rare in real images but common when reading the wrong mode.
+///
+/// Only widths that are multiples of 16 and aspect ratios no more extreme than 8:1 are considered.
+pub fn guess_from_content(ch: &[u8]) -> Vec<Guess> {
+	let mut guesses = Vec::new();
+	for mode in [Mode::Argb1555, Mode::Argb4444, Mode::Argb8888] {
+		if ch.len() % mode.bytes_per() != 0 {
+			continue
+		}
+		let pixels = ch.chunks_exact(mode.bytes_per()).map(|a| match mode {
+			Mode::Argb1555 => from1555(u16::from_le_bytes([a[0], a[1]])),
+			Mode::Argb4444 => from4444(u16::from_le_bytes([a[0], a[1]])),
+			Mode::Argb8888 => from8888(u32::from_le_bytes([a[0], a[1], a[2], a[3]])),
+		}.0).collect::<Vec<_>>();
+
+		for width in (16..=4096).step_by(16) {
+			if pixels.len() % width != 0 {
+				continue
+			}
+			let height = pixels.len() / width;
+			if height < 2 || height * 8 < width || width * 8 < height {
+				continue
+			}
+			let cost = cost(&pixels, width, height);
+			guesses.push(Guess { mode, width, height, cost });
+		}
+	}
+	guesses.sort_by(|a, b| a.cost.total_cmp(&b.cost));
+	guesses
+}
+
+fn cost(pixels: &[[u8; 4]], width: usize, height: usize) -> f32 {
+	let dist = |a: [u8; 4], b: [u8; 4]| a.iter().zip(b).map(|(a, b)| a.abs_diff(b) as u32).sum::<u32>();
+	// Looking at a few hundred rows is plenty, and keeps it fast for large images.
+	let step = (height / 256).max(1);
+	let mut diff = 0u64;
+	let mut semi = 0u64;
+	let mut n = 0u64;
+	for y in (0..height - 1).step_by(step) {
+		let row = &pixels[y * width..][..width];
+		let next = &pixels[(y + 1) * width..][..width];
+		for x in 0..width - 1 {
+			diff += (dist(row[x], next[x]) + dist(row[x], row[x + 1])) as u64;
+			semi += (row[x][3] != 0 && row[x][3] != 255) as u64;
+			n += 1;
+		}
+	}
+	(diff as f32 / 2. + 64. * semi as f32) / n as f32
+}
+
+/// Guesses which mode an image is best written in: argb1555 if all pixels are either opaque or
+/// fully transparent, otherwise argb4444.
+pub fn guess_mode(img: &RgbaImage) -> Mode {
+	if img.pixels().all(|a| a.0[3] == 0 || a.0[3] == 255) {
+		Mode::Argb1555
+	} else {
+		Mode::Argb4444
+	}
+}
+
+// These are only meant for files that exist in the games, since many of the prefixes are short
+// enough to match arbitrary names. Other files should use guess_from_content instead.
 macro_rules! guess {
 	($($prefix:literal, $mode:ident, $w:literal, $h:literal;)*) => {
 		pub fn guess_from_byte_size(name: &str, bytes: usize) -> Option<(Mode, usize, usize)> {
@@ -219,3 +296,30 @@ fn test() -> Result<(), Box<dyn std::error::Error>> {
 
 	Ok(())
 }
+
+#[test]
+fn guess() {
+	let img = RgbaImage::from_fn(128, 96, |x, y| {
+		let d = (x as i32 - 64).pow(2) + (y as i32 - 48).pow(2);
+		let n = (x * 7 + y * 13) % 5;
+		Rgba([(x * 2 + n) as u8, (y * 2) as u8, (128 + n * 3) as u8, if d < 40 * 40 { 255 } else { 0 }])
+	});
+	for mode in [Mode::Argb1555, Mode::Argb4444, Mode::Argb8888] {
+		let data = write(mode, &img).unwrap();
+		let g = guess_from_content(&data);
+		assert_eq!((g[0].mode, g[0].width, g[0].height), (mode, 128, 96), "{mode:?} {:?}", &g[..3]);
+	}
+	assert_eq!(guess_mode(&img), Mode::Argb1555);
+	// Known files that match no prefix fall back to 4444 by size.
+	assert_eq!(guess_from_image_size("mt4301", 1024, 1024), Some(Mode::Argb4444));
+	assert_eq!(guess_from_byte_size("mt4301", 512 * 512 * 2), Some((Mode::Argb4444, 512, 512)));
+
+	// Soft edges can't be 1555, but shouldn't be mistaken for it either.
+	let img = RgbaImage::from_fn(128, 96, |x, y| {
+		let d = ((x as f32 - 64.).hypot(y as f32 - 48.) - 24.).clamp(0., 16.);
+		Rgba([(x * 2) as u8, (y * 2) as u8, 128, 255 - (d * 15.9) as u8])
+	});
+	assert_eq!(guess_mode(&img), Mode::Argb4444);
+	let g = guess_from_content(&write(Mode::Argb4444, &img).unwrap());
+	assert_eq!((g[0].mode, g[0].width), (Mode::Argb4444, 128), "{:?}", &g[..3]);
+}
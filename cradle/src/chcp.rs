@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use image::{GenericImage, GenericImageView, Rgba, RgbaImage};
 use gospel::read::{Reader, Le as _};
 use gospel::write::{Writer, Le as _};
@@ -36,10 +38,13 @@ pub fn read(ch: &[u8], cp: &[u8]) -> Result<Vec<RgbaImage>, Error> {
 	Ok(frames)
 }
 
+/// Tiles that look the same after reducing to ARGB4444 are stored only once, across all frames.
+/// The format has no way to flip tiles, so mirrored ones are stored separately.
 pub fn write<I>(frames: &[I]) -> Result<(Vec<u8>, Vec<u8>), Error> where
 	I: GenericImageView<Pixel=Rgba<u8>>
 {
 	let mut base = Vec::<[[u16; 16]; 16]>::new();
+	let mut index = HashMap::new();
 	let mut pat = vec![[[0xFFFF; 16]; 16]; frames.len()];
 
 	for f in frames {
@@ -55,15 +60,17 @@ pub fn write<I>(frames: &[I]) -> Result<(Vec<u8>, Vec<u8>), Error> where
 					let mut c = [[0; 16]; 16];
 					for (c, (_, _, p)) in c.as_flattened_mut().iter_mut().zip(sub.pixels()) {
 						*c = ch::to4444(p);
+						// The color of invisible pixels doesn't matter, so don't let it prevent sharing.
+						if *c & 0xF000 == 0 {
+							*c = 0;
+						}
 					}
 					let p = &mut p[y as usize][x as usize];
 					if c != [[0; 16]; 16] {
-						if let Some(n) = base.iter().position(|a| *a == c) {
-							*p = n as u16;
-						} else {
-							*p = base.len() as u16;
+						*p = *index.entry(c).or_insert_with(|| {
 							base.push(c);
-						}
+							base.len() as u16 - 1
+						});
 					}
 				}
 			}
@@ -98,9 +105,12 @@ fn test() -> Result<(), Box<dyn std::error::Error>> {
 		let p2 = p.with_file_name(format!("{}p._cp", p.with_extension("").file_name().unwrap().to_str().unwrap()));
 		let ch = std::fs::read(&p)?;
 		let cp = std::fs::read(&p2)?;
-		let frames = read(&ch, &cp)?;
+		let mut frames = read(&ch, &cp)?;
 		let (ch2, cp2) = write(&frames)?;
 		let frames2 = read(&ch2, &cp2)?;
+		for p in frames.iter_mut().flat_map(|a| a.pixels_mut()).filter(|p| p.0[3] < 0x10) {
+			*p = Rgba([0, 0, 0, 0]);
+		}
 		assert!(frames == frames2);
 	}
 	Ok(())
@@ -116,3 +126,23 @@ fn test2() -> Result<(), Box<dyn std::error::Error>> {
 	std::fs::write("/tmp/ap._ph", cp2)?;
 	Ok(())
 }
+
+#[test]
+fn dedup() -> Result<(), Box<dyn std::error::Error>> {
+	let mut a = RgbaImage::new(256, 256);
+	let mut b = RgbaImage::new(256, 256);
+	for (x, y, p) in a.enumerate_pixels_mut() {
+		*p = if x < 32 && y < 16 { Rgba([255, 0, 0, 255]) } else { Rgba([0, 0, 0, 0]) };
+	}
+	for (x, y, p) in b.enumerate_pixels_mut() {
+		*p = if x >= 240 && y >= 240 { Rgba([255, 0, 0, 255]) } else { Rgba([9, 9, 9, 0]) };
+	}
+	b.put_pixel(241, 0, Rgba([255, 255, 255, 0]));
+	let (ch, cp) = write(&[a.clone(), b.clone()])?;
+	assert_eq!(u16::from_le_bytes([ch[0], ch[1]]), 1);
+	let out = read(&ch, &cp)?;
+	assert_eq!(out[0], a);
+	assert_eq!(out[1].get_pixel(245, 245), &Rgba([255, 0, 0, 255]));
+	assert_eq!(out[1].get_pixel(0, 0), &Rgba([0, 0, 0, 0]));
+	Ok(())
+}
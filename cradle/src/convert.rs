@@ -0,0 +1,748 @@
+//! Converting whole files between the games' formats and ordinary ones, as the `cradle` tool does.
+//!
+//! Files are read into an [`Asset`], which is either a single image or a set of sprite frames,
+//! and written from that in the target format. A few conversions skip this step to avoid
+//! recompressing: BC7 textures move between `.dds` and `.itp` unchanged, other block-compressed
+//! `.dds` become `.itp` with their mipmaps intact, and `._ds` files that are not block compressed
+//! are passed through as `.dds`.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use image::{GenericImage, GenericImageView, ImageFormat as IF, Rgba, RgbaImage};
+use crate::{ch, chcp, dds, mip, quant};
+use crate::itc::{self, Itc};
+use crate::itp::{self, Itp};
+use crate::itp32::{self, Itp32};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	#[error(transparent)]
+	Cradle(#[from] crate::util::Error),
+	#[error(transparent)]
+	Io(#[from] std::io::Error),
+	#[error(transparent)]
+	Image(#[from] image::ImageError),
+	#[error(transparent)]
+	PngDecode(#[from] png::DecodingError),
+	#[error(transparent)]
+	PngEncode(#[from] png::EncodingError),
+	#[error(transparent)]
+	Json(#[from] serde_json::Error),
+	#[error(transparent)]
+	Dds(#[from] ddsfile::Error),
+	#[error("{0}")]
+	Invalid(String),
+}
+
+fn invalid(msg: impl Into<String>) -> Error {
+	Error::Invalid(msg.into())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+	/// An ordinary png. Images that have a palette are written as indexed pngs, and images with
+	/// mipmaps as one png per level: `name.0.png`, `name.1.png`, and so on.
+	Png,
+	/// An ordinary dds.
+	Dds,
+	/// An ED6 image, `._ch`.
+	Ch,
+	/// An ED6 sprite: a `._cp` with its `._ch` tileset, named like `ch00000p._cp` and `ch00000._ch`.
+	Chcp,
+	/// An ED6 block-compressed texture, `._ds`.
+	Ds,
+	/// An ED7 image, `.itp`, either indexed or 32-bit.
+	Itp,
+	/// An ED7 sprite, `.itc`.
+	Itc,
+	/// A directory of pngs, one per frame, described by a `chip.json`.
+	Frames,
+	/// A sprite sheet png, described by a `.atlas.json`. Each row holds a group of eight frames,
+	/// which are usually the eight directions of a pose.
+	Atlas,
+}
+
+impl Format {
+	/// Guesses the format of a file from its name. Directories containing a `chip.json` are [`Format::Frames`].
+	pub fn of(path: &Path) -> Option<Format> {
+		if path.join("chip.json").is_file() {
+			return Some(Format::Frames)
+		}
+		let name = path.file_name()?.to_str()?.to_lowercase();
+		if name == "chip.json" || name.ends_with(".chip.json") {
+			Some(Format::Frames)
+		} else if name.ends_with(".atlas.json") {
+			Some(Format::Atlas)
+		} else if name.ends_with(".png") {
+			Some(Format::Png)
+		} else if name.ends_with(".dds") {
+			Some(Format::Dds)
+		} else if name.ends_with("._ch") {
+			Some(Format::Ch)
+		} else if name.ends_with("._cp") {
+			Some(Format::Chcp)
+		} else if name.ends_with("._ds") {
+			Some(Format::Ds)
+		} else if name.ends_with(".itp") {
+			Some(Format::Itp)
+		} else if name.ends_with(".itc") {
+			Some(Format::Itc)
+		} else {
+			None
+		}
+	}
+
+	/// Where to write the output of converting `input` to this format, if not told otherwise: next
+	/// to the input, with the same name but a different extension.
+	pub fn output_path(self, input: &Path) -> PathBuf {
+		let base = base_path(input);
+		let with = |suffix: &str| {
+			let mut name = base.file_name().unwrap_or_default().to_os_string();
+			name.push(suffix);
+			base.with_file_name(name)
+		};
+		match self {
+			Format::Png | Format::Atlas => with(".png"),
+			Format::Dds => with(".dds"),
+			Format::Ch => with("._ch"),
+			Format::Chcp => with("p._cp"),
+			Format::Ds => with("._ds"),
+			Format::Itp => with(".itp"),
+			Format::Itc => with(".itc"),
+			Format::Frames => base,
+		}
+	}
+}
+
+/// The input path without its extension, or whatever else is specific to the format.
+fn base_path(path: &Path) -> PathBuf {
+	if path.join("chip.json").is_file() {
+		return path.to_path_buf()
+	}
+	let Some(name) = path.file_name().and_then(|a| a.to_str()) else {
+		return path.to_path_buf()
+	};
+	let lower = name.to_lowercase();
+	if lower == "chip.json" {
+		return path.parent().unwrap_or(path).to_path_buf()
+	}
+	let len = [".chip.json", ".atlas.json", "p._cp", ".0.png"].into_iter()
+		.find(|s| lower.ends_with(s))
+		.map_or_else(|| path.extension().map_or(0, |e| e.len() + 1), str::len);
+	path.with_file_name(&name[..name.len() - len])
+}
+
+/// Where the `._ch` tileset that goes with a `._cp` is.
+pub fn chcp_tileset_path(cp: &Path) -> PathBuf {
+	let mut name = base_path(cp).file_name().unwrap_or_default().to_os_string();
+	name.push("._ch");
+	cp.with_file_name(name)
+}
+
+/// How to read and write the formats that need some extra information.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+	/// Which mode to read `._ch` files in. If unset, it is guessed from the content.
+	pub ch_mode: Option<ch::Mode>,
+	/// Which width to read `._ch` files as. If unset, it is guessed from the content.
+	pub ch_width: Option<usize>,
+	/// Which format to write `._ds` and `.dds` files in. If unset, `._ds` uses BC1 for opaque
+	/// images and BC3 otherwise, and `.dds` uses BC7.
+	pub ds: Option<dds::Format>,
+	/// Generate mipmaps when writing 32-bit itp, `._ds` and `.dds`, with this filter.
+	pub mipmaps: Option<mip::Filter>,
+	/// Which format to write indexed itp in.
+	pub itp: ItpVersion,
+	/// Reduce truecolor images to an indexed itp, rather than writing a 32-bit itp.
+	pub quant: Option<quant::Options>,
+	/// How hard to try when compressing BC7.
+	pub bc7: bc7::Quality,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ItpVersion {
+	V1000,
+	V1002,
+	#[default]
+	V1004,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Asset {
+	Image(Image),
+	Frames(Vec<Frame>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+	pub image: RgbaImage,
+	/// The palette of images from indexed formats. All pixels are colors in the palette.
+	pub palette: Option<Vec<Rgba<u8>>>,
+	/// Mipmap levels after the first, each half the size of the previous one.
+	pub mipmaps: Vec<RgbaImage>,
+}
+
+impl From<RgbaImage> for Image {
+	fn from(image: RgbaImage) -> Self {
+		Image { image, palette: None, mipmaps: Vec::new() }
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+	pub frame: usize,
+	pub image: Image,
+	/// How far the center of the image is from the center of the frame, negated. If this is
+	/// `None`, the image is instead a canvas centered on the frame, and will be cropped when
+	/// needed.
+	pub offset: Option<(f32, f32)>,
+	pub scale: (f32, f32),
+}
+
+/// Converts a file, or for [`Format::Frames`] a directory.
+pub fn convert(input: &Path, from: Format, output: &Path, to: Format, opts: &Options) -> Result<(), Error> {
+	match (from, to) {
+		(Format::Dds, Format::Itp) => {
+			let dds = ddsfile::Dds::read(BufReader::new(File::open(input)?))?;
+			if let Some(itp) = Itp32::from_bc7_dds(&dds) {
+				return Ok(std::fs::write(output, itp32::write(&itp)?)?)
+			}
+			// Keep the texture's own mipmaps, unless asked to quantize or to generate new ones.
+			if opts.quant.is_none() && dds::Format::of(&dds).is_some() && (dds.get_num_mipmap_levels() > 1 || opts.mipmaps.is_none()) {
+				return Ok(std::fs::write(output, itp32::write(&Itp32::from_dds(&dds, opts.bc7)?)?)?)
+			}
+		}
+		(Format::Itp, Format::Dds) => {
+			let data = std::fs::read(input)?;
+			if data.starts_with(b"ITP\xFF") {
+				return Ok(itp32::read(&data)?.to_bc7_dds().write(&mut File::create(output)?)?)
+			}
+		}
+		(Format::Dds, Format::Ds) => {
+			return Ok(std::fs::copy(input, output).map(|_| ())?)
+		}
+		(Format::Ds, Format::Dds) => {
+			return Ok(std::fs::copy(input, output).map(|_| ())?)
+		}
+		_ => {}
+	}
+	write(&read(input, from, opts)?, output, to, opts)
+}
+
+pub fn read(path: &Path, format: Format, opts: &Options) -> Result<Asset, Error> {
+	Ok(match format {
+		Format::Png => Asset::Image(read_png_levels(path)?),
+		Format::Dds => {
+			let dds = ddsfile::Dds::read(BufReader::new(File::open(path)?))?;
+			if let Some(itp) = Itp32::from_bc7_dds(&dds) {
+				Asset::Image(itp32_image(&itp))
+			} else {
+				let mut levels = dds::to_rgba_levels(&dds)?;
+				let image = levels.remove(0);
+				Asset::Image(Image { image, palette: None, mipmaps: levels })
+			}
+		}
+		Format::Ch => Asset::Image(read_ch(path, opts)?.into()),
+		Format::Chcp => {
+			let ch = std::fs::read(chcp_tileset_path(path))?;
+			let cp = std::fs::read(path)?;
+			Asset::Frames(chcp::read(&ch, &cp)?.into_iter().enumerate().map(|(frame, image)| Frame {
+				frame,
+				image: image.into(),
+				offset: None,
+				scale: (1., 1.),
+			}).collect())
+		}
+		Format::Ds => {
+			let dds = ddsfile::Dds::read(BufReader::new(File::open(path)?))?;
+			if dds::Format::of(&dds).is_none() {
+				return Err(invalid("unsupported texture format, try converting to dds"))
+			}
+			Asset::Image(dds::to_rgba(&dds)?.into())
+		}
+		Format::Itp => {
+			let data = std::fs::read(path)?;
+			if data.starts_with(b"ITP\xFF") {
+				Asset::Image(itp32_image(&itp32::read(&data)?))
+			} else {
+				let itp = itp::read(&data)?;
+				Asset::Image(Image { image: itp.to_rgba(), palette: Some(itp.palette), mipmaps: Vec::new() })
+			}
+		}
+		Format::Itc => Asset::Frames(read_itc(&itc::read(&std::fs::read(path)?)?)?),
+		Format::Frames => Asset::Frames(read_frames(path)?),
+		Format::Atlas => Asset::Frames(read_atlas(path)?),
+	})
+}
+
+pub fn write(asset: &Asset, path: &Path, format: Format, opts: &Options) -> Result<(), Error> {
+	match (format, asset) {
+		(Format::Png, Asset::Image(img)) => write_png_levels(img, path),
+		(Format::Dds, Asset::Image(img)) => {
+			let dds = if img.mipmaps.is_empty() || opts.ds.is_some_and(|f| f != dds::Format::Bc7) {
+				dds::from_rgba(&img.image, opts.ds.unwrap_or(dds::Format::Bc7), opts.mipmaps)
+			} else {
+				Itp32::from_rgba_levels(&levels(img), opts.bc7)?.to_bc7_dds()
+			};
+			Ok(dds.write(&mut File::create(path)?)?)
+		}
+		(Format::Ch, Asset::Image(img)) => {
+			let mode = opts.ch_mode.unwrap_or_else(|| ch::guess_mode(&img.image));
+			Ok(std::fs::write(path, ch::write(mode, &img.image)?)?)
+		}
+		(Format::Ds, Asset::Image(img)) => {
+			let format = opts.ds.unwrap_or_else(|| {
+				if img.image.pixels().all(|a| a.0[3] == 255) { dds::Format::Bc1 } else { dds::Format::Bc3 }
+			});
+			Ok(dds::from_rgba(&img.image, format, opts.mipmaps).write(&mut File::create(path)?)?)
+		}
+		(Format::Itp, Asset::Image(img)) => Ok(std::fs::write(path, write_itp(img, opts)?)?),
+		(Format::Chcp, Asset::Frames(frames)) => {
+			let (ch, cp) = chcp::write(&chcp_frames(frames)?)?;
+			std::fs::write(chcp_tileset_path(path), ch)?;
+			Ok(std::fs::write(path, cp)?)
+		}
+		(Format::Itc, Asset::Frames(frames)) => Ok(std::fs::write(path, itc::write(&write_itc(frames, opts)?)?)?),
+		(Format::Frames, Asset::Frames(frames)) => write_frames(frames, path),
+		(Format::Atlas, Asset::Frames(frames)) => write_atlas(&chcp_frames(frames)?, path),
+		(_, Asset::Image(_)) => Err(invalid(format!("cannot write a single image as {format:?}"))),
+		(_, Asset::Frames(_)) => Err(invalid(format!("cannot write sprite frames as {format:?}"))),
+	}
+}
+
+fn levels(img: &Image) -> Vec<RgbaImage> {
+	std::iter::once(&img.image).chain(&img.mipmaps).cloned().collect()
+}
+
+fn itp32_image(itp: &Itp32) -> Image {
+	Image {
+		image: itp.to_rgba(0),
+		palette: None,
+		mipmaps: (1..itp.levels()).map(|n| itp.to_rgba(n)).collect(),
+	}
+}
+
+fn read_ch(path: &Path, opts: &Options) -> Result<RgbaImage, Error> {
+	let data = std::fs::read(path)?;
+	if let (Some(mode), Some(width)) = (opts.ch_mode, opts.ch_width) {
+		return Ok(ch::read(mode, width, &data)?)
+	}
+	let name = path.file_name().and_then(|a| a.to_str()).unwrap_or_default().to_lowercase();
+	if opts.ch_mode.is_none() && opts.ch_width.is_none() && name.starts_with("ch") && data.len() % (2*16*16) == 2 {
+		// The tileset of a chcp.
+		return Ok(ch::read(ch::Mode::Argb4444, 16, &data[2..])?)
+	}
+	let guess = ch::guess_from_content(&data).into_iter()
+		.find(|g| opts.ch_mode.is_none_or(|m| m == g.mode) && opts.ch_width.is_none_or(|w| w == g.width))
+		.ok_or_else(|| invalid("could not guess format"))?;
+	Ok(ch::read(guess.mode, guess.width, &data)?)
+}
+
+fn write_itp(img: &Image, opts: &Options) -> Result<Vec<u8>, Error> {
+	let indexed = |itp: &Itp| -> Result<Vec<u8>, Error> {
+		Ok(match opts.itp {
+			ItpVersion::V1000 => itp::write1000(itp)?,
+			ItpVersion::V1002 => itp::write1002(itp)?,
+			ItpVersion::V1004 => itp::write1004(itp)?,
+		})
+	};
+	if let Some(pal) = &img.palette {
+		let itp = Itp::from_rgba(&img.image, pal.clone())
+			.map_err(|c| invalid(format!("color {c:?} is not in the palette")))?;
+		indexed(&itp)
+	} else if let Some(quant) = &opts.quant {
+		indexed(&quant::quantize(&img.image, quant))
+	} else if !img.mipmaps.is_empty() {
+		Ok(itp32::write(&Itp32::from_rgba_levels(&levels(img), opts.bc7)?)?)
+	} else if let Some(filter) = opts.mipmaps {
+		Ok(itp32::write(&Itp32::from_rgba_mipmapped(&img.image, filter, opts.bc7)?)?)
+	} else {
+		Ok(itp32::write(&Itp32::from_rgba(&img.image, opts.bc7))?)
+	}
+}
+
+/// Reads a png, along with its palette if it is indexed.
+pub fn read_png(mut r: impl BufRead + Seek) -> Result<Image, Error> {
+	let pos = r.stream_position()?;
+	let png = png::Decoder::new(&mut r).read_info()?;
+	let info = png.info();
+	let palette = info.palette.as_ref().map(|pal| {
+		if let Some(trns) = &info.trns {
+			pal.chunks_exact(3).zip(trns.iter().chain(std::iter::repeat(&0xFF)))
+				.map(|(a, b)| Rgba([a[0], a[1], a[2], *b]))
+				.collect()
+		} else {
+			pal.chunks_exact(3)
+				.map(|a| Rgba([a[0], a[1], a[2], 0xFF]))
+				.collect()
+		}
+	});
+	r.seek(SeekFrom::Start(pos))?;
+	let image = image::load(r, IF::Png)?.to_rgba8();
+	Ok(Image { image, palette, mipmaps: Vec::new() })
+}
+
+/// Writes a png, as an indexed one if the image has a palette.
+pub fn write_png(img: &Image, mut w: impl Write + Seek) -> Result<(), Error> {
+	let Some(palette) = &img.palette else {
+		img.image.write_to(&mut w, IF::Png)?;
+		return Ok(())
+	};
+	let itp = Itp::from_rgba(&img.image, palette.clone())
+		.map_err(|c| invalid(format!("color {c:?} is not in the palette")))?;
+	let mut png = png::Encoder::new(&mut w, itp.image.width(), itp.image.height());
+	let mut pal = Vec::with_capacity(3*itp.palette.len());
+	let mut alp = Vec::with_capacity(itp.palette.len());
+	for &Rgba([r,g,b,a]) in &itp.palette {
+		pal.push(r);
+		pal.push(g);
+		pal.push(b);
+		alp.push(a);
+	}
+	png.set_color(png::ColorType::Indexed);
+	png.set_depth(png::BitDepth::Eight);
+	png.set_palette(pal);
+	png.set_trns(alp);
+	let mut w = png.write_header()?;
+	w.write_image_data(&itp.image)?;
+	w.finish()?;
+	Ok(())
+}
+
+/// `name.N.png`, for mipmap level `N` of `name.png`.
+fn level_path(path: &Path, n: usize) -> PathBuf {
+	base_path(path).with_extension(format!("{n}.png"))
+}
+
+fn read_png_levels(path: &Path) -> Result<Image, Error> {
+	let mut img = read_png(BufReader::new(File::open(path)?))?;
+	let is_level0 = path.file_name().and_then(|a| a.to_str()).is_some_and(|a| a.to_lowercase().ends_with(".0.png"));
+	if is_level0 {
+		while let Ok(f) = File::open(level_path(path, img.mipmaps.len() + 1)) {
+			img.mipmaps.push(read_png(BufReader::new(f))?.image);
+		}
+	}
+	Ok(img)
+}
+
+fn write_png_levels(img: &Image, path: &Path) -> Result<(), Error> {
+	if img.mipmaps.is_empty() {
+		return write_png(img, File::create(path)?)
+	}
+	for (n, level) in levels(img).into_iter().enumerate() {
+		level.write_to(&mut File::create(level_path(path, n))?, IF::Png)?;
+	}
+	Ok(())
+}
+
+fn read_itc(itc: &Itc) -> Result<Vec<Frame>, Error> {
+	let mut frames = Vec::new();
+	for (n, frame) in itc.frames.iter().enumerate() {
+		let Some(index) = frame.index else { continue };
+		let data = &itc.content[index];
+		let image = if data.starts_with(b"ITP\xFF") {
+			Image::from(itp32::read(data)?.to_rgba(0))
+		} else {
+			let itp = itp::read(data)?;
+			let palette = itc.palette.clone().unwrap_or(itp.palette);
+			let itp = Itp { palette, image: itp.image };
+			Image { image: itp.to_rgba(), palette: Some(itp.palette), mipmaps: Vec::new() }
+		};
+		let xs = frame.x_scale.recip();
+		let ys = frame.y_scale.recip();
+		let xo = frame.x_offset * image.image.width() as f32 * xs;
+		let yo = frame.y_offset * image.image.height() as f32 * ys;
+		frames.push(Frame { frame: n, image, offset: Some((xo, yo)), scale: (xs, ys) });
+	}
+	Ok(frames)
+}
+
+fn write_itc(frames: &[Frame], opts: &Options) -> Result<Itc<'static>, Error> {
+	let mut itc = Itc::default();
+	for f in frames {
+		if f.frame >= itc.frames.len() {
+			return Err(invalid(format!("frame {} is out of range, itc only has {}", f.frame, itc.frames.len())))
+		}
+		let (img, (xo, yo)) = if let Some(o) = f.offset {
+			(f.image.clone(), o)
+		} else {
+			let (img, (xo, yo)) = crop(&f.image.image);
+			(Image { image: img.to_image(), ..f.image.clone() }, (xo as f32, yo as f32))
+		};
+		itc.frames[f.frame] = itc::Frame {
+			index: Some(itc.content.len()),
+			unknown: 0,
+			x_offset: xo / f.scale.0 / img.image.width() as f32,
+			y_offset: yo / f.scale.1 / img.image.height() as f32,
+			x_scale: f.scale.0.recip(),
+			y_scale: f.scale.1.recip(),
+		};
+		itc.content.push(write_itp(&img, opts)?.into());
+	}
+	Ok(itc)
+}
+
+fn crop(img: &RgbaImage) -> (image::SubImage<&RgbaImage>, (i32, i32)) {
+	let bounds = || {
+		let w = img.width();
+		let h = img.height();
+		let l = (0..w). find(|&x| (0..h).any(|y| img.get_pixel(x, y).0[3] != 0))?;
+		let r = (0..w).rfind(|&x| (0..h).any(|y| img.get_pixel(x, y).0[3] != 0))?;
+		let u = (0..h). find(|&y| (0..w).any(|x| img.get_pixel(x, y).0[3] != 0))?;
+		let d = (0..h).rfind(|&y| (0..w).any(|x| img.get_pixel(x, y).0[3] != 0))?;
+
+		let ow = (r - l + 2).next_power_of_two().max(4); // I don't know why the +2
+		let oh = (d - u + 2).next_power_of_two().max(4);
+
+		let cx = if ow == w { 0 } else { w as i32 / 2 - (r+l) as i32 / 2 };
+		let cy = if oh == h { 0 } else { h as i32 / 2 - (d+u) as i32 / 2 };
+
+		let ox = (w as i32 / 2 - cx) as u32 - ow / 2;
+		let oy = (h as i32 / 2 - cy) as u32 - oh / 2;
+
+		Some((img.view(ox, oy, ow, oh), (cx, cy)))
+	};
+	bounds().unwrap_or_else(|| (img.view(0, 0, img.width(), img.height()), (0, 0)))
+}
+
+/// Places frames onto the 256×256 canvases used by chcp. Scaled frames are not supported.
+fn chcp_frames(frames: &[Frame]) -> Result<Vec<RgbaImage>, Error> {
+	let mut out = vec![RgbaImage::new(256, 256); frames.iter().map(|f| f.frame + 1).max().unwrap_or(0)];
+	for f in frames {
+		if f.scale != (1., 1.) {
+			return Err(invalid(format!("frame {} is scaled, which chcp does not support", f.frame)))
+		}
+		let img = &f.image.image;
+		let (ox, oy) = f.offset.unwrap_or_default();
+		let x = 128 - (img.width() as f32 / 2. + ox).round() as i64;
+		let y = 128 - (img.height() as f32 / 2. + oy).round() as i64;
+		image::imageops::replace(&mut out[f.frame], img, x, y);
+	}
+	Ok(out)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct FrameSpec {
+	frame: usize,
+	path: PathBuf,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	offset: Option<(f32, f32)>,
+	#[serde(default = "unit_scale", skip_serializing_if = "is_unit_scale")]
+	scale: (f32, f32),
+}
+
+fn unit_scale() -> (f32, f32) { (1.0, 1.0) }
+fn is_unit_scale(a: &(f32, f32)) -> bool { *a == unit_scale() }
+
+fn chip_json(path: &Path) -> PathBuf {
+	if path.is_dir() { path.join("chip.json") } else { path.to_path_buf() }
+}
+
+fn read_frames(path: &Path) -> Result<Vec<Frame>, Error> {
+	let jsonpath = chip_json(path);
+	let spec: Vec<FrameSpec> = serde_json::from_reader(BufReader::new(File::open(&jsonpath)?))?;
+	let dir = jsonpath.parent().unwrap();
+	spec.into_iter().map(|i| Ok(Frame {
+		frame: i.frame,
+		image: read_png(BufReader::new(File::open(dir.join(&i.path))?))?,
+		offset: i.offset,
+		scale: i.scale,
+	})).collect()
+}
+
+/// Writes frames as pngs in a directory. Frames whose offsets are whole pixels are placed on
+/// canvases of the same size; others are written as they are, with their offset in the json.
+fn write_frames(frames: &[Frame], outdir: &Path) -> Result<(), Error> {
+	std::fs::create_dir_all(outdir)?;
+
+	let placed = |f: &Frame| {
+		let (xo, yo) = f.offset?;
+		((xo - xo.round()).abs() < f32::EPSILON && (yo - yo.round()).abs() < f32::EPSILON)
+			.then_some((xo.round() as i32, yo.round() as i32))
+	};
+	let w = frames.iter().filter_map(|f| Some((f, placed(f)?)))
+		.map(|(f, o)| 2 * (f.image.image.width() / 2 + o.0.unsigned_abs()))
+		.max().unwrap_or(0).next_power_of_two();
+	let h = frames.iter().filter_map(|f| Some((f, placed(f)?)))
+		.map(|(f, o)| 2 * (f.image.image.height() / 2 + o.1.unsigned_abs()))
+		.max().unwrap_or(0).next_power_of_two();
+
+	let mut spec = Vec::new();
+	for f in frames {
+		let img = &f.image.image;
+		let path = PathBuf::from(format!("{}.png", f.frame));
+		let offset = if let Some((xo, yo)) = placed(f) {
+			let mut out = RgbaImage::new(w, h);
+			let x = w / 2 - (img.width() as i32 / 2 + xo) as u32;
+			let y = h / 2 - (img.height() as i32 / 2 + yo) as u32;
+			out.pixels_mut().for_each(|p| *p = *img.get_pixel(0, 0));
+			out.copy_from(img, x, y)?;
+			write_png(&Image { image: out, ..f.image.clone() }, File::create(outdir.join(&path))?)?;
+			None
+		} else if f.offset.is_some() {
+			write_png(&f.image, File::create(outdir.join(&path))?)?;
+			f.offset
+		} else if img.pixels().any(|a| a.0[3] != 0) {
+			write_png(&f.image, File::create(outdir.join(&path))?)?;
+			None
+		} else {
+			continue
+		};
+		spec.push(FrameSpec { frame: f.frame, path, offset, scale: f.scale });
+	}
+
+	let mut f = File::create(outdir.join("chip.json"))?;
+	{
+		use serde_json::ser::Formatter;
+		let mut pf = serde_json::ser::PrettyFormatter::with_indent(b"\t");
+		pf.begin_array(&mut f)?;
+		for (i, v) in spec.iter().enumerate() {
+			pf.begin_array_value(&mut f, i == 0)?;
+			serde_json::to_writer(&mut f, v)?;
+			pf.end_array_value(&mut f)?;
+		}
+		pf.end_array(&mut f)?;
+	}
+	Ok(())
+}
+
+/// The frame number is what decides where each frame goes; `group` and `direction` are only there
+/// to make the file easier to read.
+#[derive(Debug, Clone, PartialEq)]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Atlas {
+	image: PathBuf,
+	/// Number of frames, including any empty ones at the end.
+	frame_count: usize,
+	/// Where the top left corner of each cell goes in its 256×256 frame.
+	origin: (u32, u32),
+	frames: Vec<AtlasFrame>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct AtlasFrame {
+	frame: usize,
+	group: usize,
+	direction: usize,
+	/// x, y, width, height in the sheet.
+	rect: (u32, u32, u32, u32),
+}
+
+/// Writes the sheet to `out`, and the json next to it.
+fn write_atlas(frames: &[RgbaImage], out: &Path) -> Result<(), Error> {
+	// Crop all frames to the same rect, the smallest that fits all of them.
+	let bounds = |img: &RgbaImage| {
+		img.enumerate_pixels()
+			.filter(|p| p.2.0[3] != 0)
+			.map(|(x, y, _)| (x, y, x + 1, y + 1))
+			.reduce(|a, b| (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3)))
+	};
+	let (x0, y0, x1, y1) = frames.iter()
+		.filter_map(bounds)
+		.reduce(|a, b| (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3)))
+		.unwrap_or((0, 0, 1, 1));
+	let (w, h) = (x1 - x0, y1 - y0);
+
+	let mut sheet = RgbaImage::new(8 * w, frames.len().div_ceil(8).max(1) as u32 * h);
+	let mut cells = Vec::new();
+	for (i, img) in frames.iter().enumerate() {
+		if img.pixels().all(|a| a.0[3] == 0) {
+			continue
+		}
+		let (x, y) = ((i % 8) as u32 * w, (i / 8) as u32 * h);
+		sheet.copy_from(&*img.view(x0, y0, w, h), x, y)?;
+		cells.push(AtlasFrame {
+			frame: i,
+			group: i / 8,
+			direction: i % 8,
+			rect: (x, y, w, h),
+		});
+	}
+	sheet.write_to(&mut File::create(out)?, IF::Png)?;
+
+	let atlas = Atlas {
+		image: PathBuf::from(out.file_name().unwrap()),
+		frame_count: frames.len(),
+		origin: (x0, y0),
+		frames: cells,
+	};
+	let f = File::create(out.with_extension("atlas.json"))?;
+	let mut ser = serde_json::Serializer::with_formatter(f, serde_json::ser::PrettyFormatter::with_indent(b"\t"));
+	serde::Serialize::serialize(&atlas, &mut ser)?;
+	Ok(())
+}
+
+fn read_atlas(jsonpath: &Path) -> Result<Vec<Frame>, Error> {
+	let atlas: Atlas = serde_json::from_reader(BufReader::new(File::open(jsonpath)?))?;
+	let sheet = image::open(jsonpath.parent().unwrap().join(&atlas.image))?.to_rgba8();
+	let (ox, oy) = atlas.origin;
+	let mut frames = vec![RgbaImage::new(256, 256); atlas.frame_count];
+	for f in &atlas.frames {
+		let (x, y, w, h) = f.rect;
+		if f.frame >= frames.len() {
+			return Err(invalid(format!("frame {} is out of range", f.frame)))
+		}
+		if x + w > sheet.width() || y + h > sheet.height() {
+			return Err(invalid(format!("frame {} is outside the sheet", f.frame)))
+		}
+		if ox + w > 256 || oy + h > 256 {
+			return Err(invalid(format!("frame {} does not fit in 256×256", f.frame)))
+		}
+		frames[f.frame].copy_from(&*sheet.view(x, y, w, h), ox, oy)?;
+	}
+	Ok(frames.into_iter().enumerate().map(|(frame, image)| Frame {
+		frame,
+		image: image.into(),
+		offset: None,
+		scale: (1., 1.),
+	}).collect())
+}
+
+#[cfg(test)]
+fn png_bytes(img: &Image) -> Vec<u8> {
+	let mut c = std::io::Cursor::new(Vec::new());
+	write_png(img, &mut c).unwrap();
+	c.into_inner()
+}
+
+#[test]
+fn paths() {
+	let p = |a: &str, f: Format| f.output_path(Path::new(a)).to_str().unwrap().to_owned();
+	assert_eq!(p("a/ch00000p._cp", Format::Frames), "a/ch00000");
+	assert_eq!(p("a/ch00000.atlas.json", Format::Chcp), "a/ch00000p._cp");
+	assert_eq!(p("a/foo.0.png", Format::Itp), "a/foo.itp");
+	assert_eq!(p("a/foo.itp", Format::Png), "a/foo.png");
+	assert_eq!(p("a/ch00000/chip.json", Format::Itc), "a/ch00000.itc");
+	assert_eq!(chcp_tileset_path(Path::new("a/ch00000p._cp")), Path::new("a/ch00000._ch"));
+	assert_eq!(Format::of(Path::new("X.ITC")), Some(Format::Itc));
+	assert_eq!(Format::of(Path::new("foo.atlas.json")), Some(Format::Atlas));
+}
+
+#[test]
+fn indexed_png() {
+	let palette = vec![Rgba([0, 0, 0, 0]), Rgba([255, 0, 0, 255]), Rgba([0, 0, 255, 128])];
+	let image = RgbaImage::from_fn(4, 3, |x, y| palette[((x + y) % 3) as usize]);
+	let img = Image { image, palette: Some(palette), mipmaps: Vec::new() };
+	let out = read_png(std::io::Cursor::new(png_bytes(&img))).unwrap();
+	assert_eq!(out, img);
+}
+
+#[test]
+fn frames_between_chcp_and_itc() {
+	let mut image = RgbaImage::new(256, 256);
+	for y in 100..140 {
+		for x in 120..130 {
+			image.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+		}
+	}
+	let frames = vec![Frame { frame: 1, image: image.clone().into(), offset: None, scale: (1., 1.) }];
+	let opts = Options { quant: Some(quant::Options { colors: 16, dither: false, palette: quant::Palette::New }), ..Options::default() };
+	let itc = write_itc(&frames, &opts).unwrap();
+	let back = chcp_frames(&read_itc(&itc).unwrap()).unwrap();
+	assert_eq!(back.len(), 2);
+	assert_eq!(back[1], image);
+}
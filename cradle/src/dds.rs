@@ -0,0 +1,211 @@
+//! Block-compressed DDS textures, such as ED6's `._ds` files.
+
+use ddsfile::{Dds, D3DFormat, DxgiFormat, FourCC};
+use image::RgbaImage;
+use crate::mip;
+use crate::util::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+	/// Also known as DXT1.
+	Bc1,
+	/// Also known as DXT3.
+	Bc2,
+	/// Also known as DXT5.
+	Bc3,
+	Bc4,
+	Bc5,
+	Bc7,
+}
+
+impl Format {
+	pub fn of(dds: &Dds) -> Option<Format> {
+		if let Some(f) = dds.get_dxgi_format() {
+			return match f {
+				DxgiFormat::BC1_Typeless | DxgiFormat::BC1_UNorm | DxgiFormat::BC1_UNorm_sRGB => Some(Format::Bc1),
+				DxgiFormat::BC2_Typeless | DxgiFormat::BC2_UNorm | DxgiFormat::BC2_UNorm_sRGB => Some(Format::Bc2),
+				DxgiFormat::BC3_Typeless | DxgiFormat::BC3_UNorm | DxgiFormat::BC3_UNorm_sRGB => Some(Format::Bc3),
+				DxgiFormat::BC4_Typeless | DxgiFormat::BC4_UNorm => Some(Format::Bc4),
+				DxgiFormat::BC5_Typeless | DxgiFormat::BC5_UNorm => Some(Format::Bc5),
+				DxgiFormat::BC7_Typeless | DxgiFormat::BC7_UNorm | DxgiFormat::BC7_UNorm_sRGB => Some(Format::Bc7),
+				_ => None,
+			}
+		}
+		match dds.get_d3d_format() {
+			// DXT2 and DXT4 are premultiplied, which we don't care about.
+			Some(D3DFormat::DXT1) => return Some(Format::Bc1),
+			Some(D3DFormat::DXT2 | D3DFormat::DXT3) => return Some(Format::Bc2),
+			Some(D3DFormat::DXT4 | D3DFormat::DXT5) => return Some(Format::Bc3),
+			_ => {}
+		}
+		match dds.header.spf.fourcc {
+			Some(FourCC(FourCC::ATI1 | FourCC::BC4_UNORM)) => Some(Format::Bc4),
+			Some(FourCC(FourCC::ATI2)) => Some(Format::Bc5),
+			Some(FourCC(f)) if f == u32::from_le_bytes(*b"BC5U") => Some(Format::Bc5),
+			_ => None,
+		}
+	}
+
+	fn block_size(self) -> usize {
+		match self {
+			Format::Bc1 | Format::Bc4 => 8,
+			_ => 16,
+		}
+	}
+
+	fn decode(self, block: &[u8]) -> [[bc7::Rgba; 4]; 4] {
+		let mut b = [0; 16];
+		b[..block.len()].copy_from_slice(block);
+		let b = u128::from_le_bytes(b);
+		match self {
+			Format::Bc1 => bc7::decode_bc1(b as u64),
+			Format::Bc2 => bc7::decode_bc2(b),
+			Format::Bc3 => bc7::decode_bc3(b),
+			Format::Bc4 => bc7::decode_bc4(b as u64),
+			Format::Bc5 => bc7::decode_bc5(b),
+			Format::Bc7 => bc7::decode(b),
+		}
+	}
+
+	fn encode(self, block: &[[bc7::Rgba; 4]; 4], out: &mut Vec<u8>) {
+		match self {
+			Format::Bc1 => out.extend(bc7::encode_bc1(block).to_le_bytes()),
+			Format::Bc2 => out.extend(bc7::encode_bc2(block).to_le_bytes()),
+			Format::Bc3 => out.extend(bc7::encode_bc3(block).to_le_bytes()),
+			Format::Bc4 => out.extend(bc7::encode_bc4(block).to_le_bytes()),
+			Format::Bc5 => out.extend(bc7::encode_bc5(block).to_le_bytes()),
+			Format::Bc7 => out.extend(bc7::encode(block, bc7::Quality::default()).to_le_bytes()),
+		}
+	}
+}
+
+/// Decodes the first mipmap level of a block-compressed texture.
+pub fn to_rgba(dds: &Dds) -> Result<RgbaImage, Error> {
+	Ok(decode_levels(dds, 1)?.remove(0))
+}
+
+/// Decodes every mipmap level of a block-compressed texture, largest first.
+pub fn to_rgba_levels(dds: &Dds) -> Result<Vec<RgbaImage>, Error> {
+	decode_levels(dds, dds.get_num_mipmap_levels() as usize)
+}
+
+fn decode_levels(dds: &Dds, count: usize) -> Result<Vec<RgbaImage>, Error> {
+	let Some(format) = Format::of(dds) else {
+		bail!("dds: unsupported format")
+	};
+	let mut data = &dds.data[..];
+	let mut levels = Vec::new();
+	for level in 0..count.max(1) {
+		let width = (dds.get_width() as usize >> level).max(1);
+		let height = (dds.get_height() as usize >> level).max(1);
+		let (bw, bh) = (width.div_ceil(4), height.div_ceil(4));
+		let size = bw * bh * format.block_size();
+		ensure!(data.len() >= size, "dds: not enough data");
+
+		let mut pixels = vec![0; width * height * 4];
+		for (i, block) in data[..size].chunks_exact(format.block_size()).enumerate() {
+			let (bx, by) = (i % bw * 4, i / bw * 4);
+			for (y, row) in format.decode(block).iter().enumerate() {
+				for (x, px) in row.iter().enumerate() {
+					if bx + x < width && by + y < height {
+						let p = ((by + y) * width + bx + x) * 4;
+						pixels[p..p + 4].copy_from_slice(px);
+					}
+				}
+			}
+		}
+		data = &data[size..];
+		levels.push(image(width, height, pixels)?);
+	}
+	Ok(levels)
+}
+
+/// Encodes an image, optionally with a full chain of mipmaps. BC7 is encoded at the default quality.
+pub fn from_rgba(img: &RgbaImage, format: Format, mipmaps: Option<mip::Filter>) -> Dds {
+	let mut levels = vec![img.clone()];
+	if let Some(filter) = mipmaps {
+		while levels.last().is_some_and(|l| l.width() > 1 || l.height() > 1) {
+			levels.push(mip::downsample(levels.last().unwrap(), filter));
+		}
+	}
+
+	let (width, height) = img.dimensions();
+	let mut dds = match format {
+		Format::Bc1 | Format::Bc2 | Format::Bc3 => Dds::new_d3d(ddsfile::NewD3dParams {
+			width,
+			height,
+			depth: None,
+			format: match format {
+				Format::Bc1 => D3DFormat::DXT1,
+				Format::Bc2 => D3DFormat::DXT3,
+				_ => D3DFormat::DXT5,
+			},
+			mipmap_levels: mipmaps.map(|_| levels.len() as u32),
+			caps2: None,
+		}),
+		Format::Bc4 | Format::Bc5 | Format::Bc7 => Dds::new_dxgi(ddsfile::NewDxgiParams {
+			width,
+			height,
+			depth: None,
+			format: match format {
+				Format::Bc4 => DxgiFormat::BC4_UNorm,
+				Format::Bc5 => DxgiFormat::BC5_UNorm,
+				_ => DxgiFormat::BC7_UNorm,
+			},
+			mipmap_levels: mipmaps.map(|_| levels.len() as u32),
+			array_layers: None,
+			caps2: None,
+			is_cubemap: false,
+			resource_dimension: ddsfile::D3D10ResourceDimension::Texture2D,
+			alpha_mode: ddsfile::AlphaMode::Unknown,
+		}),
+	}.unwrap();
+
+	dds.data.clear();
+	for level in &levels {
+		let (w, h) = level.dimensions();
+		for by in (0..h).step_by(4) {
+			for bx in (0..w).step_by(4) {
+				let block = std::array::from_fn(|y| std::array::from_fn(|x| {
+					level.get_pixel((bx + x as u32).min(w - 1), (by + y as u32).min(h - 1)).0
+				}));
+				format.encode(&block, &mut dds.data);
+			}
+		}
+	}
+	dds
+}
+
+#[test]
+fn roundtrip() {
+	let img = RgbaImage::from_fn(10, 6, |x, _| image::Rgba([x as u8 * 20, 100, 200 - x as u8 * 20, 255]));
+	for format in [Format::Bc1, Format::Bc2, Format::Bc3, Format::Bc4, Format::Bc5, Format::Bc7] {
+		let dds = from_rgba(&img, format, Some(mip::Filter::Box));
+		assert_eq!(Format::of(&dds), Some(format));
+		assert_eq!(dds.get_num_mipmap_levels(), 4);
+		let mut buf = Vec::new();
+		dds.write(&mut buf).unwrap();
+		let out = to_rgba(&Dds::read(&buf[..]).unwrap()).unwrap();
+		assert_eq!(out.dimensions(), (10, 6));
+		let chans = match format {
+			Format::Bc4 => 1,
+			Format::Bc5 => 2,
+			_ => 4,
+		};
+		for (a, b) in img.pixels().zip(out.pixels()) {
+			assert!((0..chans).all(|k| a.0[k].abs_diff(b.0[k]) <= 12), "{format:?} {a:?} {b:?}");
+		}
+	}
+}
+
+#[test]
+fn to_itp32() {
+	let img = RgbaImage::from_fn(32, 16, |x, y| image::Rgba([x as u8 * 4, y as u8 * 8, 128, 255]));
+	let dds = from_rgba(&img, Format::Bc3, Some(mip::Filter::Box));
+	let itp = crate::itp32::Itp32::from_dds(&dds, bc7::Quality::Fast).unwrap();
+	// 4×2 and smaller can't be stored in itp32.
+	assert_eq!(itp.levels.iter().map(Vec::len).collect::<Vec<_>>(), [32, 8, 2]);
+	for (a, b) in img.pixels().zip(itp.to_rgba(0).pixels()) {
+		assert!((0..4).all(|k| a.0[k].abs_diff(b.0[k]) <= 16), "{a:?} {b:?}");
+	}
+}
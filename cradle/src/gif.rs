@@ -0,0 +1,199 @@
+//! Writing animated GIFs, for previewing animations in places that don't show APNG.
+//!
+//! All frames share one palette, picked by [`quant`](crate::quant). GIF only has on/off
+//! transparency, so pixels that are less than half opaque become transparent and the rest fully
+//! opaque.
+
+use std::collections::HashMap;
+
+use image::{Rgba, RgbaImage};
+use crate::quant;
+use crate::util::*;
+
+/// Encodes frames of the same size as a looping GIF, showing each for `delay_ms` milliseconds.
+pub fn write(frames: &[RgbaImage], delay_ms: u32) -> Result<Vec<u8>, Error> {
+	ensure!(!frames.is_empty(), "gif: no frames");
+	let (width, height) = frames[0].dimensions();
+	ensure!(frames.iter().all(|f| f.dimensions() == (width, height)), "gif: frames differ in size");
+	ensure!(width <= u16::MAX as u32 && height <= u16::MAX as u32, "gif: too large");
+
+	let mut sheet = tile(frames, 1);
+	for p in sheet.pixels_mut() {
+		*p = if p.0[3] < 128 { Rgba([0, 0, 0, 0]) } else { Rgba([p.0[0], p.0[1], p.0[2], 255]) };
+	}
+	// Index 0 is kept for transparency.
+	let itp = quant::quantize(&sheet, &quant::Options {
+		palette: quant::Palette::Extend(vec![Rgba([0, 0, 0, 0])]),
+		..quant::Options::default()
+	});
+
+	let mut out = Vec::new();
+	out.extend(b"GIF89a");
+	out.extend((width as u16).to_le_bytes());
+	out.extend((height as u16).to_le_bytes());
+	out.extend([0xF7, 0, 0]); // 256-color global palette
+	for i in 0..256 {
+		out.extend(&itp.palette.get(i).map_or([0; 4], |c| c.0)[..3]);
+	}
+	// Loop forever.
+	out.extend(b"\x21\xFF\x0BNETSCAPE2.0\x03\x01\0\0\0");
+
+	let delay = (delay_ms + 5) / 10;
+	let size = (width * height) as usize;
+	for frame in itp.image.as_raw().chunks_exact(size) {
+		// Clear to transparent before each frame, and index 0 is transparent.
+		out.extend([0x21, 0xF9, 4, 0x09]);
+		out.extend((delay.min(u16::MAX as u32) as u16).to_le_bytes());
+		out.extend([0, 0]);
+
+		out.push(0x2C);
+		out.extend([0, 0, 0, 0]);
+		out.extend((width as u16).to_le_bytes());
+		out.extend((height as u16).to_le_bytes());
+		out.push(0);
+
+		out.push(8);
+		for block in lzw(frame, 8).chunks(255) {
+			out.push(block.len() as u8);
+			out.extend(block);
+		}
+		out.push(0);
+	}
+	out.push(0x3B);
+	Ok(out)
+}
+
+/// GIF's variable-width LZW, starting at `min_size + 1` bits, up to 12.
+fn lzw(data: &[u8], min_size: u8) -> Vec<u8> {
+	struct Bits {
+		out: Vec<u8>,
+		acc: u32,
+		n: u32,
+	}
+
+	impl Bits {
+		fn put(&mut self, code: u16, size: u8) {
+			self.acc |= (code as u32) << self.n;
+			self.n += size as u32;
+			while self.n >= 8 {
+				self.out.push(self.acc as u8);
+				self.acc >>= 8;
+				self.n -= 8;
+			}
+		}
+	}
+
+	let clear = 1u16 << min_size;
+	let end = clear + 1;
+	let mut bits = Bits { out: Vec::new(), acc: 0, n: 0 };
+	let mut dict = HashMap::<(u16, u8), u16>::new();
+	let mut next = end + 1;
+	let mut size = min_size + 1;
+	bits.put(clear, size);
+
+	let mut it = data.iter();
+	let Some(&first) = it.next() else {
+		bits.put(end, size);
+		bits.put(0, 7);
+		return bits.out
+	};
+	let mut cur = first as u16;
+	for &b in it {
+		if let Some(&code) = dict.get(&(cur, b)) {
+			cur = code;
+			continue
+		}
+		bits.put(cur, size);
+		if next < 4096 {
+			dict.insert((cur, b), next);
+			next += 1;
+			if next > 1 << size && size < 12 {
+				size += 1;
+			}
+		} else {
+			bits.put(clear, size);
+			dict.clear();
+			next = end + 1;
+			size = min_size + 1;
+		}
+		cur = b as u16;
+	}
+	bits.put(cur, size);
+	bits.put(end, size);
+	bits.put(0, 7);
+	bits.out
+}
+
+#[cfg(test)]
+fn unlzw(data: &[u8], min_size: u8) -> Vec<u8> {
+	let clear = 1u16 << min_size;
+	let mut dict: Vec<Vec<u8>> = Vec::new();
+	let reset = |dict: &mut Vec<Vec<u8>>| {
+		*dict = (0..clear).map(|a| vec![a as u8]).collect();
+		dict.extend([vec![], vec![]]);
+	};
+	reset(&mut dict);
+	let mut size = min_size + 1;
+	let (mut acc, mut n, mut pos) = (0u32, 0u32, 0);
+	let mut prev: Option<Vec<u8>> = None;
+	let mut out = Vec::new();
+	loop {
+		while n < size as u32 {
+			acc |= (data[pos] as u32) << n;
+			pos += 1;
+			n += 8;
+		}
+		let code = (acc & ((1 << size) - 1)) as u16;
+		acc >>= size;
+		n -= size as u32;
+
+		if code == clear {
+			reset(&mut dict);
+			size = min_size + 1;
+			prev = None;
+			continue
+		}
+		if code == clear + 1 {
+			return out
+		}
+		let entry = match (dict.get(code as usize), &prev) {
+			(Some(e), _) => e.clone(),
+			(None, Some(p)) => [&p[..], &p[..1]].concat(),
+			(None, None) => panic!("bad code"),
+		};
+		if let Some(p) = prev {
+			if dict.len() < 4096 {
+				dict.push([&p[..], &entry[..1]].concat());
+			}
+		}
+		if dict.len() == 1 << size && size < 12 {
+			size += 1;
+		}
+		out.extend(&entry);
+		prev = Some(entry);
+	}
+}
+
+#[test]
+fn lzw_roundtrip() {
+	for data in [
+		vec![],
+		vec![7],
+		vec![1; 1000],
+		(0..20000u32).map(|a| (a * 7919 % 251) as u8 ^ (a / 100) as u8).collect::<Vec<_>>(),
+	] {
+		assert_eq!(unlzw(&lzw(&data, 8), 8), data);
+	}
+}
+
+#[test]
+fn frames() -> Result<(), Box<dyn std::error::Error>> {
+	let a = RgbaImage::from_fn(3, 2, |x, _| Rgba([x as u8 * 100, 0, 0, 255]));
+	let b = RgbaImage::new(3, 2);
+	let gif = write(&[a.clone(), b], 120)?;
+	assert!(gif.starts_with(b"GIF89a\x03\0\x02\0"));
+	assert!(gif.ends_with(b"\x3B"));
+	assert_eq!(gif.windows(4).filter(|w| w == &[0x21, 0xF9, 4, 0x09]).count(), 2);
+	assert!(write(&[a, RgbaImage::new(2, 2)], 120).is_err());
+	Ok(())
+}
@@ -3,7 +3,7 @@ use std::collections::BTreeSet;
 
 use gospel::read::{Reader, Le as _};
 use gospel::write::{Writer, Le as _, Label};
-use image::Rgba;
+use image::{Rgba, RgbaImage};
 use crate::util::*;
 
 #[derive(Clone, PartialEq)]
@@ -86,6 +86,25 @@ impl<'a> std::fmt::Debug for Itc<'a> {
 	}
 }
 
+impl Itc<'_> {
+	/// Decodes the image shown by the given frame, if it has one.
+	pub fn image(&self, frame: usize) -> Result<Option<RgbaImage>, Error> {
+		let Some(index) = self.frames.get(frame).and_then(|a| a.index) else {
+			return Ok(None)
+		};
+		let data = &self.content[index];
+		if data.starts_with(b"ITP\xFF") {
+			Ok(Some(crate::itp32::read(data)?.to_rgba(0)))
+		} else {
+			let mut itp = crate::itp::read(data)?;
+			if let Some(pal) = &self.palette {
+				itp.palette = pal.clone();
+			}
+			Ok(Some(itp.to_rgba()))
+		}
+	}
+}
+
 #[allow(clippy::type_complexity)]
 pub fn read(data: &[u8]) -> Result<Itc, Error> {
 	let mut f = Reader::new(data);
@@ -62,7 +62,7 @@ pub fn write1000(itp: &Itp) -> Result<Vec<u8>, Error> {
 	f.u32(1000);
 	f.u32(itp.image.width());
 	f.u32(itp.image.height());
-	write_palette(&itp.palette, &mut f);
+	write_palette(&full_palette(itp)?, &mut f);
 	f.slice(itp.image.as_raw());
 	Ok(f.finish()?)
 }
@@ -84,7 +84,7 @@ pub fn write1002(itp: &Itp) -> Result<Vec<u8>, Error> {
 	f.u32(itp.image.height());
 	compress(&mut f, &{
 		let mut g = Writer::new();
-		write_palette(&itp.palette, &mut g);
+		write_palette(&full_palette(itp)?, &mut g);
 		g.finish()?
 	}, bzip::CompressMode::default());
 	compress(&mut f, itp.image.as_raw(), bzip::CompressMode::default());
@@ -259,6 +259,14 @@ pub(crate) fn read_palette(pal_size: u32, g: &mut Reader) -> Result<Vec<Rgba<u8>
 	Ok(palette)
 }
 
+/// 1000 and 1002 always have 256 colors, so pad smaller palettes.
+fn full_palette(itp: &Itp) -> Result<Vec<Rgba<u8>>, Error> {
+	ensure!(itp.palette.len() <= 256, "too many colors");
+	let mut pal = itp.palette.clone();
+	pal.resize(256, Rgba([0, 0, 0, 0]));
+	Ok(pal)
+}
+
 pub(crate) fn write_palette(pal: &[Rgba<u8>], g: &mut Writer) {
 	for p in pal {
 		g.array(p.0);
@@ -22,26 +22,47 @@ impl Itp32 {
 		image(width, height, pixels).unwrap()
 	}
 
-	#[cfg(feature = "intel_tex_2")]
-	pub fn from_rgba(img: &RgbaImage) -> Itp32 {
-		let a = intel_tex_2::bc7::compress_blocks(
-			&intel_tex_2::bc7::alpha_very_fast_settings(),
-			&intel_tex_2::RgbaSurface {
-				width: img.width(),
-				height: img.height(),
-				stride: img.width() * 4,
-				data: img,
-			}
-		);
-		let data = a
-			.chunks_exact(16)
-			.map(|a| u128::from_le_bytes(a.try_into().unwrap()))
-			.collect();
+	pub fn from_rgba(img: &RgbaImage, quality: bc7::Quality) -> Itp32 {
 		Itp32 {
 			width: img.width() as usize,
 			height: img.height() as usize,
-			levels: vec![data],
+			levels: vec![encode_level(img, quality)],
+		}
+	}
+
+	/// Encodes an image along with as many mipmap levels as the format allows, that is, until the
+	/// size is no longer divisible into 4×4 blocks.
+	pub fn from_rgba_mipmapped(img: &RgbaImage, filter: crate::mip::Filter, quality: bc7::Quality) -> Result<Itp32, Error> {
+		let mut levels = vec![img.clone()];
+		loop {
+			let last = levels.last().unwrap();
+			let (w, h) = (last.width() / 2, last.height() / 2);
+			if w == 0 || h == 0 || w % 4 != 0 || h % 4 != 0 {
+				break
+			}
+			levels.push(crate::mip::downsample(last, filter));
 		}
+		Self::from_rgba_levels(&levels, quality)
+	}
+
+	/// Encodes each of the given images as one mipmap level. Each must be half the size of the
+	/// previous one.
+	pub fn from_rgba_levels(levels: &[RgbaImage], quality: bc7::Quality) -> Result<Itp32, Error> {
+		ensure!(!levels.is_empty(), "itp32: no levels");
+		let width = levels[0].width() as usize;
+		let height = levels[0].height() as usize;
+		for (n, img) in levels.iter().enumerate() {
+			let (w, h) = (img.width() as usize, img.height() as usize);
+			ensure!((w, h) == (width >> n, height >> n), "itp32: level {n} is {w}×{h}, expected {}×{}", width >> n, height >> n);
+			if n > 0 {
+				ensure!(w % 4 == 0 && h % 4 == 0 && w > 0 && h > 0, "itp32: level {n} is {w}×{h}, which is not a multiple of 4");
+			}
+		}
+		Ok(Itp32 {
+			width,
+			height,
+			levels: levels.iter().map(|img| encode_level(img, quality)).collect(),
+		})
 	}
 
 	pub fn levels(&self) -> usize {
@@ -76,6 +97,21 @@ impl Itp32 {
 		Some(Itp32 { width, height, levels })
 	}
 
+	/// Converts any block-compressed texture that [`dds`](crate::dds) can read. BC7 is taken as
+	/// is, while the others are decoded and encoded again as BC7, keeping the mipmap levels that
+	/// itp32 can hold.
+	#[cfg(feature = "ddsfile")]
+	pub fn from_dds(dds: &ddsfile::Dds, quality: bc7::Quality) -> Result<Itp32, Error> {
+		if let Some(itp) = Self::from_bc7_dds(dds) {
+			return Ok(itp)
+		}
+		let mut levels = crate::dds::to_rgba_levels(dds)?;
+		if let Some(n) = levels.iter().skip(1).position(|l| l.width() % 4 != 0 || l.height() % 4 != 0) {
+			levels.truncate(n + 1);
+		}
+		Self::from_rgba_levels(&levels, quality)
+	}
+
 	#[cfg(feature = "ddsfile")]
 	pub fn to_bc7_dds(self) -> ddsfile::Dds {
 		let mut dds = ddsfile::Dds::new_dxgi(ddsfile::NewDxgiParams {
@@ -95,6 +131,27 @@ impl Itp32 {
 	}
 }
 
+fn encode_level(img: &RgbaImage, quality: bc7::Quality) -> Vec<u128> {
+	let width = img.width() as usize;
+	let height = img.height() as usize;
+	let mut data = Vec::with_capacity(width.div_ceil(4) * height.div_ceil(4));
+	for by in (0..height).step_by(4) {
+		for bx in (0..width).step_by(4) {
+			let mut block = [[[0; 4]; 4]; 4];
+			for (y, row) in block.iter_mut().enumerate() {
+				for (x, px) in row.iter_mut().enumerate() {
+					// Pixels past the edge repeat the last ones, so they don't affect the fit.
+					let x = (bx + x).min(width - 1) as u32;
+					let y = (by + y).min(height - 1) as u32;
+					*px = img.get_pixel(x, y).0;
+				}
+			}
+			data.push(bc7::encode(&block, quality));
+		}
+	}
+	data
+}
+
 pub fn read(data: &[u8]) -> Result<Itp32, Error> {
 	let mut f = Reader::new(data);
 	f.check(b"ITP\xFF")?;
@@ -328,3 +385,20 @@ fn test() -> Result<(), Box<dyn std::error::Error>> {
 
 	Ok(())
 }
+
+#[test]
+fn encode() {
+	let img = RgbaImage::from_fn(10, 6, |x, y| image::Rgba([x as u8 * 25, y as u8 * 40, 128, 255 - x as u8 * 10]));
+	let itp = Itp32::from_rgba(&img, bc7::Quality::Normal);
+	assert_eq!(itp.levels[0].len(), 3 * 2);
+	let img2 = Itp32 { width: 12, height: 8, ..itp }.to_rgba(0);
+	for (x, y, p) in img.enumerate_pixels() {
+		let q = img2.get_pixel(x, y);
+		assert!(p.0.iter().zip(q.0).all(|(a, b)| a.abs_diff(b) <= 8), "{p:?} {q:?}");
+	}
+
+	let img = RgbaImage::new(32, 8);
+	let itp = Itp32::from_rgba_mipmapped(&img, crate::mip::Filter::Box, bc7::Quality::Fast).unwrap();
+	assert_eq!(itp.levels.iter().map(Vec::len).collect::<Vec<_>>(), [16, 4]);
+	assert!(Itp32::from_rgba_levels(&[img, RgbaImage::new(16, 8)], bc7::Quality::Fast).is_err());
+}
@@ -1,9 +1,17 @@
 #![feature(error_generic_member_access)]
 #![feature(decl_macro)]
 
+pub mod anim;
 pub mod ch;
 pub mod chcp;
+#[cfg(feature = "convert")]
+pub mod convert;
+#[cfg(feature = "ddsfile")]
+pub mod dds;
+pub mod gif;
 pub mod itp;
 pub mod itp32;
 pub mod itc;
+pub mod mip;
+pub mod quant;
 pub mod util;
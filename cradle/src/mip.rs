@@ -0,0 +1,87 @@
+//! Downsampling images for mipmaps.
+//!
+//! Filtering is done on linear, alpha-premultiplied colors, so that averaging a bright and a dark
+//! pixel gives the brightness one would expect, and transparent pixels don't bleed their color
+//! into their neighbors.
+
+use image::{ImageBuffer, Rgba, RgbaImage};
+use image::imageops::FilterType;
+
+type LinearImage = ImageBuffer<Rgba<f32>, Vec<f32>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Filter {
+	/// Average of each 2×2 square. Fast and sharp, but prone to aliasing.
+	#[default]
+	Box,
+	/// Lanczos with a radius of three. Smoother, at the cost of some ringing near edges.
+	Lanczos,
+}
+
+/// Halves the size of an image, rounding down but not below 1.
+pub fn downsample(img: &RgbaImage, filter: Filter) -> RgbaImage {
+	let w = (img.width() / 2).max(1);
+	let h = (img.height() / 2).max(1);
+	let lin = to_linear(img);
+	let out = match filter {
+		Filter::Box => LinearImage::from_fn(w, h, |x, y| {
+			let mut sum = [0.; 4];
+			for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+				let x = (2 * x + dx).min(img.width() - 1);
+				let y = (2 * y + dy).min(img.height() - 1);
+				for (s, v) in sum.iter_mut().zip(lin.get_pixel(x, y).0) {
+					*s += v / 4.;
+				}
+			}
+			Rgba(sum)
+		}),
+		Filter::Lanczos => image::imageops::resize(&lin, w, h, FilterType::Lanczos3),
+	};
+	from_linear(&out)
+}
+
+fn to_linear(img: &RgbaImage) -> LinearImage {
+	let lut: [f32; 256] = std::array::from_fn(|i| {
+		let c = i as f32 / 255.;
+		if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+	});
+	LinearImage::from_fn(img.width(), img.height(), |x, y| {
+		let Rgba([r, g, b, a]) = *img.get_pixel(x, y);
+		let a = a as f32 / 255.;
+		Rgba([lut[r as usize] * a, lut[g as usize] * a, lut[b as usize] * a, a])
+	})
+}
+
+fn from_linear(img: &LinearImage) -> RgbaImage {
+	fn srgb(c: f32) -> f32 {
+		if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1. / 2.4) - 0.055 }
+	}
+	let q = |v: f32| (v * 255.).round().clamp(0., 255.) as u8;
+	RgbaImage::from_fn(img.width(), img.height(), |x, y| {
+		let Rgba([r, g, b, a]) = *img.get_pixel(x, y);
+		let a = a.clamp(0., 1.);
+		if a <= 0. {
+			return Rgba([0, 0, 0, 0])
+		}
+		let c = |v: f32| q(srgb((v / a).clamp(0., 1.)));
+		Rgba([c(r), c(g), c(b), q(a)])
+	})
+}
+
+#[test]
+fn downsample_checkers() {
+	let img = RgbaImage::from_fn(8, 6, |x, y| if (x + y) % 2 == 0 { Rgba([255, 255, 255, 255]) } else { Rgba([0, 0, 0, 255]) });
+	for filter in [Filter::Box, Filter::Lanczos] {
+		let out = downsample(&img, filter);
+		assert_eq!(out.dimensions(), (4, 3));
+		for p in out.pixels() {
+			// Half of full intensity in linear light, not 128.
+			assert!(p.0[0].abs_diff(188) <= 4, "{filter:?} {p:?}");
+			assert_eq!(p.0[3], 255);
+		}
+	}
+
+	// Transparent pixels don't darken their neighbors.
+	let img = RgbaImage::from_fn(2, 2, |x, _| if x == 0 { Rgba([200, 100, 0, 255]) } else { Rgba([0, 0, 0, 0]) });
+	assert_eq!(downsample(&img, Filter::Box).get_pixel(0, 0), &Rgba([200, 100, 0, 128]));
+}
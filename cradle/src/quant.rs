@@ -0,0 +1,252 @@
+//! Reducing truecolor images to at most 256 colors, for the indexed itp formats.
+//!
+//! Colors are compared premultiplied by alpha, so that differences in the color of nearly
+//! transparent pixels matter little, and fully transparent pixels are all considered equal.
+//! The palette is picked by median cut, then refined with a few rounds of k-means.
+
+use std::collections::HashMap;
+
+use image::{GrayImage, Rgba, RgbaImage};
+
+use crate::itp::Itp;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Palette {
+	/// Pick a new palette.
+	New,
+	/// Only use colors from the given palette.
+	Reuse(Vec<Rgba<u8>>),
+	/// Keep the given palette as is, adding new colors after it if there is room.
+	Extend(Vec<Rgba<u8>>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+	/// Maximum size of the palette, at most 256.
+	pub colors: usize,
+	/// Whether to use Floyd–Steinberg dithering.
+	pub dither: bool,
+	pub palette: Palette,
+}
+
+impl Default for Options {
+	fn default() -> Self {
+		Options {
+			colors: 256,
+			dither: false,
+			palette: Palette::New,
+		}
+	}
+}
+
+const KMEANS_ROUNDS: usize = 4;
+
+type Color = [f32; 4];
+
+pub fn quantize(image: &RgbaImage, opts: &Options) -> Itp {
+	let colors = opts.colors.clamp(1, 256);
+	let (fixed, n_new) = match &opts.palette {
+		Palette::New => (&[][..], colors),
+		Palette::Reuse(p) => (&p[..], 0),
+		Palette::Extend(p) => (&p[..], colors.saturating_sub(p.len())),
+	};
+	let fixed = &fixed[..fixed.len().min(256)];
+
+	let mut hist = HashMap::<Rgba<u8>, u32>::new();
+	for &p in image.pixels() {
+		*hist.entry(normalize(p)).or_default() += 1;
+	}
+	hist.retain(|k, _| !fixed.contains(k));
+	let mut hist = hist.into_iter().collect::<Vec<_>>();
+	hist.sort_by_key(|a| a.0.0);
+
+	let mut palette = fixed.to_vec();
+	if hist.len() <= n_new {
+		palette.extend(hist.iter().map(|a| a.0));
+	} else if n_new > 0 {
+		let hist = hist.iter().map(|&(c, n)| (premul(c), n as f32)).collect::<Vec<_>>();
+		let mut new = median_cut(&hist, n_new);
+		kmeans(&hist, fixed.iter().map(|&c| premul(c)).collect(), &mut new);
+		palette.extend(new.into_iter().map(unpremul));
+	}
+	if palette.is_empty() {
+		palette.push(Rgba([0, 0, 0, 0]));
+	}
+
+	let pal = palette.iter().map(|&c| premul(c)).collect::<Vec<_>>();
+	let pixels = if opts.dither {
+		dither(image, &pal)
+	} else {
+		let mut cache = HashMap::new();
+		image.pixels()
+			.map(|&p| *cache.entry(p).or_insert_with(|| nearest(&pal, premul(normalize(p))) as u8))
+			.collect()
+	};
+	Itp {
+		palette,
+		image: GrayImage::from_vec(image.width(), image.height(), pixels).unwrap(),
+	}
+}
+
+fn normalize(p: Rgba<u8>) -> Rgba<u8> {
+	if p.0[3] == 0 { Rgba([0, 0, 0, 0]) } else { p }
+}
+
+fn premul(Rgba([r, g, b, a]): Rgba<u8>) -> Color {
+	let a = a as f32;
+	[r as f32 * a / 255., g as f32 * a / 255., b as f32 * a / 255., a]
+}
+
+fn unpremul([r, g, b, a]: Color) -> Rgba<u8> {
+	let c = |v: f32| if a <= 0. { 0 } else { (v * 255. / a).round().clamp(0., 255.) as u8 };
+	Rgba([c(r), c(g), c(b), a.round().clamp(0., 255.) as u8])
+}
+
+fn dist(a: &Color, b: &Color) -> f32 {
+	a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum()
+}
+
+fn nearest(pal: &[Color], c: Color) -> usize {
+	let mut best = (0, f32::INFINITY);
+	for (i, p) in pal.iter().enumerate() {
+		let d = dist(p, &c);
+		if d < best.1 {
+			best = (i, d);
+		}
+	}
+	best.0
+}
+
+fn median_cut(hist: &[(Color, f32)], n: usize) -> Vec<Color> {
+	fn range(b: &[(Color, f32)]) -> (usize, f32) {
+		(0..4).map(|k| {
+			let lo = b.iter().map(|a| a.0[k]).fold(f32::INFINITY, f32::min);
+			let hi = b.iter().map(|a| a.0[k]).fold(f32::NEG_INFINITY, f32::max);
+			(k, hi - lo)
+		}).max_by(|a, b| a.1.total_cmp(&b.1)).unwrap()
+	}
+
+	let mut boxes = vec![hist.to_vec()];
+	while boxes.len() < n {
+		// Split the box with the most spread, weighted by how many pixels it covers.
+		let Some((i, k)) = boxes.iter().enumerate()
+			.filter(|a| a.1.len() > 1)
+			.map(|(i, b)| {
+				let (k, r) = range(b);
+				let w = b.iter().map(|a| a.1).sum::<f32>();
+				(i, k, r * w.sqrt())
+			})
+			.max_by(|a, b| a.2.total_cmp(&b.2))
+			.map(|a| (a.0, a.1))
+		else { break };
+
+		let mut b = boxes.swap_remove(i);
+		b.sort_by(|x, y| x.0[k].total_cmp(&y.0[k]));
+		let total = b.iter().map(|a| a.1).sum::<f32>();
+		let mut acc = 0.;
+		let mut mid = b.len() - 1;
+		for (j, a) in b.iter().enumerate() {
+			acc += a.1;
+			if acc >= total / 2. {
+				mid = j;
+				break
+			}
+		}
+		let rest = b.split_off((mid + 1).min(b.len() - 1));
+		boxes.push(b);
+		boxes.push(rest);
+	}
+
+	boxes.iter().map(|b| mean(b.iter().map(|a| (&a.0, a.1)))).collect()
+}
+
+fn mean<'a>(it: impl Iterator<Item=(&'a Color, f32)>) -> Color {
+	let mut sum = [0.; 4];
+	let mut total = 0.;
+	for (c, w) in it {
+		for k in 0..4 {
+			sum[k] += c[k] * w;
+		}
+		total += w;
+	}
+	sum.map(|a| a / total)
+}
+
+/// Moves the `new` colors towards the center of the pixels closest to them. The `fixed` colors
+/// take part in the assignment, but are not moved.
+fn kmeans(hist: &[(Color, f32)], fixed: Vec<Color>, new: &mut [Color]) {
+	let nf = fixed.len();
+	let mut pal = fixed;
+	pal.extend_from_slice(new);
+	for _ in 0..KMEANS_ROUNDS {
+		let mut sums = vec![([0.; 4], 0.); pal.len()];
+		for (c, w) in hist {
+			let (sum, total) = &mut sums[nearest(&pal, *c)];
+			for k in 0..4 {
+				sum[k] += c[k] * w;
+			}
+			*total += w;
+		}
+		for (p, (sum, total)) in pal.iter_mut().zip(sums).skip(nf) {
+			if total > 0. {
+				*p = sum.map(|a: f32| a / total);
+			}
+		}
+	}
+	new.copy_from_slice(&pal[nf..]);
+}
+
+fn dither(image: &RgbaImage, pal: &[Color]) -> Vec<u8> {
+	let (w, h) = (image.width() as usize, image.height() as usize);
+	let mut err = vec![[0f32; 4]; 2 * (w + 2)];
+	let mut out = Vec::with_capacity(w * h);
+	for y in 0..h {
+		let (cur, next) = err.split_at_mut(w + 2);
+		for x in 0..w {
+			let p = premul(normalize(*image.get_pixel(x as u32, y as u32)));
+			let mut c = [0.; 4];
+			for k in 0..4 {
+				c[k] = p[k] + cur[x + 1][k];
+			}
+			// Keep the color within what premultiplication allows, or errors accumulate without bound.
+			c[3] = c[3].clamp(0., 255.);
+			for k in 0..3 {
+				c[k] = c[k].clamp(0., c[3]);
+			}
+			let i = nearest(pal, c);
+			out.push(i as u8);
+			for k in 0..4 {
+				let e = c[k] - pal[i][k];
+				cur[x + 2][k] += e * 7. / 16.;
+				next[x][k] += e * 3. / 16.;
+				next[x + 1][k] += e * 5. / 16.;
+				next[x + 2][k] += e * 1. / 16.;
+			}
+		}
+		err.copy_within(w + 2.., 0);
+		err[w + 2..].fill([0.; 4]);
+	}
+	out
+}
+
+#[test]
+fn quantize_gradient() {
+	let img = RgbaImage::from_fn(16, 8, |x, y| Rgba([x as u8 * 16, y as u8 * 32, 0, if x == 0 { 0 } else { 255 }]));
+	let itp = quantize(&img, &Options { colors: 200, ..Options::default() });
+	// 15×8 opaque colors and one transparent, which all fit.
+	assert_eq!(itp.palette.len(), 121);
+	assert_eq!(itp.to_rgba(), RgbaImage::from_fn(16, 8, |x, y| normalize(*img.get_pixel(x, y))));
+
+	let itp = quantize(&img, &Options { colors: 16, dither: true, ..Options::default() });
+	assert_eq!(itp.palette.len(), 16);
+	assert!(itp.palette.contains(&Rgba([0, 0, 0, 0])));
+
+	let base = vec![Rgba([0, 0, 0, 0]), Rgba([255, 0, 0, 255])];
+	let itp = quantize(&img, &Options { colors: 4, palette: Palette::Extend(base.clone()), ..Options::default() });
+	assert_eq!(itp.palette[..2], base);
+	assert_eq!(itp.palette.len(), 4);
+	let itp = quantize(&img, &Options { palette: Palette::Reuse(base.clone()), ..Options::default() });
+	assert_eq!(itp.palette, base);
+	assert_eq!(itp.image.get_pixel(0, 0).0[0], 0);
+	assert_eq!(itp.image.get_pixel(15, 0).0[0], 1);
+}
@@ -0,0 +1,397 @@
+#![feature(decl_macro)]
+
+use std::path::{Path, PathBuf};
+
+use clap::{Parser, Subcommand, ValueHint};
+use kiseki::translate::{self, Entry, Names, Str, Translatable};
+use kiseki::{world, xref};
+use themelios::tables;
+use themelios::text::layout::{self, Metrics};
+use themelios::types::Game;
+
+#[derive(Debug, Clone, Parser)]
+struct Cli {
+	/// Game the files belong to.
+	#[clap(long, short, global = true, hide_possible_values = true)]
+	game: Option<CliGame>,
+
+	#[command(subcommand)]
+	command: Command,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+enum Command {
+	/// Extract translatable strings into a .po or .xliff file.
+	Extract {
+		/// Where to write the strings. The format is determined by the extension.
+		#[clap(long, short, required = true, value_hint = ValueHint::FilePath)]
+		output: PathBuf,
+
+		/// Name table to use for naming speakers.
+		#[clap(long, value_hint = ValueHint::FilePath)]
+		names: Option<PathBuf>,
+
+		/// Source language, written into xliff files.
+		#[clap(long, default_value = "ja")]
+		lang: String,
+
+		/// Scena and table files to extract strings from.
+		#[clap(required = true, value_hint = ValueHint::FilePath)]
+		files: Vec<PathBuf>,
+	},
+
+	/// Write translated strings from a .po or .xliff file back into the binary files.
+	Inject {
+		/// The translated strings.
+		#[clap(long, short, required = true, value_hint = ValueHint::FilePath)]
+		translation: PathBuf,
+
+		/// Directory to place the translated files in.
+		#[clap(long, short, required = true, value_hint = ValueHint::DirPath)]
+		output: PathBuf,
+
+		/// Re-wrap dialogue that does not fit in the text box.
+		#[clap(long)]
+		wrap: bool,
+
+		/// Glyph widths to use for --wrap, as lines of `char width`.
+		#[clap(long, requires = "wrap", value_hint = ValueHint::FilePath)]
+		glyphs: Option<PathBuf>,
+
+		/// The original files.
+		#[clap(required = true, value_hint = ValueHint::FilePath)]
+		files: Vec<PathBuf>,
+	},
+
+	/// Find where flags, variables, scenes, battles and items are used.
+	Xref {
+		/// What to search for, such as `flag[12]`, `var[3]`, or `scene[t0100]`.
+		#[clap(long, short)]
+		query: Vec<String>,
+
+		/// Write all uses as JSON.
+		#[clap(long, value_hint = ValueHint::FilePath)]
+		json: Option<PathBuf>,
+
+		/// Write the scene transitions as a Graphviz graph.
+		#[clap(long, value_hint = ValueHint::FilePath)]
+		dot: Option<PathBuf>,
+
+		/// Scena files to scan.
+		#[clap(required = true, value_hint = ValueHint::FilePath)]
+		files: Vec<PathBuf>,
+	},
+
+	/// Build a graph of how the maps connect, from scena, `._en` and `t_world._dt` files.
+	///
+	/// Connections that lead to missing files or entrances are listed as they are found.
+	World {
+		/// Write the graph as JSON.
+		#[clap(long, value_hint = ValueHint::FilePath)]
+		json: Option<PathBuf>,
+
+		/// Write the graph as a Graphviz graph.
+		#[clap(long, value_hint = ValueHint::FilePath)]
+		dot: Option<PathBuf>,
+
+		/// Files to read. Anything that is not an entrance or world table is read as scena.
+		#[clap(required = true, value_hint = ValueHint::FilePath)]
+		files: Vec<PathBuf>,
+	},
+
+	/// Convert a BGM table between `t_bgmtbl._dt` and the text form shipped with the Evolution versions.
+	///
+	/// Text files are recognized by the .txt extension. Since the binary tables do not name the
+	/// tracks, converting to text names them by number. Comments, such as the track descriptions
+	/// in the shipped files, are not kept either, so converting text to binary and back loses them.
+	Bgmtbl {
+		/// Where to write the converted table. Defaults to the input with the other extension.
+		#[clap(long, short, value_hint = ValueHint::FilePath)]
+		output: Option<PathBuf>,
+
+		/// The table to convert.
+		#[clap(value_hint = ValueHint::FilePath)]
+		file: PathBuf,
+	},
+}
+
+// Same as in calmare-cli.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum CliGame {
+	#[value(name = "fc")] Fc,
+	#[value(name = "fc_e")] FcEvo,
+	#[value(name = "fc_k")] FcKai,
+	#[value(name = "sc")] Sc,
+	#[value(name = "sc_e")] ScEvo,
+	#[value(name = "sc_k")] ScKai,
+	#[value(name = "tc")] Tc,
+	#[value(name = "tc_e")] TcEvo,
+	#[value(name = "tc_k")] TcKai,
+
+	#[value(name = "zero")] Zero,
+	#[value(name = "zero_e")] ZeroEvo,
+	#[value(name = "zero_k")] ZeroKai,
+	#[value(name = "ao")] Ao,
+	#[value(name = "ao_e")] AoEvo,
+	#[value(name = "ao_k")] AoKai,
+}
+
+fn main() -> eyre::Result<()> {
+	let cli = Cli::parse();
+	let game = || cli.game.map(cli_game).ok_or_else(|| eyre::eyre!("--game is required"));
+
+	match &cli.command {
+		Command::Extract { output, names, lang, files } => {
+			let game = game()?;
+			let names = match names {
+				Some(path) => load_names(game, path)?,
+				None => Names::new(),
+			};
+			let mut entries = Vec::new();
+			for path in files {
+				let (name, file) = load(game, path)?;
+				entries.extend(file.extract(&name, &names));
+			}
+			let out = match format(output)? {
+				Format::Po => translate::po::write(&entries),
+				Format::Xliff => translate::xliff::write(&entries, lang, None),
+			};
+			std::fs::write(output, out)?;
+		}
+
+		Command::Inject { translation, output, wrap, glyphs, files } => {
+			let game = game()?;
+			let mut metrics = Metrics::for_game(game);
+			if let Some(path) = glyphs {
+				metrics.glyphs = layout::parse_glyphs(&std::fs::read_to_string(path)?)
+					.map_err(|line| eyre::eyre!("{}:{line}: invalid glyph width", path.display()))?;
+			}
+			let src = std::fs::read_to_string(translation)?;
+			let entries = match format(translation)? {
+				Format::Po => translate::po::read(&src)?,
+				Format::Xliff => translate::xliff::read(&src)?,
+			};
+			std::fs::create_dir_all(output)?;
+			let mut n_errors = 0;
+			for path in files {
+				let (name, mut file) = load(game, path)?;
+				for e in file.inject(&name, &entries) {
+					eprintln!("{e}");
+					n_errors += 1;
+				}
+				if *wrap {
+					file.wrap(&metrics);
+				}
+				std::fs::write(output.join(&name), file.write(game)?)?;
+			}
+			if n_errors != 0 {
+				eyre::bail!("{n_errors} strings could not be injected");
+			}
+		}
+
+		Command::Xref { query, json, dot, files } => {
+			let game = game()?;
+			let lookup = themelios::lookup::default_for(game);
+			let mut index = xref::Index::new();
+			for path in files {
+				let (name, file) = load(game, path)?;
+				let File::Scena(scena) = file else {
+					eyre::bail!("not a scena file: {}", path.display());
+				};
+				index.add(&name, &scena);
+			}
+			for q in query {
+				let Some(target) = xref::Target::parse(q, lookup) else {
+					eyre::bail!("invalid query: {q}");
+				};
+				for u in index.find(target) {
+					let access = match u.access {
+						xref::Access::Read => "read",
+						xref::Access::Write => "write",
+					};
+					println!("{}\t{}\t{access}\t{target}", u.file, u.location);
+				}
+			}
+			if let Some(path) = json {
+				std::fs::write(path, format!("{:#}\n", index.to_json(lookup)))?;
+			}
+			if let Some(path) = dot {
+				std::fs::write(path, index.to_dot(lookup))?;
+			}
+		}
+
+		Command::World { json, dot, files } => {
+			use tables::registry::Kind;
+			let game = game()?;
+			let mut graph = world::Graph::new(themelios::lookup::default_for(game));
+			for path in files {
+				let Some(name) = path.file_name().and_then(|a| a.to_str()) else {
+					eyre::bail!("file has no name: {}", path.display());
+				};
+				let data = std::fs::read(path)?;
+				match Kind::from_name(game, name) {
+					Some(Kind::ED6Ent) => graph.add_entrances(name, &tables::ent::ED6Ent::read(&data)?),
+					Some(Kind::ED6World) => graph.add_world(&tables::world::ED6World::read(&data)?),
+					Some(_) => eyre::bail!("not a scena, entrance or world file: {}", path.display()),
+					None => graph.add_scena(name, &kiseki::Scena::read(game, &data)?),
+				}
+			}
+			for (edge, problem) in graph.problems() {
+				eprintln!("{}\t{}\t{problem}", edge.from, edge.via);
+			}
+			if let Some(path) = json {
+				std::fs::write(path, format!("{:#}\n", graph.to_json()))?;
+			}
+			if let Some(path) = dot {
+				std::fs::write(path, graph.to_dot())?;
+			}
+		}
+
+		Command::Bgmtbl { output, file } => {
+			use tables::bgm::{BgmTbl, ED6Bgm, ED7Bgm};
+			let game = game()?;
+			if file.extension().is_some_and(|e| e.eq_ignore_ascii_case("txt")) {
+				let tbl = BgmTbl::parse(&std::fs::read_to_string(file)?)?;
+				let data = if game.is_ed7() {
+					ED7Bgm::write(&tbl.to_ed7()?)?
+				} else {
+					ED6Bgm::write(&tbl.to_ed6()?)?
+				};
+				let output = output.clone().unwrap_or_else(|| file.with_extension("_dt"));
+				std::fs::write(output, data)?;
+			} else {
+				let data = std::fs::read(file)?;
+				let tbl = if game.is_ed7() {
+					BgmTbl::from_ed7(&ED7Bgm::read(&data)?)
+				} else {
+					BgmTbl::from_ed6(&ED6Bgm::read(&data)?)
+				};
+				let output = output.clone().unwrap_or_else(|| file.with_extension("txt"));
+				std::fs::write(output, tbl.write())?;
+			}
+		}
+	}
+
+	Ok(())
+}
+
+enum Format {
+	Po,
+	Xliff,
+}
+
+fn format(path: &Path) -> eyre::Result<Format> {
+	match path.extension().and_then(|a| a.to_str()) {
+		Some("po" | "pot") => Ok(Format::Po),
+		Some("xlf" | "xliff") => Ok(Format::Xliff),
+		_ => eyre::bail!("unknown translation format: {}", path.display()),
+	}
+}
+
+#[allow(clippy::large_enum_variant)]
+enum File {
+	Scena(kiseki::Scena),
+	ED6Name((Vec<tables::name::ED6Name>, Vec<tables::name::ED6Name>)),
+	ED7Name(Vec<tables::name::ED7Name>),
+	ED6Quest(Vec<tables::quest::ED6Quest>),
+	ED7Quest(Vec<tables::quest::ED7Quest>),
+	Town(Vec<tables::town::Town>),
+}
+
+macro each($v:expr, $a:ident => $e:expr) {
+	match $v {
+		File::Scena($a) => $e,
+		File::ED6Name($a) => $e,
+		File::ED7Name($a) => $e,
+		File::ED6Quest($a) => $e,
+		File::ED7Quest($a) => $e,
+		File::Town($a) => $e,
+	}
+}
+
+impl File {
+	fn extract(&self, name: &str, names: &Names) -> Vec<Entry> {
+		each!(self, a => translate::extract(name, a, names))
+	}
+
+	fn inject(&mut self, name: &str, entries: &[Entry]) -> Vec<translate::Error> {
+		each!(self, a => translate::inject(name, a, &Names::new(), entries))
+	}
+
+	/// Wraps any dialogue that overflows its text box.
+	fn wrap(&mut self, metrics: &Metrics) {
+		each!(self, a => a.walk(&Names::new(), &mut |slot| {
+			if let Str::Text(text) = slot.value {
+				if !metrics.check(text).is_empty() {
+					*text = metrics.wrap(text);
+				}
+			}
+		}))
+	}
+
+	fn write(&self, game: Game) -> eyre::Result<Vec<u8>> {
+		Ok(match self {
+			File::Scena(a) => kiseki::Scena::write(game, a)?,
+			File::ED6Name(a) => tables::name::ED6Name::write(game, &a.0, &a.1)?,
+			File::ED7Name(a) => tables::name::ED7Name::write(a)?,
+			File::ED6Quest(a) => tables::quest::ED6Quest::write(a)?,
+			File::ED7Quest(a) => tables::quest::ED7Quest::write(a)?,
+			File::Town(a) => tables::town::Town::write(game, a)?,
+		})
+	}
+}
+
+fn load(game: Game, path: &Path) -> eyre::Result<(String, File)> {
+	let Some(name) = path.file_name().and_then(|a| a.to_str()) else {
+		eyre::bail!("file has no name: {}", path.display());
+	};
+	let data = std::fs::read(path)?;
+	let lower = name.to_lowercase();
+	let file = if lower.starts_with("t_name") {
+		if game.is_ed7() {
+			File::ED7Name(tables::name::ED7Name::read(&data)?)
+		} else {
+			File::ED6Name(tables::name::ED6Name::read(game, &data)?)
+		}
+	} else if lower.starts_with("t_quest") {
+		if game.is_ed7() {
+			File::ED7Quest(tables::quest::ED7Quest::read(&data)?)
+		} else {
+			File::ED6Quest(tables::quest::ED6Quest::read(&data)?)
+		}
+	} else if lower.starts_with("t_town") {
+		File::Town(tables::town::Town::read(game, &data)?)
+	} else {
+		File::Scena(kiseki::Scena::read(game, &data)?)
+	};
+	Ok((name.to_owned(), file))
+}
+
+fn load_names(game: Game, path: &Path) -> eyre::Result<Names> {
+	let data = std::fs::read(path)?;
+	if game.is_ed7() {
+		Ok(translate::names_ed7(&tables::name::ED7Name::read(&data)?))
+	} else {
+		Ok(translate::names_ed6(&tables::name::ED6Name::read(game, &data)?))
+	}
+}
+
+fn cli_game(e: CliGame) -> Game {
+	match e {
+		CliGame::Fc      => Game::Fc,
+		CliGame::FcEvo   => Game::FcEvo,
+		CliGame::FcKai   => Game::FcKai,
+		CliGame::Sc      => Game::Sc,
+		CliGame::ScEvo   => Game::ScEvo,
+		CliGame::ScKai   => Game::ScKai,
+		CliGame::Tc      => Game::Tc,
+		CliGame::TcEvo   => Game::TcEvo,
+		CliGame::TcKai   => Game::TcKai,
+		CliGame::Zero    => Game::Zero,
+		CliGame::ZeroEvo => Game::ZeroEvo,
+		CliGame::ZeroKai => Game::ZeroKai,
+		CliGame::Ao      => Game::Ao,
+		CliGame::AoEvo   => Game::AoEvo,
+		CliGame::AoKai   => Game::AoKai,
+	}
+}
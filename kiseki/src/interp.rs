@@ -0,0 +1,380 @@
+//! A headless interpreter for scena code, for testing script logic without running the game.
+//!
+//! Only the logical state of the game is simulated: flags, variables, items, mira, and the party.
+//! Everything else — dialogue, fades, battles, and all the instructions that are not understood —
+//! is recorded as a trace of [`Event`]s, which tests can inspect.
+//!
+//! Forked code (`Fork`, `ForkLoop`, `ForkFunc`) is not run, since it is nearly always animation.
+//! For the same reason, instructions that wait for other threads, such as `FlagWaitSet`, do not
+//! wait.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use themelios::scena::code::{Code, Expr, ExprOp, ExprTerm, FlatInsn, Insn, Label, OpKind};
+use themelios::text::Text;
+use themelios::types::*;
+
+use crate::Scena;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Error {
+	#[error("file {0:?} is not loaded")]
+	NoFile(FileId),
+	#[error("file {0:?} has no function {1}")]
+	NoFunction(FileId, u16),
+	#[error("file {0:?} has no include {1}")]
+	NoInclude(FileId, u16),
+	#[error("label {0:?} does not exist")]
+	NoLabel(Label),
+	#[error("menu was opened, but no choices are left")]
+	NoChoice,
+	#[error("malformed expression")]
+	Expr,
+	#[error("division by zero")]
+	DivZero,
+	#[error("instruction limit reached")]
+	Limit,
+}
+
+/// Where the interpreter finds the code to run.
+pub trait Scripts {
+	fn function(&self, file: FileId, func: u16) -> Result<&Code, Error>;
+	/// Resolves the file part of a [`FuncId`] called from `file`.
+	fn include(&self, file: FileId, n: u16) -> Result<FileId, Error>;
+}
+
+impl Scripts for BTreeMap<FileId, Scena> {
+	fn function(&self, file: FileId, func: u16) -> Result<&Code, Error> {
+		let scena = self.get(&file).ok_or(Error::NoFile(file))?;
+		scena.functions().get(func as usize).ok_or(Error::NoFunction(file, func))
+	}
+
+	fn include(&self, file: FileId, n: u16) -> Result<FileId, Error> {
+		let scena = self.get(&file).ok_or(Error::NoFile(file))?;
+		match scena.includes().get(n as usize) {
+			Some(&id) if id != FileId::NONE => Ok(id),
+			_ => Err(Error::NoInclude(file, n)),
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct State {
+	pub flags: BTreeSet<Flag>,
+	pub vars: BTreeMap<Var, i32>,
+	pub attrs: BTreeMap<Attr, i32>,
+	pub globals: BTreeMap<Global, i32>,
+	pub char_attrs: BTreeMap<CharAttr, i32>,
+	pub system_flags: u32,
+	pub items: BTreeMap<ItemId, i32>,
+	pub mira: i32,
+	pub party: Vec<NameId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+	/// Any of `TextTalk`, `TextTalkNamed`, and `TextMessage`.
+	Text { char: CharId, name: Option<TString>, text: Text },
+	Menu { id: MenuId, options: Vec<TString> },
+	NewScene { file: FileId, entrance: u8 },
+	FadeOut(Time),
+	FadeIn(Time),
+	/// `None` for `ED7NpcBattle`, which does not refer to a battle table.
+	Battle(Option<BattleId>),
+	ReturnToTitle,
+	/// An instruction with no simulated effect.
+	Insn(Insn),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Frame {
+	file: FileId,
+	func: u16,
+	pc: usize,
+}
+
+pub struct Machine<'a> {
+	scripts: &'a dyn Scripts,
+	pub state: State,
+	pub trace: Vec<Event>,
+	/// Menu choices to make, in order.
+	pub choices: VecDeque<i32>,
+	/// Results of battles, written to `system[3]`. Defaults to 0 when empty.
+	pub battle_results: VecDeque<i32>,
+	/// Number of instructions to run before giving up, to guard against infinite loops.
+	pub limit: usize,
+	seed: u32,
+}
+
+impl<'a> Machine<'a> {
+	pub fn new(scripts: &'a dyn Scripts, seed: u32) -> Self {
+		Machine {
+			scripts,
+			state: State::default(),
+			trace: Vec::new(),
+			choices: VecDeque::new(),
+			battle_results: VecDeque::new(),
+			limit: 1_000_000,
+			seed,
+		}
+	}
+
+	/// Runs a function until it returns or the script otherwise ends.
+	pub fn run(&mut self, file: FileId, func: u16) -> Result<(), Error> {
+		let mut stack = vec![Frame { file, func, pc: 0 }];
+		while let Some(frame) = stack.last_mut() {
+			if self.limit == 0 {
+				return Err(Error::Limit)
+			}
+			self.limit -= 1;
+
+			let code = self.scripts.function(frame.file, frame.func)?;
+			let Some(insn) = code.get(frame.pc) else {
+				stack.pop();
+				continue
+			};
+			frame.pc += 1;
+			match insn {
+				FlatInsn::Label(_) => {}
+				FlatInsn::Goto(l) => {
+					frame.pc = find_label(code, *l)?;
+				}
+				FlatInsn::Unless(e, l) => {
+					if self.eval(e, None)? == 0 {
+						frame.pc = find_label(code, *l)?;
+					}
+				}
+				FlatInsn::Switch(e, cases, default) => {
+					let v = self.eval(e, None)?;
+					let l = cases.iter().find(|a| a.0 as i32 == v).map_or(*default, |a| a.1);
+					frame.pc = find_label(code, l)?;
+				}
+				FlatInsn::Insn(insn) => {
+					match insn {
+						Insn::Return() => {
+							stack.pop();
+						}
+						Insn::Call(FuncId(a, b)) | Insn::Event(FuncId(a, b)) => {
+							let file = self.scripts.include(frame.file, *a)?;
+							stack.push(Frame { file, func: *b, pc: 0 });
+						}
+						Insn::Hcf() => return Ok(()),
+						Insn::NewScene(file, entrance, ..) => {
+							self.trace.push(Event::NewScene { file: *file, entrance: *entrance });
+							return Ok(())
+						}
+						Insn::ReturnToTitle(..) => {
+							self.trace.push(Event::ReturnToTitle);
+							return Ok(())
+						}
+						insn => self.exec(insn)?,
+					}
+				}
+			}
+		}
+		Ok(())
+	}
+
+	fn exec(&mut self, insn: &Insn) -> Result<(), Error> {
+		let s = &mut self.state;
+		match insn {
+			Insn::FlagSet(f) => { s.flags.insert(*f); }
+			Insn::FlagUnset(f) => { s.flags.remove(f); }
+			Insn::SystemFlagsSet(f) => s.system_flags |= f.0,
+			Insn::SystemFlagsUnset(f) => s.system_flags &= !f.0,
+
+			Insn::Var(k, e) => {
+				let mut v = s.vars.get(k).copied().unwrap_or(0);
+				self.eval(e, Some(&mut v))?;
+				self.state.vars.insert(*k, v);
+			}
+			Insn::Attr(k, e) => {
+				let mut v = s.attrs.get(k).copied().unwrap_or(0);
+				self.eval(e, Some(&mut v))?;
+				self.state.attrs.insert(*k, v);
+			}
+			Insn::Global(k, e) => {
+				let mut v = s.globals.get(k).copied().unwrap_or(0);
+				self.eval(e, Some(&mut v))?;
+				self.state.globals.insert(*k, v);
+			}
+			Insn::CharAttr(k, e) => {
+				let mut v = s.char_attrs.get(k).copied().unwrap_or(0);
+				self.eval(e, Some(&mut v))?;
+				self.state.char_attrs.insert(*k, v);
+			}
+
+			Insn::ItemAdd(item, n) => *s.items.entry(*item).or_default() += *n as i32,
+			Insn::ItemRemove(item, n) => *s.items.entry(*item).or_default() -= *n as i32,
+			Insn::MiraAdd(n) => s.mira += *n as i32,
+			Insn::MiraSub(n) => s.mira -= *n as i32,
+			Insn::PartyAdd(name, ..) => s.party.push(*name),
+			Insn::PartyRemove(name, _) => s.party.retain(|a| a != name),
+			Insn::PartyClear() => s.party.clear(),
+
+			Insn::TextTalk(char, text) | Insn::TextMessage(char, text) => {
+				self.trace.push(Event::Text { char: *char, name: None, text: text.clone() });
+			}
+			Insn::TextTalkNamed(char, name, text) => {
+				self.trace.push(Event::Text { char: *char, name: Some(name.clone()), text: text.clone() });
+			}
+			Insn::Menu(id, _, _, _, options) => {
+				self.trace.push(Event::Menu { id: *id, options: options.clone() });
+			}
+			Insn::MenuWait(var) => {
+				let choice = self.choices.pop_front().ok_or(Error::NoChoice)?;
+				s.vars.insert(*var, choice);
+			}
+			Insn::FadeOut(time, ..) => self.trace.push(Event::FadeOut(*time)),
+			Insn::FadeIn(time, ..) => self.trace.push(Event::FadeIn(*time)),
+			Insn::ED6Battle(id, ..) | Insn::ED7Battle(id, ..) => self.battle(Some(*id)),
+			Insn::ED7NpcBattle(..) => self.battle(None),
+
+			insn => self.trace.push(Event::Insn(insn.clone())),
+		}
+		Ok(())
+	}
+
+	fn battle(&mut self, id: Option<BattleId>) {
+		self.trace.push(Event::Battle(id));
+		let result = self.battle_results.pop_front().unwrap_or(0);
+		self.state.attrs.insert(Attr(3), result);
+	}
+
+	/// Evaluates an expression. Assignments within it write to `target`.
+	pub fn eval(&mut self, e: &Expr, mut target: Option<&mut i32>) -> Result<i32, Error> {
+		let mut stack = Vec::<i32>::new();
+		for term in &e.0 {
+			let v = match term {
+				ExprTerm::Const(v) => *v as i32,
+				ExprTerm::Flag(f) => self.state.flags.contains(f) as i32,
+				ExprTerm::Var(k) => self.state.vars.get(k).copied().unwrap_or(0),
+				ExprTerm::Attr(k) => self.state.attrs.get(k).copied().unwrap_or(0),
+				ExprTerm::CharAttr(k) => self.state.char_attrs.get(k).copied().unwrap_or(0),
+				ExprTerm::Global(k) => self.state.globals.get(k).copied().unwrap_or(0),
+				ExprTerm::Rand => self.rand(),
+				ExprTerm::Insn(insn) => self.query(insn),
+				ExprTerm::Op(op) => match op.kind() {
+					OpKind::Unary => {
+						let a = stack.pop().ok_or(Error::Expr)?;
+						match op {
+							ExprOp::Not => (a == 0) as i32,
+							ExprOp::Neg => a.wrapping_neg(),
+							ExprOp::Inv => !a,
+							_ => unreachable!(),
+						}
+					}
+					OpKind::Binary => {
+						let b = stack.pop().ok_or(Error::Expr)?;
+						let a = stack.pop().ok_or(Error::Expr)?;
+						binop(*op, a, b)?
+					}
+					OpKind::Assign => {
+						let v = stack.pop().ok_or(Error::Expr)?;
+						let t = target.as_deref_mut().ok_or(Error::Expr)?;
+						*t = match op {
+							ExprOp::Ass => v,
+							ExprOp::MulAss => binop(ExprOp::Mul, *t, v)?,
+							ExprOp::DivAss => binop(ExprOp::Div, *t, v)?,
+							ExprOp::ModAss => binop(ExprOp::Mod, *t, v)?,
+							ExprOp::AddAss => binop(ExprOp::Add, *t, v)?,
+							ExprOp::SubAss => binop(ExprOp::Sub, *t, v)?,
+							ExprOp::AndAss => binop(ExprOp::And, *t, v)?,
+							ExprOp::XorAss => binop(ExprOp::Xor, *t, v)?,
+							ExprOp::OrAss => binop(ExprOp::Or, *t, v)?,
+							_ => unreachable!(),
+						};
+						*t
+					}
+				},
+			};
+			stack.push(v);
+		}
+		Ok(stack.pop().unwrap_or(0))
+	}
+
+	/// The value of an instruction used inside an expression.
+	fn query(&mut self, insn: &Insn) -> i32 {
+		match insn {
+			Insn::ItemHas(item, _) => (self.state.items.get(item).copied().unwrap_or(0) > 0) as i32,
+			insn => {
+				self.trace.push(Event::Insn(insn.clone()));
+				0
+			}
+		}
+	}
+
+	// Same generator as the MSVC runtime's `rand`, which also produces 15-bit numbers.
+	fn rand(&mut self) -> i32 {
+		self.seed = self.seed.wrapping_mul(214013).wrapping_add(2531011);
+		((self.seed >> 16) & 0x7FFF) as i32
+	}
+}
+
+fn binop(op: ExprOp, a: i32, b: i32) -> Result<i32, Error> {
+	Ok(match op {
+		ExprOp::Eq => (a == b) as i32,
+		ExprOp::Ne => (a != b) as i32,
+		ExprOp::Lt => (a < b) as i32,
+		ExprOp::Gt => (a > b) as i32,
+		ExprOp::Le => (a <= b) as i32,
+		ExprOp::Ge => (a >= b) as i32,
+		ExprOp::BoolAnd => (a != 0 && b != 0) as i32,
+		ExprOp::And => a & b,
+		ExprOp::Or => a | b,
+		ExprOp::Xor => a ^ b,
+		ExprOp::Add => a.wrapping_add(b),
+		ExprOp::Sub => a.wrapping_sub(b),
+		ExprOp::Mul => a.wrapping_mul(b),
+		ExprOp::Div => a.checked_div(b).ok_or(Error::DivZero)?,
+		ExprOp::Mod => a.checked_rem(b).ok_or(Error::DivZero)?,
+		_ => unreachable!(),
+	})
+}
+
+fn find_label(code: &Code, l: Label) -> Result<usize, Error> {
+	code.iter().position(|a| *a == FlatInsn::Label(l)).ok_or(Error::NoLabel(l))
+}
+
+#[test]
+fn branching() {
+	use themelios::text::TextSegment;
+	struct Single(Vec<Code>);
+	impl Scripts for Single {
+		fn function(&self, file: FileId, func: u16) -> Result<&Code, Error> {
+			self.0.get(func as usize).ok_or(Error::NoFunction(file, func))
+		}
+		fn include(&self, _: FileId, _: u16) -> Result<FileId, Error> {
+			Ok(FileId(1))
+		}
+	}
+
+	let text = |s: &str| Text(vec![vec![TextSegment::String(s.to_owned())]]);
+	let talk = |s: &str| FlatInsn::Insn(Insn::TextTalk(CharId::Local(LocalCharId(3)), text(s)));
+	let scripts = Single(vec![
+		Code(vec![
+			FlatInsn::Unless(Expr(vec![ExprTerm::Flag(Flag(100))]), Label(0)),
+			talk("Again?"),
+			FlatInsn::Goto(Label(1)),
+			FlatInsn::Label(Label(0)),
+			talk("Hello."),
+			FlatInsn::Insn(Insn::FlagSet(Flag(100))),
+			FlatInsn::Insn(Insn::Call(FuncId(0, 1))),
+			FlatInsn::Label(Label(1)),
+		]),
+		Code(vec![
+			FlatInsn::Insn(Insn::Var(Var(2), Expr(vec![ExprTerm::Const(5), ExprTerm::Op(ExprOp::AddAss)]))),
+			FlatInsn::Insn(Insn::Return()),
+		]),
+	]);
+
+	let mut m = Machine::new(&scripts, 0);
+	m.run(FileId(1), 0).unwrap();
+	m.run(FileId(1), 0).unwrap();
+	let texts = m.trace.iter().filter_map(|a| match a {
+		Event::Text { text, .. } => Some(text.clone()),
+		_ => None,
+	}).collect::<Vec<_>>();
+	assert_eq!(texts, [text("Hello."), text("Again?")]);
+	assert_eq!(m.state.vars.get(&Var(2)), Some(&5));
+}
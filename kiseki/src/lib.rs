@@ -0,0 +1,59 @@
+#![feature(decl_macro)]
+
+//! Tools that operate on the game's scripts as a whole, rather than on one file at a time.
+
+pub mod interp;
+pub mod translate;
+pub mod world;
+pub mod xref;
+
+use themelios::scena::{ed6, ed7};
+use themelios::scena::code::Code;
+use themelios::types::*;
+use themelios::{ReadError, WriteError};
+
+/// A scena file from either engine.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Scena {
+	ED6(ed6::Scena),
+	ED7(ed7::Scena),
+}
+
+impl Scena {
+	pub fn read(game: Game, data: &[u8]) -> Result<Scena, ReadError> {
+		if game.is_ed7() {
+			Ok(Scena::ED7(ed7::Scena::read(game, data)?))
+		} else {
+			Ok(Scena::ED6(ed6::Scena::read(game, data)?))
+		}
+	}
+
+	pub fn write(game: Game, scena: &Scena) -> Result<Vec<u8>, WriteError> {
+		match scena {
+			Scena::ED6(s) => ed6::Scena::write(game, s),
+			Scena::ED7(s) => ed7::Scena::write(game, s),
+		}
+	}
+
+	pub fn functions(&self) -> &[Code] {
+		match self {
+			Scena::ED6(s) => &s.functions,
+			Scena::ED7(s) => &s.functions,
+		}
+	}
+
+	pub fn includes(&self) -> &[FileId] {
+		match self {
+			Scena::ED6(s) => &s.includes,
+			Scena::ED7(s) => &s.includes,
+		}
+	}
+
+	/// The functions run by each of the scena's triggers.
+	pub fn trigger_functions(&self) -> Vec<FuncId> {
+		match self {
+			Scena::ED6(s) => s.triggers.iter().map(|t| t.func).collect(),
+			Scena::ED7(s) => s.triggers.iter().map(|t| t.function).collect(),
+		}
+	}
+}
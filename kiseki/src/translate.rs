@@ -0,0 +1,490 @@
+//! Extraction and reinjection of translatable strings.
+//!
+//! Every [`TString`] and [`Text`] in a file is given a key, such as `fn[3]:12#0` (the first string
+//! in the twelfth instruction of function 3), which stays stable as long as the file's structure
+//! is not edited. These are exported as [`Entry`]s, which can be written as [`po`] or [`xliff`]
+//! files, and later written back into the file with [`inject`].
+//!
+//! Texts are flattened into a string using the same escapes as Calmare's dialogue syntax:
+//! `{wait}`, `{color 5}`, `{item[12]}` and `{0x0D}`, plus `{page}` for page breaks. These, and
+//! `#`-codes like `#0040F` inside the text, are placeholders: a translation must contain the same
+//! set of them as the original, though not necessarily in the same order.
+
+pub mod po;
+pub mod xliff;
+
+use std::collections::{BTreeMap, HashMap};
+
+use themelios::scena::{ed6, ed7};
+use themelios::scena::code::{Code, FlatInsn, Insn};
+use themelios::tables::name::{ED6Name, ED7Name};
+use themelios::tables::quest::{ED6Quest, ED7Quest};
+use themelios::tables::town::Town;
+use themelios::text::{Text, TextSegment};
+use themelios::types::*;
+
+use crate::Scena;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+	/// Name of the file the string was extracted from.
+	pub file: String,
+	/// Location of the string inside the file.
+	pub key: String,
+	/// Who is saying the line, if known.
+	pub speaker: Option<String>,
+	pub source: String,
+	pub target: Option<String>,
+}
+
+impl Entry {
+	/// The file and key joined together, as used for `msgctxt` and XLIFF ids.
+	pub fn context(&self) -> String {
+		format!("{}:{}", self.file, self.key)
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Error {
+	#[error("{key}: no such string")]
+	Missing { key: String },
+	#[error("{key}: original text has changed since extraction")]
+	Stale { key: String },
+	#[error("{key}: {error}")]
+	Syntax { key: String, error: String },
+	#[error("{key}: placeholders differ: expected [{expected}], got [{got}]")]
+	Placeholders { key: String, expected: String, got: String },
+}
+
+/// A mutable reference to a translatable string.
+#[derive(Debug)]
+pub enum Str<'a> {
+	TString(&'a mut TString),
+	Text(&'a mut Text),
+}
+
+impl Str<'_> {
+	pub fn encode(&self) -> String {
+		match self {
+			Str::TString(s) => s.0.clone(),
+			Str::Text(t) => encode_text(t),
+		}
+	}
+
+	fn decode(&mut self, s: &str) -> Result<(), String> {
+		match self {
+			Str::TString(v) => **v = TString(s.to_owned()),
+			Str::Text(v) => **v = decode_text(s)?,
+		}
+		Ok(())
+	}
+}
+
+#[derive(Debug)]
+pub struct Slot<'a> {
+	pub key: String,
+	pub speaker: Option<String>,
+	pub value: Str<'a>,
+}
+
+/// Names used for describing speakers, usually from the `t_name` table.
+pub type Names = BTreeMap<NameId, String>;
+
+pub fn names_ed6(table: &(Vec<ED6Name>, Vec<ED6Name>)) -> Names {
+	table.0.iter().chain(&table.1).map(|a| (a.id, a.name.0.clone())).collect()
+}
+
+pub fn names_ed7(table: &[ED7Name]) -> Names {
+	table.iter().map(|a| (a.id, a.name.0.clone())).collect()
+}
+
+/// Anything containing translatable strings.
+///
+/// This takes a mutable reference so that the same traversal can be used both for extraction and
+/// injection.
+pub trait Translatable {
+	fn walk(&mut self, names: &Names, f: &mut dyn FnMut(Slot));
+}
+
+pub fn extract<T: Translatable + Clone>(file: &str, v: &T, names: &Names) -> Vec<Entry> {
+	let mut out = Vec::new();
+	v.clone().walk(names, &mut |slot| {
+		out.push(Entry {
+			file: file.to_owned(),
+			key: slot.key,
+			speaker: slot.speaker,
+			source: slot.value.encode(),
+			target: None,
+		});
+	});
+	out
+}
+
+/// Writes the translated strings into `v`.
+///
+/// Entries belonging to other files, or which have no translation, are ignored. Entries which
+/// cannot be applied are reported, and the corresponding strings are left untouched.
+pub fn inject(file: &str, v: &mut impl Translatable, names: &Names, entries: &[Entry]) -> Vec<Error> {
+	let mut errors = Vec::new();
+	let mut todo = entries.iter()
+		.filter(|a| a.file == file)
+		.filter_map(|a| Some((a.key.as_str(), (a.source.as_str(), a.target.as_deref()?))))
+		.collect::<HashMap<_, _>>();
+
+	v.walk(names, &mut |mut slot| {
+		let Some((source, target)) = todo.remove(slot.key.as_str()) else { return };
+		let key = format!("{file}:{}", slot.key);
+		let current = slot.value.encode();
+		if current != source {
+			errors.push(Error::Stale { key });
+			return
+		}
+		let expected = placeholders(&current);
+		let got = placeholders(target);
+		if expected != got {
+			errors.push(Error::Placeholders { key, expected: expected.join(" "), got: got.join(" ") });
+			return
+		}
+		if let Err(error) = slot.value.decode(target) {
+			errors.push(Error::Syntax { key, error });
+		}
+	});
+
+	let mut missing = todo.into_keys().collect::<Vec<_>>();
+	missing.sort();
+	for key in missing {
+		errors.push(Error::Missing { key: format!("{file}:{key}") });
+	}
+	errors
+}
+
+pub fn encode_text(text: &Text) -> String {
+	let mut out = String::new();
+	for (i, page) in text.0.iter().enumerate() {
+		if i != 0 {
+			out.push_str("{page}");
+		}
+		for seg in page {
+			match seg {
+				TextSegment::String(s) => {
+					for c in s.chars() {
+						if matches!(c, '\\' | '{' | '}') {
+							out.push('\\');
+						}
+						out.push(c);
+					}
+				}
+				TextSegment::Line => out.push('\n'),
+				TextSegment::Wait => out.push_str("{wait}"),
+				TextSegment::Color(n) => out.push_str(&format!("{{color {n}}}")),
+				TextSegment::Item(n) => out.push_str(&format!("{{item[{}]}}", n.0)),
+				TextSegment::Byte(n) => out.push_str(&format!("{{0x{n:02X}}}")),
+			}
+		}
+	}
+	out
+}
+
+pub fn decode_text(s: &str) -> Result<Text, String> {
+	let mut pages = vec![Vec::new()];
+	let mut buf = String::new();
+	let mut chars = s.chars();
+	while let Some(c) = chars.next() {
+		let seg = match c {
+			'\\' => {
+				buf.push(chars.next().ok_or("unterminated escape")?);
+				continue
+			}
+			'{' => {
+				let mut code = String::new();
+				loop {
+					match chars.next() {
+						Some('}') => break,
+						Some(c) => code.push(c),
+						None => return Err("unterminated placeholder".to_owned()),
+					}
+				}
+				if code == "page" {
+					flush(&mut buf, pages.last_mut().unwrap());
+					pages.push(Vec::new());
+					continue
+				}
+				parse_placeholder(&code).ok_or_else(|| format!("invalid placeholder {{{code}}}"))?
+			}
+			'}' => return Err("unmatched '}'".to_owned()),
+			'\n' => TextSegment::Line,
+			c => {
+				buf.push(c);
+				continue
+			}
+		};
+		let page = pages.last_mut().unwrap();
+		flush(&mut buf, page);
+		page.push(seg);
+	}
+	flush(&mut buf, pages.last_mut().unwrap());
+	Ok(Text(pages))
+}
+
+fn flush(buf: &mut String, page: &mut Vec<TextSegment>) {
+	if !buf.is_empty() {
+		page.push(TextSegment::String(std::mem::take(buf)));
+	}
+}
+
+fn parse_placeholder(code: &str) -> Option<TextSegment> {
+	if code == "wait" {
+		Some(TextSegment::Wait)
+	} else if let Some(n) = code.strip_prefix("color ") {
+		Some(TextSegment::Color(n.parse().ok()?))
+	} else if let Some(n) = code.strip_prefix("item[").and_then(|a| a.strip_suffix(']')) {
+		Some(TextSegment::Item(ItemId(n.parse().ok()?)))
+	} else if let Some(n) = code.strip_prefix("0x") {
+		Some(TextSegment::Byte(u8::from_str_radix(n, 16).ok()?))
+	} else {
+		None
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Piece<'a> {
+	Text(&'a str),
+	/// A `{...}` placeholder or a `#`-code, which should be left as is by translators.
+	Code(&'a str),
+}
+
+/// Splits an encoded string into text and placeholders.
+pub fn pieces(s: &str) -> Vec<Piece<'_>> {
+	let mut out = Vec::new();
+	let mut start = 0;
+	let mut it = s.char_indices().peekable();
+	while let Some((i, c)) = it.next() {
+		let end = match c {
+			'\\' => {
+				it.next();
+				continue
+			}
+			'{' => {
+				it.by_ref().find(|a| a.1 == '}').map_or(s.len(), |a| a.0 + 1)
+			}
+			'#' => {
+				while it.next_if(|a| a.1.is_ascii_digit()).is_some() {}
+				match it.peek() {
+					Some(&(j, c)) if c.is_ascii_alphabetic() => {
+						it.next();
+						j + 1
+					}
+					_ => continue,
+				}
+			}
+			_ => continue,
+		};
+		if start != i {
+			out.push(Piece::Text(&s[start..i]));
+		}
+		out.push(Piece::Code(&s[i..end]));
+		start = end;
+	}
+	if start != s.len() {
+		out.push(Piece::Text(&s[start..]));
+	}
+	out
+}
+
+fn placeholders(s: &str) -> Vec<&str> {
+	let mut out = pieces(s).into_iter()
+		.filter_map(|a| match a {
+			Piece::Code(a) => Some(a),
+			Piece::Text(_) => None,
+		})
+		.collect::<Vec<_>>();
+	out.sort();
+	out
+}
+
+struct Chars<'a> {
+	names: &'a Names,
+	locals: Vec<Option<String>>,
+}
+
+impl Chars<'_> {
+	fn speaker(&self, c: &CharId) -> Option<String> {
+		match *c {
+			CharId::Name(n) => Some(self.names.get(&n).cloned().unwrap_or_else(|| format!("name[{}]", n.0))),
+			CharId::Local(n) => self.locals.get(n.0 as usize).cloned().flatten()
+				.or_else(|| Some(format!("char[{}]", n.0))),
+			CharId::FieldParty(n) => Some(format!("field_party[{n}]")),
+			CharId::Party(n) => Some(format!("party[{n}]")),
+			CharId::Custom(n) => Some(format!("custom[{n}]")),
+			CharId::Self_ | CharId::Null => None,
+		}
+	}
+}
+
+fn walk_functions(functions: &mut [Code], chars: &Chars, f: &mut dyn FnMut(Slot)) {
+	for (i, func) in functions.iter_mut().enumerate() {
+		walk_code(func, &format!("fn[{i}]:"), chars, f);
+	}
+}
+
+fn walk_code(code: &mut Code, prefix: &str, chars: &Chars, f: &mut dyn FnMut(Slot)) {
+	for (i, insn) in code.iter_mut().enumerate() {
+		if let FlatInsn::Insn(insn) = insn {
+			walk_insn(insn, &format!("{prefix}{i}"), chars, f);
+		}
+	}
+}
+
+#[allow(unused_assignments)] // the last `n += 1` in each arm
+fn walk_insn(insn: &mut Insn, key: &str, chars: &Chars, f: &mut dyn FnMut(Slot)) {
+	let mut n = 0;
+
+	macro slot($speaker:expr, $value:expr) {
+		f(Slot { key: format!("{key}#{n}"), speaker: $speaker, value: $value });
+		n += 1;
+	}
+
+	macro run {
+		([$(($ident:ident $(($_n:ident $($ty:tt)*))*))*]) => {
+			match insn {
+				$(Insn::$ident($($_n),*) => {
+					$(arg!($_n $($ty)*);)*
+				})*
+			}
+		}
+	}
+
+	macro arg {
+		($v:ident TString) => { slot!(None, Str::TString($v)); },
+		($v:ident Text) => { slot!(None, Str::Text($v)); },
+		($v:ident Vec<TString>) => {
+			for s in $v {
+				slot!(None, Str::TString(s));
+			}
+		},
+		($v:ident Code) => { walk_code($v, &format!("{key}/"), chars, f); },
+		($v:ident $($ty:tt)*) => { let _ = $v; },
+	}
+
+	match insn {
+		Insn::TextTalk(c, text) => {
+			slot!(chars.speaker(c), Str::Text(text));
+		}
+		Insn::TextTalkNamed(_, name, text) => {
+			let speaker = Some(name.0.clone());
+			slot!(None, Str::TString(name));
+			slot!(speaker, Str::Text(text));
+		}
+		_ => {
+			themelios::scena::code::introspect!(run);
+		}
+	}
+}
+
+impl Translatable for ed6::Scena {
+	fn walk(&mut self, names: &Names, f: &mut dyn FnMut(Slot)) {
+		let mut locals = Vec::new();
+		for (i, npc) in self.npcs.iter_mut().enumerate() {
+			locals.push(Some(npc.name.0.clone()));
+			f(Slot { key: format!("char[{i}]"), speaker: None, value: Str::TString(&mut npc.name) });
+		}
+		for monster in &mut self.monsters {
+			let i = locals.len();
+			locals.push(Some(monster.name.0.clone()));
+			f(Slot { key: format!("char[{i}]"), speaker: None, value: Str::TString(&mut monster.name) });
+		}
+		walk_functions(&mut self.functions, &Chars { names, locals }, f);
+	}
+}
+
+impl Translatable for ed7::Scena {
+	fn walk(&mut self, names: &Names, f: &mut dyn FnMut(Slot)) {
+		let mut locals = Vec::new();
+		for (i, npc) in self.npcs.iter_mut().enumerate() {
+			locals.push(Some(npc.name.0.clone()));
+			f(Slot { key: format!("char[{i}]"), speaker: None, value: Str::TString(&mut npc.name) });
+		}
+		for (i, label) in self.labels.iter_mut().flatten().enumerate() {
+			f(Slot { key: format!("label[{i}]"), speaker: None, value: Str::TString(&mut label.name) });
+		}
+		walk_functions(&mut self.functions, &Chars { names, locals }, f);
+	}
+}
+
+impl Translatable for Scena {
+	fn walk(&mut self, names: &Names, f: &mut dyn FnMut(Slot)) {
+		match self {
+			Scena::ED6(s) => s.walk(names, f),
+			Scena::ED7(s) => s.walk(names, f),
+		}
+	}
+}
+
+impl Translatable for (Vec<ED6Name>, Vec<ED6Name>) {
+	fn walk(&mut self, _: &Names, f: &mut dyn FnMut(Slot)) {
+		for name in self.0.iter_mut().chain(self.1.iter_mut()) {
+			f(Slot { key: format!("name[{}]", name.id.0), speaker: None, value: Str::TString(&mut name.name) });
+		}
+	}
+}
+
+impl Translatable for Vec<ED7Name> {
+	fn walk(&mut self, _: &Names, f: &mut dyn FnMut(Slot)) {
+		for name in self {
+			f(Slot { key: format!("name[{}]", name.id.0), speaker: None, value: Str::TString(&mut name.name) });
+		}
+	}
+}
+
+impl Translatable for Vec<ED6Quest> {
+	fn walk(&mut self, _: &Names, f: &mut dyn FnMut(Slot)) {
+		for q in self {
+			let k = format!("quest[{}]", q.id.0);
+			f(Slot { key: format!("{k}.name"), speaker: None, value: Str::TString(&mut q.name) });
+			f(Slot { key: format!("{k}.desc"), speaker: None, value: Str::Text(&mut q.desc) });
+			for (i, step) in q.steps.iter_mut().enumerate() {
+				f(Slot { key: format!("{k}.step[{i}]"), speaker: None, value: Str::Text(step) });
+			}
+		}
+	}
+}
+
+impl Translatable for Vec<ED7Quest> {
+	fn walk(&mut self, _: &Names, f: &mut dyn FnMut(Slot)) {
+		for q in self {
+			let k = format!("quest[{}]", q.id.0);
+			f(Slot { key: format!("{k}.name"), speaker: None, value: Str::TString(&mut q.name) });
+			f(Slot { key: format!("{k}.client"), speaker: None, value: Str::TString(&mut q.client) });
+			f(Slot { key: format!("{k}.desc"), speaker: None, value: Str::Text(&mut q.desc) });
+			for (i, step) in q.steps.iter_mut().enumerate() {
+				f(Slot { key: format!("{k}.step[{i}]"), speaker: None, value: Str::Text(step) });
+			}
+		}
+	}
+}
+
+impl Translatable for Vec<Town> {
+	fn walk(&mut self, _: &Names, f: &mut dyn FnMut(Slot)) {
+		for town in self {
+			f(Slot { key: format!("town[{}]", town.id.0), speaker: None, value: Str::TString(&mut town.name) });
+		}
+	}
+}
+
+#[test]
+fn text_roundtrip() {
+	let text = Text(vec![
+		vec![
+			TextSegment::Color(5),
+			TextSegment::String("#0040F{Hello}\\".to_owned()),
+			TextSegment::Line,
+			TextSegment::Item(ItemId(12)),
+			TextSegment::Wait,
+		],
+		vec![TextSegment::Byte(0x0D), TextSegment::String("world".to_owned())],
+	]);
+	let s = encode_text(&text);
+	assert_eq!(s, "{color 5}#0040F\\{Hello\\}\\\\\n{item[12]}{wait}{page}{0x0D}world");
+	assert_eq!(decode_text(&s).unwrap(), text);
+	assert_eq!(placeholders(&s), ["#0040F", "{0x0D}", "{color 5}", "{item[12]}", "{page}", "{wait}"]);
+}
@@ -0,0 +1,186 @@
+//! Reading and writing gettext PO files.
+//!
+//! Each entry's file and key are stored in `msgctxt`, and the speaker in an extracted comment.
+//! Entries marked `fuzzy` are read as untranslated.
+
+use super::Entry;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("line {line}: {error}")]
+pub struct Error {
+	pub line: usize,
+	pub error: String,
+}
+
+pub fn write(entries: &[Entry]) -> String {
+	let mut out = String::new();
+	out.push_str("msgid \"\"\n");
+	out.push_str("msgstr \"\"\n");
+	out.push_str("\"Content-Type: text/plain; charset=UTF-8\\n\"\n");
+	for e in entries {
+		out.push('\n');
+		if let Some(speaker) = &e.speaker {
+			out.push_str(&format!("#. {speaker}\n"));
+		}
+		out.push_str(&format!("#: {}\n", e.file));
+		string(&mut out, "msgctxt", &e.context());
+		string(&mut out, "msgid", &e.source);
+		string(&mut out, "msgstr", e.target.as_deref().unwrap_or(""));
+	}
+	out
+}
+
+fn string(out: &mut String, kw: &str, s: &str) {
+	let lines = s.split_inclusive('\n').collect::<Vec<_>>();
+	if lines.len() > 1 {
+		out.push_str(&format!("{kw} \"\"\n"));
+		for line in lines {
+			out.push_str(&format!("\"{}\"\n", escape(line)));
+		}
+	} else {
+		out.push_str(&format!("{kw} \"{}\"\n", escape(s)));
+	}
+}
+
+fn escape(s: &str) -> String {
+	let mut out = String::with_capacity(s.len());
+	for c in s.chars() {
+		match c {
+			'\\' => out.push_str("\\\\"),
+			'"' => out.push_str("\\\""),
+			'\n' => out.push_str("\\n"),
+			'\t' => out.push_str("\\t"),
+			'\r' => out.push_str("\\r"),
+			c => out.push(c),
+		}
+	}
+	out
+}
+
+fn unescape(s: &str) -> Result<String, String> {
+	let mut out = String::with_capacity(s.len());
+	let mut it = s.chars();
+	while let Some(c) = it.next() {
+		if c == '\\' {
+			out.push(match it.next() {
+				Some('\\') => '\\',
+				Some('"') => '"',
+				Some('n') => '\n',
+				Some('t') => '\t',
+				Some('r') => '\r',
+				Some(c) => return Err(format!("unknown escape '\\{c}'")),
+				None => return Err("unterminated escape".to_owned()),
+			});
+		} else {
+			out.push(c);
+		}
+	}
+	Ok(out)
+}
+
+#[derive(Default)]
+struct Pending {
+	fuzzy: bool,
+	speaker: Option<String>,
+	ctxt: Option<String>,
+	id: Option<String>,
+	str: Option<String>,
+}
+
+pub fn read(src: &str) -> Result<Vec<Entry>, Error> {
+	let mut out = Vec::new();
+	let mut cur = Pending::default();
+	let mut field: Option<&mut String> = None;
+
+	fn finish(out: &mut Vec<Entry>, cur: Pending, line: usize) -> Result<(), Error> {
+		let Some(id) = cur.id else {
+			return Ok(())
+		};
+		// The header has no context; anything else lacking one is not ours.
+		let Some(ctxt) = cur.ctxt else {
+			return Ok(())
+		};
+		let Some((file, key)) = ctxt.split_once(':') else {
+			return Err(Error { line, error: format!("invalid context {ctxt:?}") })
+		};
+		let target = cur.str.filter(|a| !a.is_empty() && !cur.fuzzy);
+		out.push(Entry {
+			file: file.to_owned(),
+			key: key.to_owned(),
+			speaker: cur.speaker,
+			source: id,
+			target,
+		});
+		Ok(())
+	}
+
+	for (lineno, line) in src.lines().enumerate() {
+		let lineno = lineno + 1;
+		let err = |error: String| Error { line: lineno, error };
+		let line = line.trim();
+
+		if let Some(s) = line.strip_prefix('"') {
+			let Some(field) = field.as_deref_mut() else {
+				return Err(err("unexpected string".to_owned()))
+			};
+			let s = s.strip_suffix('"').ok_or_else(|| err("unterminated string".to_owned()))?;
+			field.push_str(&unescape(s).map_err(err)?);
+			continue
+		}
+
+		field = None;
+		if line.is_empty() {
+			continue
+		}
+
+		if let Some(c) = line.strip_prefix('#') {
+			if cur.id.is_some() {
+				finish(&mut out, std::mem::take(&mut cur), lineno)?;
+			}
+			if let Some(flags) = c.strip_prefix(',') {
+				cur.fuzzy |= flags.split(',').any(|a| a.trim() == "fuzzy");
+			} else if let Some(s) = c.strip_prefix(". ") {
+				cur.speaker = Some(s.to_owned());
+			}
+			continue
+		}
+
+		let (kw, rest) = line.split_once(' ').ok_or_else(|| err("expected keyword".to_owned()))?;
+		let s = rest.trim().strip_prefix('"').and_then(|a| a.strip_suffix('"'))
+			.ok_or_else(|| err("expected string".to_owned()))?;
+		let s = unescape(s).map_err(err)?;
+		if kw == "msgctxt" && cur.id.is_some() || kw == "msgid" && cur.str.is_some() {
+			finish(&mut out, std::mem::take(&mut cur), lineno)?;
+		}
+		let slot = match kw {
+			"msgctxt" => &mut cur.ctxt,
+			"msgid" => &mut cur.id,
+			"msgstr" => &mut cur.str,
+			_ => return Err(err(format!("unknown keyword {kw:?}"))),
+		};
+		field = Some(slot.insert(s));
+	}
+	finish(&mut out, cur, src.lines().count())?;
+	Ok(out)
+}
+
+#[test]
+fn roundtrip() {
+	let entries = vec![
+		Entry {
+			file: "t0100._sn".to_owned(),
+			key: "fn[3]:12#0".to_owned(),
+			speaker: Some("Estelle".to_owned()),
+			source: "#0040F\"Hello\"\n{wait}{page}world".to_owned(),
+			target: Some("Hallo\nWelt{wait}{page}#0040F".to_owned()),
+		},
+		Entry {
+			file: "t0100._sn".to_owned(),
+			key: "char[0]".to_owned(),
+			speaker: None,
+			source: "Girl".to_owned(),
+			target: None,
+		},
+	];
+	assert_eq!(read(&write(&entries)).unwrap(), entries);
+}
@@ -0,0 +1,174 @@
+//! Reading and writing XLIFF 1.2 files.
+//!
+//! Each source file becomes a `<file>`, and each entry a `<trans-unit>` whose id is the entry's
+//! key. Placeholders are wrapped in `<ph>` elements so that translation tools keep them intact.
+
+use quick_xml::events::Event;
+
+use super::{Entry, Piece};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	#[error("{0}")]
+	Xml(#[from] quick_xml::Error),
+	#[error("{0}")]
+	Invalid(String),
+}
+
+pub fn write(entries: &[Entry], source_lang: &str, target_lang: Option<&str>) -> String {
+	let mut out = String::new();
+	out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+	out.push_str("<xliff version=\"1.2\" xmlns=\"urn:oasis:names:tc:xliff:document:1.2\">\n");
+
+	let mut i = 0;
+	while i < entries.len() {
+		let file = &entries[i].file;
+		out.push_str(&format!("\t<file original=\"{}\" source-language=\"{}\"", escape(file), escape(source_lang)));
+		if let Some(lang) = target_lang {
+			out.push_str(&format!(" target-language=\"{}\"", escape(lang)));
+		}
+		out.push_str(" datatype=\"plaintext\">\n");
+		out.push_str("\t\t<body>\n");
+		for e in entries[i..].iter().take_while(|a| &a.file == file) {
+			out.push_str(&format!("\t\t\t<trans-unit id=\"{}\" xml:space=\"preserve\">\n", escape(&e.key)));
+			out.push_str(&format!("\t\t\t\t<source>{}</source>\n", content(&e.source)));
+			if let Some(target) = &e.target {
+				out.push_str(&format!("\t\t\t\t<target>{}</target>\n", content(target)));
+			}
+			if let Some(speaker) = &e.speaker {
+				out.push_str(&format!("\t\t\t\t<note from=\"speaker\">{}</note>\n", escape(speaker)));
+			}
+			out.push_str("\t\t\t</trans-unit>\n");
+			i += 1;
+		}
+		out.push_str("\t\t</body>\n");
+		out.push_str("\t</file>\n");
+	}
+
+	out.push_str("</xliff>\n");
+	out
+}
+
+fn content(s: &str) -> String {
+	let mut out = String::new();
+	let mut n = 0;
+	for p in super::pieces(s) {
+		match p {
+			Piece::Text(s) => out.push_str(&escape(s)),
+			Piece::Code(s) => {
+				n += 1;
+				out.push_str(&format!("<ph id=\"{n}\">{}</ph>", escape(s)));
+			}
+		}
+	}
+	out
+}
+
+fn escape(s: &str) -> String {
+	quick_xml::escape::escape(s).into_owned()
+}
+
+pub fn read(src: &str) -> Result<Vec<Entry>, Error> {
+	let mut f = quick_xml::Reader::from_str(src);
+	f.trim_text(false);
+
+	let mut out = Vec::new();
+	let mut file = None;
+	let mut entry = None::<Entry>;
+	let mut text = None::<String>;
+	let mut is_speaker = false;
+
+	loop {
+		let ev = f.read_event()?;
+		match ev {
+			Event::Start(ref e) | Event::Empty(ref e) => {
+				let empty = matches!(ev, Event::Empty(_));
+				match e.local_name().as_ref() {
+					b"file" => {
+						let Some(name) = e.try_get_attribute("original")? else {
+							return Err(Error::Invalid("<file> without 'original'".to_owned()))
+						};
+						file = Some(name.unescape_value()?.into_owned());
+					}
+					b"trans-unit" => {
+						let (Some(file), Some(id)) = (&file, e.try_get_attribute("id")?) else {
+							return Err(Error::Invalid("<trans-unit> without <file> or 'id'".to_owned()))
+						};
+						entry = Some(Entry {
+							file: file.clone(),
+							key: id.unescape_value()?.into_owned(),
+							speaker: None,
+							source: String::new(),
+							target: None,
+						});
+					}
+					b"source" | b"target" if !empty => {
+						text = Some(String::new());
+					}
+					b"note" if !empty => {
+						is_speaker = e.try_get_attribute("from")?.is_some_and(|a| a.value.as_ref() == b"speaker");
+						text = Some(String::new());
+					}
+					_ => {}
+				}
+			}
+			Event::End(e) => {
+				match e.local_name().as_ref() {
+					b"trans-unit" => {
+						out.extend(entry.take());
+					}
+					b"source" => {
+						if let (Some(e), Some(t)) = (&mut entry, text.take()) {
+							e.source = t;
+						}
+					}
+					b"target" => {
+						if let (Some(e), Some(t)) = (&mut entry, text.take()) {
+							e.target = Some(t).filter(|a| !a.is_empty());
+						}
+					}
+					b"note" => {
+						if let (Some(e), Some(t), true) = (&mut entry, text.take(), is_speaker) {
+							e.speaker = Some(t);
+						}
+					}
+					_ => {}
+				}
+			}
+			Event::Text(e) => {
+				if let Some(t) = &mut text {
+					t.push_str(&e.unescape()?);
+				}
+			}
+			Event::CData(e) => {
+				if let Some(t) = &mut text {
+					t.push_str(&String::from_utf8_lossy(&e));
+				}
+			}
+			Event::Eof => break,
+			_ => {}
+		}
+	}
+	Ok(out)
+}
+
+#[test]
+fn roundtrip() {
+	let entries = vec![
+		Entry {
+			file: "t0100._sn".to_owned(),
+			key: "fn[3]:12#0".to_owned(),
+			speaker: Some("Estelle".to_owned()),
+			source: "#0040F<Hello> & {color 5}\n{wait}{page}world".to_owned(),
+			target: Some("Hallo\nWelt{wait}{page}#0040F{color 5}".to_owned()),
+		},
+		Entry {
+			file: "t0101._sn".to_owned(),
+			key: "char[0]".to_owned(),
+			speaker: None,
+			source: "Girl".to_owned(),
+			target: None,
+		},
+	];
+	assert_eq!(read(&write(&entries, "ja", Some("en"))).unwrap(), entries);
+}
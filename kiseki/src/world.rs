@@ -0,0 +1,360 @@
+//! A graph of how the maps connect, for checking that every area can be reached and left the way
+//! it should after adding new ones.
+//!
+//! Connections come from two places. In ED6, most exits are entries in the map's `._en` file,
+//! which the engine follows on its own, while ED7 has no such table that we know of, and its
+//! exits are instead triggers whose functions run `NewScene`. Any other `NewScene`, such as at the
+//! end of an event, is included as well, but marked as such.
+//!
+//! Nodes are named by file name without directory or extension, like in
+//! [`xref`](crate::xref). Positions on the world map are taken from ED6's `t_world._dt`.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use glam::IVec2;
+use themelios::lookup::Lookup;
+use themelios::scena::code::{Code, FlatInsn, Insn};
+use themelios::tables::ent::ED6Ent;
+use themelios::tables::world::ED6World;
+use themelios::types::*;
+
+use crate::xref::node_name;
+use crate::Scena;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Node {
+	/// Whether the node's scena was added. Nodes that are only known as destinations are drawn
+	/// dashed.
+	pub scena: bool,
+	/// The number of entries in the map's `._en` file, if it was added.
+	pub entrances: Option<usize>,
+	/// Position on the world map.
+	pub world_pos: Option<IVec2>,
+	/// Whether the node is named by a file id that is not in the lookup.
+	pub unknown: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edge {
+	pub from: String,
+	pub to: String,
+	/// Which of the destination's entrances the player arrives at.
+	pub entrance: EntranceId,
+	pub via: Via,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Via {
+	/// The `n`th entry in the map's `._en` file.
+	Entrance(usize),
+	/// A `NewScene` in code run by the `n`th trigger, either directly or through `Call`.
+	Trigger(usize, String),
+	/// A `NewScene` anywhere else, at the given location.
+	Code(String),
+}
+
+impl std::fmt::Display for Via {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Via::Entrance(n) => write!(f, "entrance[{n}]"),
+			Via::Trigger(n, loc) => write!(f, "trigger[{n}] {loc}"),
+			Via::Code(loc) => write!(f, "{loc}"),
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Problem {
+	#[error("leads to {0}, which is not in the lookup")]
+	UnknownFile(String),
+	#[error("leads to entrance {1} of {0}, which only has {2}")]
+	NoEntrance(String, u8, usize),
+}
+
+pub struct Graph<'a> {
+	lookup: &'a dyn Lookup,
+	pub nodes: BTreeMap<String, Node>,
+	pub edges: Vec<Edge>,
+}
+
+impl<'a> Graph<'a> {
+	pub fn new(lookup: &'a dyn Lookup) -> Self {
+		Graph {
+			lookup,
+			nodes: BTreeMap::new(),
+			edges: Vec::new(),
+		}
+	}
+
+	/// Adds the `NewScene`s in a scena file, which is named `file`.
+	pub fn add_scena(&mut self, file: &str, scena: &Scena) {
+		self.add_code(file, scena.functions(), &scena.trigger_functions());
+	}
+
+	fn add_code(&mut self, file: &str, functions: &[Code], triggers: &[FuncId]) {
+		let from = node_name(file);
+		self.node(&from).scena = true;
+
+		let mut calls = vec![BTreeSet::new(); functions.len()];
+		let mut scenes = Vec::new();
+		for (i, func) in functions.iter().enumerate() {
+			insns(func, &format!("fn[{i}]:"), &mut |loc, insn| match insn {
+				Insn::Call(FuncId(0, n)) => {
+					calls[i].insert(*n as usize);
+				}
+				Insn::NewScene(file, entrance, ..) => {
+					scenes.push((i, loc, *file, EntranceId(*entrance)));
+				}
+				_ => {}
+			});
+		}
+
+		// Functions called from other files are not followed, since those are nearly always
+		// shared cutscene code rather than exits.
+		let mut trigger = vec![None; functions.len()];
+		for (t, &FuncId(a, b)) in triggers.iter().enumerate() {
+			if a != 0 {
+				continue
+			}
+			let mut stack = vec![b as usize];
+			while let Some(n) = stack.pop() {
+				if n >= functions.len() || trigger[n].is_some() {
+					continue
+				}
+				trigger[n] = Some(t);
+				stack.extend(&calls[n]);
+			}
+		}
+
+		for (func, loc, file, entrance) in scenes {
+			let to = self.file_node(file);
+			let via = match trigger[func] {
+				Some(t) => Via::Trigger(t, loc),
+				None => Via::Code(loc),
+			};
+			self.edges.push(Edge { from: from.clone(), to, entrance, via });
+		}
+	}
+
+	/// Adds the exits in an ED6 `._en` file, which is named `file`.
+	pub fn add_entrances(&mut self, file: &str, table: &[ED6Ent]) {
+		let from = node_name(file);
+		self.node(&from).entrances = Some(table.len());
+		for (i, ent) in table.iter().enumerate() {
+			if ent.dest == FileId::NONE {
+				continue
+			}
+			let to = self.file_node(ent.dest);
+			self.edges.push(Edge {
+				from: from.clone(),
+				to,
+				entrance: ent.dest_entrance,
+				via: Via::Entrance(i),
+			});
+		}
+	}
+
+	/// Adds the world map positions from ED6's `t_world._dt`.
+	pub fn add_world(&mut self, table: &[ED6World]) {
+		for a in table {
+			let name = self.file_node(a.scena);
+			self.node(&name).world_pos = Some(a.pos);
+		}
+	}
+
+	fn node(&mut self, name: &str) -> &mut Node {
+		self.nodes.entry(name.to_owned()).or_default()
+	}
+
+	fn file_node(&mut self, file: FileId) -> String {
+		match self.lookup.name(file.0) {
+			Some(name) => {
+				let name = node_name(&name);
+				self.node(&name);
+				name
+			}
+			None => {
+				let name = format!("0x{:08X}", file.0);
+				self.node(&name).unknown = true;
+				name
+			}
+		}
+	}
+
+	/// Finds connections that lead nowhere: to files that do not exist, or to entrances that the
+	/// destination does not have. The latter can only be checked if its `._en` file was added.
+	pub fn problems(&self) -> Vec<(&Edge, Problem)> {
+		let mut out = Vec::new();
+		for edge in &self.edges {
+			let Some(node) = self.nodes.get(&edge.to) else { continue };
+			if node.unknown {
+				out.push((edge, Problem::UnknownFile(edge.to.clone())));
+			}
+			if let Some(n) = node.entrances {
+				if edge.entrance.0 as usize >= n {
+					out.push((edge, Problem::NoEntrance(edge.to.clone(), edge.entrance.0, n)));
+				}
+			}
+		}
+		out
+	}
+
+	pub fn to_json(&self) -> serde_json::Value {
+		let nodes = self.nodes.iter().map(|(name, node)| {
+			serde_json::json!({
+				"name": name,
+				"scena": node.scena,
+				"entrances": node.entrances,
+				"world_pos": node.world_pos.map(|p| [p.x, p.y]),
+				"unknown": node.unknown,
+			})
+		}).collect::<Vec<_>>();
+
+		let problems = self.problems();
+		let edges = self.edges.iter().map(|e| {
+			let mut v = serde_json::json!({
+				"from": e.from,
+				"to": e.to,
+				"entrance": e.entrance.0,
+			});
+			match &e.via {
+				Via::Entrance(n) => {
+					v["via"] = "entrance".into();
+					v["index"] = (*n).into();
+				}
+				Via::Trigger(n, loc) => {
+					v["via"] = "trigger".into();
+					v["index"] = (*n).into();
+					v["location"] = loc.as_str().into();
+				}
+				Via::Code(loc) => {
+					v["via"] = "code".into();
+					v["location"] = loc.as_str().into();
+				}
+			}
+			let p = problems.iter()
+				.filter(|a| std::ptr::eq(a.0, e))
+				.map(|a| a.1.to_string())
+				.collect::<Vec<_>>();
+			if !p.is_empty() {
+				v["problems"] = p.into();
+			}
+			v
+		}).collect::<Vec<_>>();
+
+		serde_json::json!({ "nodes": nodes, "edges": edges })
+	}
+
+	/// Writes the graph for Graphviz.
+	///
+	/// Nodes with a world map position are pinned there, which is respected by `neato` and
+	/// `fdp`. Connections that are not exits are dotted, and ones with problems are red.
+	pub fn to_dot(&self) -> String {
+		let mut out = String::new();
+		out.push_str("digraph world {\n");
+		for (name, node) in &self.nodes {
+			let mut attrs = Vec::new();
+			if let Some(p) = node.world_pos {
+				// Graphviz's y axis points up.
+				attrs.push(format!("pos=\"{},{}!\"", p.x, -p.y));
+			}
+			if !node.scena && node.entrances.is_none() {
+				attrs.push("style=dashed".to_owned());
+			}
+			out.push_str(&format!("\t{name:?}{};\n", self::attrs(&attrs)));
+		}
+
+		let problems = self.problems();
+		let mut seen = BTreeSet::new();
+		for e in &self.edges {
+			let code = matches!(e.via, Via::Code(_));
+			if !seen.insert((&e.from, &e.to, e.entrance, code)) {
+				continue
+			}
+			let mut attrs = vec![format!("label=\"{}\"", e.entrance.0)];
+			if code {
+				attrs.push("style=dotted".to_owned());
+			}
+			if problems.iter().any(|a| a.0.from == e.from && a.0.to == e.to && a.0.entrance == e.entrance) {
+				attrs.push("color=red".to_owned());
+			}
+			out.push_str(&format!("\t{:?} -> {:?}{};\n", e.from, e.to, self::attrs(&attrs)));
+		}
+		out.push_str("}\n");
+		out
+	}
+}
+
+fn attrs(attrs: &[String]) -> String {
+	if attrs.is_empty() {
+		String::new()
+	} else {
+		format!(" [{}]", attrs.join(", "))
+	}
+}
+
+type Sink<'a> = dyn FnMut(String, &Insn) + 'a;
+
+/// Calls `f` on every instruction, including those inside forks and other code blocks.
+fn insns(code: &Code, prefix: &str, f: &mut Sink) {
+	for (i, insn) in code.iter().enumerate() {
+		let FlatInsn::Insn(insn) = insn else { continue };
+		let loc = format!("{prefix}{i}");
+		f(loc.clone(), insn);
+		nested(insn, &loc, f);
+	}
+}
+
+fn nested(insn: &Insn, loc: &str, f: &mut Sink) {
+	macro run {
+		([$(($ident:ident $(($_n:ident $($ty:tt)*))*))*]) => {
+			match insn {
+				$(Insn::$ident($($_n),*) => {
+					$(arg!($_n $($ty)*);)*
+				})*
+			}
+		}
+	}
+
+	macro arg {
+		($v:ident Code) => { insns($v, &format!("{loc}/"), f) },
+		($v:ident $($ty:tt)*) => { let _ = $v; },
+	}
+
+	themelios::scena::code::introspect!(run);
+}
+
+#[test]
+fn graph() {
+	let lookup = themelios::lookup::default_for(Game::Fc);
+	let t0001 = FileId(lookup.index("t0001._sn").unwrap());
+	let new_scene = |entrance| FlatInsn::Insn(Insn::NewScene(t0001, entrance, 0, 0));
+	let functions = [
+		Code(vec![FlatInsn::Insn(Insn::Call(FuncId(0, 1)))]),
+		Code(vec![FlatInsn::Insn(Insn::Fork(CharId::Self_, ForkId(0), Code(vec![new_scene(0)])))]),
+		Code(vec![new_scene(3)]),
+	];
+
+	let mut graph = Graph::new(lookup);
+	graph.add_code("t0100._sn", &functions, &[FuncId(0, 0)]);
+	graph.nodes.entry("t0001".to_owned()).or_default().entrances = Some(2);
+	graph.add_world(&[ED6World { scena: t0001, pos: IVec2::new(10, 20) }]);
+
+	assert_eq!(graph.edges, [
+		Edge { from: "t0100".to_owned(), to: "t0001".to_owned(), entrance: EntranceId(0), via: Via::Trigger(0, "fn[1]:0/0".to_owned()) },
+		Edge { from: "t0100".to_owned(), to: "t0001".to_owned(), entrance: EntranceId(3), via: Via::Code("fn[2]:0".to_owned()) },
+	]);
+	assert_eq!(graph.nodes["t0001"].world_pos, Some(IVec2::new(10, 20)));
+
+	let problems = graph.problems();
+	assert_eq!(problems.len(), 1);
+	assert_eq!(problems[0].1, Problem::NoEntrance("t0001".to_owned(), 3, 2));
+	assert_eq!(graph.to_dot(), "\
+digraph world {
+	\"t0001\" [pos=\"10,-20!\"];
+	\"t0100\";
+	\"t0100\" -> \"t0001\" [label=\"0\"];
+	\"t0100\" -> \"t0001\" [label=\"3\", style=dotted, color=red];
+}
+");
+}
@@ -0,0 +1,272 @@
+//! Cross-references between scripts: which code reads and writes which flags and variables,
+//! which scenes lead to which, and which battles and items are used where.
+//!
+//! Locations use the same format as [`translate`](crate::translate) keys, without the string
+//! number: `fn[3]:12` is the twelfth instruction in function 3, and `fn[3]:12/4` is the fourth
+//! instruction inside the code block of that instruction.
+
+use std::collections::BTreeSet;
+
+use themelios::lookup::Lookup;
+use themelios::scena::code::{Code, Expr, ExprTerm, FlatInsn, Insn};
+use themelios::text::{Text, TextSegment};
+use themelios::types::*;
+
+use crate::Scena;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Target {
+	Flag(Flag),
+	Var(Var),
+	Global(Global),
+	/// A `NewScene` into the given file.
+	Scene(FileId),
+	Battle(BattleId),
+	Item(ItemId),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Access {
+	Read,
+	Write,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Use {
+	pub file: String,
+	pub location: String,
+	pub target: Target,
+	pub access: Access,
+}
+
+impl Target {
+	/// Parses a target in Calmare's syntax, such as `flag[12]`.
+	///
+	/// Scenes are written as `scene[...]`, with either a file id or a file name.
+	pub fn parse(s: &str, lookup: &dyn Lookup) -> Option<Target> {
+		let (kind, arg) = s.strip_suffix(']')?.split_once('[')?;
+		let num = || -> Option<u32> {
+			match arg.strip_prefix("0x") {
+				Some(hex) => u32::from_str_radix(hex, 16).ok(),
+				None => arg.parse().ok(),
+			}
+		};
+		Some(match kind {
+			"flag" => Target::Flag(Flag(num()?.try_into().ok()?)),
+			"var" => Target::Var(Var(num()?.try_into().ok()?)),
+			"global" => Target::Global(Global(num()?.try_into().ok()?)),
+			"scene" => Target::Scene(FileId(num().or_else(|| lookup.index(arg))?)),
+			"battle" => Target::Battle(BattleId(num()?)),
+			"item" => Target::Item(ItemId(num()?.try_into().ok()?)),
+			_ => return None,
+		})
+	}
+
+	fn kind(&self) -> &'static str {
+		match self {
+			Target::Flag(_) => "flag",
+			Target::Var(_) => "var",
+			Target::Global(_) => "global",
+			Target::Scene(_) => "scene",
+			Target::Battle(_) => "battle",
+			Target::Item(_) => "item",
+		}
+	}
+
+	fn id(&self) -> u32 {
+		match *self {
+			Target::Flag(v) => v.0 as u32,
+			Target::Var(v) => v.0 as u32,
+			Target::Global(v) => v.0 as u32,
+			Target::Scene(v) => v.0,
+			Target::Battle(v) => v.0,
+			Target::Item(v) => v.0 as u32,
+		}
+	}
+}
+
+impl std::fmt::Display for Target {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Target::Scene(v) => write!(f, "scene[0x{:08X}]", v.0),
+			_ => write!(f, "{}[{}]", self.kind(), self.id()),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Index {
+	pub uses: Vec<Use>,
+}
+
+impl Index {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn add(&mut self, file: &str, scena: &Scena) {
+		for (i, func) in scena.functions().iter().enumerate() {
+			code(func, &format!("fn[{i}]:"), &mut |location, target, access| {
+				self.uses.push(Use { file: file.to_owned(), location, target, access });
+			});
+		}
+	}
+
+	pub fn find(&self, target: Target) -> impl Iterator<Item=&Use> {
+		self.uses.iter().filter(move |a| a.target == target)
+	}
+
+	pub fn to_json(&self, lookup: &dyn Lookup) -> serde_json::Value {
+		self.uses.iter().map(|u| {
+			let mut v = serde_json::json!({
+				"file": u.file,
+				"location": u.location,
+				"kind": u.target.kind(),
+				"id": u.target.id(),
+				"access": match u.access {
+					Access::Read => "read",
+					Access::Write => "write",
+				},
+			});
+			if let Target::Scene(id) = u.target {
+				v["name"] = lookup.name(id.0).into();
+			}
+			v
+		}).collect()
+	}
+
+	/// Writes the scene transitions as a Graphviz graph.
+	pub fn to_dot(&self, lookup: &dyn Lookup) -> String {
+		let mut edges = BTreeSet::new();
+		for u in &self.uses {
+			let Target::Scene(id) = u.target else { continue };
+			let to = match lookup.name(id.0) {
+				Some(name) => node_name(&name),
+				None => format!("0x{:08X}", id.0),
+			};
+			edges.insert((node_name(&u.file), to));
+		}
+
+		let mut out = String::new();
+		out.push_str("digraph scenes {\n");
+		for (from, to) in edges {
+			out.push_str(&format!("\t{from:?} -> {to:?};\n"));
+		}
+		out.push_str("}\n");
+		out
+	}
+}
+
+/// Strips directory and extension, since the lookup's names are not formatted quite like the files.
+pub(crate) fn node_name(name: &str) -> String {
+	let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+	let name = name.split('.').next().unwrap_or(name);
+	name.to_lowercase()
+}
+
+type Sink<'a> = dyn FnMut(String, Target, Access) + 'a;
+
+fn code(code: &Code, prefix: &str, f: &mut Sink) {
+	for (i, insn) in code.iter().enumerate() {
+		let loc = format!("{prefix}{i}");
+		match insn {
+			FlatInsn::Unless(e, _) | FlatInsn::Switch(e, _, _) => expr(e, &loc, f),
+			FlatInsn::Insn(insn) => self::insn(insn, &loc, f),
+			FlatInsn::Goto(_) | FlatInsn::Label(_) => {}
+		}
+	}
+}
+
+fn expr(e: &Expr, loc: &str, f: &mut Sink) {
+	for term in &e.0 {
+		match term {
+			ExprTerm::Flag(v) => f(loc.to_owned(), Target::Flag(*v), Access::Read),
+			ExprTerm::Var(v) => f(loc.to_owned(), Target::Var(*v), Access::Read),
+			ExprTerm::Global(v) => f(loc.to_owned(), Target::Global(*v), Access::Read),
+			ExprTerm::Insn(i) => insn(i, loc, f),
+			_ => {}
+		}
+	}
+}
+
+fn text(t: &Text, loc: &str, f: &mut Sink) {
+	for seg in t.0.iter().flatten() {
+		if let TextSegment::Item(v) = seg {
+			f(loc.to_owned(), Target::Item(*v), Access::Read);
+		}
+	}
+}
+
+fn insn(insn: &Insn, loc: &str, f: &mut Sink) {
+	macro run {
+		([$(($ident:ident $(($_n:ident $($ty:tt)*))*))*]) => {
+			match insn {
+				$(Insn::$ident($($_n),*) => {
+					$(arg!($_n $($ty)*);)*
+				})*
+			}
+		}
+	}
+
+	macro arg {
+		($v:ident Flag) => { f(loc.to_owned(), Target::Flag(*$v), Access::Read) },
+		($v:ident Var) => { f(loc.to_owned(), Target::Var(*$v), Access::Read) },
+		($v:ident Global) => { f(loc.to_owned(), Target::Global(*$v), Access::Read) },
+		($v:ident BattleId) => { f(loc.to_owned(), Target::Battle(*$v), Access::Read) },
+		($v:ident ItemId) => { f(loc.to_owned(), Target::Item(*$v), Access::Read) },
+		($v:ident Expr) => { expr($v, loc, f) },
+		($v:ident Text) => { text($v, loc, f) },
+		($v:ident Code) => { code($v, &format!("{loc}/"), f) },
+		($v:ident $($ty:tt)*) => { let _ = $v; },
+	}
+
+	match insn {
+		Insn::FlagSet(v) | Insn::FlagUnset(v) => {
+			f(loc.to_owned(), Target::Flag(*v), Access::Write);
+		}
+		Insn::Var(v, e) => {
+			f(loc.to_owned(), Target::Var(*v), Access::Write);
+			expr(e, loc, f);
+		}
+		Insn::Global(v, e) => {
+			f(loc.to_owned(), Target::Global(*v), Access::Write);
+			expr(e, loc, f);
+		}
+		Insn::MenuWait(v) => {
+			f(loc.to_owned(), Target::Var(*v), Access::Write);
+		}
+		Insn::ItemAdd(v, _) | Insn::ItemRemove(v, _) => {
+			f(loc.to_owned(), Target::Item(*v), Access::Write);
+		}
+		Insn::NewScene(v, ..) => {
+			f(loc.to_owned(), Target::Scene(*v), Access::Read);
+		}
+		_ => {
+			themelios::scena::code::introspect!(run);
+		}
+	}
+}
+
+#[test]
+fn uses() {
+	use themelios::scena::code::{ExprOp, Label};
+	let func = Code(vec![
+		FlatInsn::Unless(Expr(vec![ExprTerm::Flag(Flag(5)), ExprTerm::Var(Var(2)), ExprTerm::Op(ExprOp::BoolAnd)]), Label(0)),
+		FlatInsn::Insn(Insn::Fork(CharId::Self_, ForkId(0), Code(vec![
+			FlatInsn::Insn(Insn::FlagSet(Flag(6))),
+		]))),
+		FlatInsn::Insn(Insn::NewScene(FileId(0x10001), 2, 0, 0)),
+		FlatInsn::Label(Label(0)),
+	]);
+	let mut out = Vec::new();
+	code(&func, "fn[0]:", &mut |loc, target, access| out.push((loc, target.to_string(), access)));
+	assert_eq!(out, [
+		("fn[0]:0".to_owned(), "flag[5]".to_owned(), Access::Read),
+		("fn[0]:0".to_owned(), "var[2]".to_owned(), Access::Read),
+		("fn[0]:1/0".to_owned(), "flag[6]".to_owned(), Access::Write),
+		("fn[0]:2".to_owned(), "scene[0x00010001]".to_owned(), Access::Read),
+	]);
+	let lookup = &themelios::lookup::NullLookup;
+	assert_eq!(Target::parse("scene[0x00010001]", lookup), Some(Target::Scene(FileId(0x10001))));
+	assert_eq!(Target::parse("var[2]", lookup), Some(Target::Var(Var(2))));
+}
@@ -48,12 +48,15 @@ pub mod ed6 {
 		pub insns: Vec<(Addr, crate::insn::Insn)>,
 	}
 
-	pub fn read_monster(game: Game, data: &[u8]) -> Result<Ani, ReadError> {
+	/// Reads only the chip list of a monster's animation script, as `(ch, cp)` pairs. Unlike
+	/// [`read_monster`], this does not need to understand the script itself.
+	pub fn read_chips(data: &[u8]) -> Result<Vec<(FileId, FileId)>, ReadError> {
 		let mut f = Reader::new(data);
-		let f_func_table = f.ptr16()?;
-		let f_sprite_offsets = f.ptr16()?;
-		let f_bones = f.ptr16()?;
+		f.slice(6)?;
+		chips(&mut f)
+	}
 
+	fn chips(f: &mut Reader) -> Result<Vec<(FileId, FileId)>, ReadError> {
 		let mut chips = Vec::new();
 		loop {
 			match f.u32()? {
@@ -61,6 +64,16 @@ pub mod ed6 {
 				a => chips.push((FileId(a), FileId(f.u32()?)))
 			}
 		}
+		Ok(chips)
+	}
+
+	pub fn read_monster(game: Game, data: &[u8]) -> Result<Ani, ReadError> {
+		let mut f = Reader::new(data);
+		let f_func_table = f.ptr16()?;
+		let f_sprite_offsets = f.ptr16()?;
+		let f_bones = f.ptr16()?;
+
+		let chips = chips(&mut f)?;
 
 		let models = super::strings(&mut f)?;
 
@@ -130,6 +143,20 @@ pub mod ed6 {
 		})
 	}
 
+	#[test]
+	fn test_read_chips() -> Result<(), Box<dyn std::error::Error>> {
+		let mut data = vec![0; 6];
+		for a in [0x00100002u32, 0x00100003, 0x00100004, 0x00100005, 0xFFFFFFFF] {
+			data.extend(a.to_le_bytes());
+		}
+		assert_eq!(read_chips(&data)?, [
+			(FileId(0x00100002), FileId(0x00100003)),
+			(FileId(0x00100004), FileId(0x00100005)),
+		]);
+		assert!(read_chips(&data[..14]).is_err());
+		Ok(())
+	}
+
 	#[test]
 	fn test() -> Result<(), Box<dyn std::error::Error>> {
 		let mut i = std::fs::read_dir("../data/fc.extract/10/")?.collect::<Result<Vec<_>, _>>()?;
@@ -5,3 +5,7 @@
 
 pub mod types;
 pub mod util;
+
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+pub use serde as __serde;
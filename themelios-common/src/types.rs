@@ -15,6 +15,34 @@ macro_rules! impl_from_into {
 	}
 }
 
+// Implemented by hand rather than derived, so that crates using `newtype!` don't need to depend on
+// serde themselves.
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __serde_transparent {
+	($outer:ident($inner:ty)) => {
+		impl $crate::__serde::Serialize for $outer {
+			fn serialize<S: $crate::__serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+				$crate::__serde::Serialize::serialize(&self.0, s)
+			}
+		}
+
+		impl<'de> $crate::__serde::Deserialize<'de> for $outer {
+			fn deserialize<D: $crate::__serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+				<$inner as $crate::__serde::Deserialize>::deserialize(d).map($outer)
+			}
+		}
+	}
+}
+
+#[cfg(not(feature = "serde"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __serde_transparent {
+	($outer:ident($inner:ty)) => {}
+}
+
 #[macro_export]
 macro_rules! newtype {
 	($outer:ident($inner:ident)) => {
@@ -22,12 +50,14 @@ macro_rules! newtype {
 		#[repr(transparent)]
 		pub struct $outer(pub $inner);
 		$crate::impl_from_into!($outer($inner));
+		$crate::__serde_transparent!($outer($inner));
 	};
 	($outer:ident($inner:ident), $fmt:literal) => {
 		#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 		#[repr(transparent)]
 		pub struct $outer(pub $inner);
 		$crate::impl_from_into!($outer($inner));
+		$crate::__serde_transparent!($outer($inner));
 
 		impl ::core::fmt::Debug for $outer {
 			fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -40,9 +70,11 @@ macro_rules! newtype {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FuncId(pub u16, pub u16);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pos2 {
 	pub x: i32,
 	pub z: i32,
@@ -77,6 +109,7 @@ newtype!(AngularSpeed(u16));
 newtype!(Angle32(i32));
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CharId {
 	FieldParty(u16),
 	Local(LocalCharId),
@@ -111,6 +144,10 @@ newtype!(QuestId(u16));
 newtype!(QuestFlags(u8));
 newtype!(QuestTask(u16));
 
+// In t_item. Which numbers mean what is not mapped out yet.
+newtype!(ItemUsage(u8));
+newtype!(ItemCategory(u8));
+
 // 0x0002 PF_NOVEC
 // 0x0004 PF_NOHEIGHT
 // 0x0008 PF_NODISP
@@ -168,6 +205,7 @@ newtype!(VisId(u8));
 newtype!(ForkId(u16));
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CharAttr(pub CharId, pub u8);
 
 // Translatable string
@@ -175,6 +213,7 @@ pub struct CharAttr(pub CharId, pub u8);
 #[repr(transparent)]
 pub struct TString(pub String);
 impl_from_into!(TString(String));
+__serde_transparent!(TString(String));
 
 impl std::ops::Deref for TString {
 	type Target = String;
@@ -216,12 +255,14 @@ impl std::fmt::Debug for TString {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BaseGame {
 	Fc, Sc, Tc,
 	Zero, Ao,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Game {
 	Fc, FcEvo, FcKai,
 	Sc, ScEvo, ScKai,
@@ -111,7 +111,10 @@ pub fn bytecode(tokens: TokenStream0) -> TokenStream0 {
 		}
 	};
 
+	// One attribute per line: the table is over 64 KiB, and serde's derive on the enum panics on
+	// literals that long ("assertion failed: repr.len() <= u16::MAX as usize").
 	let doc_insn_table = make_table(&ctx);
+	let doc_insn_table = doc_insn_table.split('\n');
 
 	let mut hex = BTreeMap::<Ident, BTreeMap<u8, Vec<Ident>>>::new();
 	for insn in &ctx.defs {
@@ -147,7 +150,7 @@ pub fn bytecode(tokens: TokenStream0) -> TokenStream0 {
 		#attrs
 		/// # Encoding
 		// /// <details><summary>Click to expand</summary>
-		#[doc = #doc_insn_table]
+		#(#[doc = #doc_insn_table])*
 		// /// </details>
 		pub enum Insn {
 			#Insn_body
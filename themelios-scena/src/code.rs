@@ -11,6 +11,7 @@ mod insn;
 pub use insn::{Insn, introspect};
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Label(pub usize);
 
 impl std::fmt::Debug for Label {
@@ -21,6 +22,7 @@ impl std::fmt::Debug for Label {
 
 // TODO make this one stricter so it does not permit duplicate labels
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Code(pub Vec<FlatInsn>);
 
 impl std::ops::Deref for Code {
@@ -39,6 +41,7 @@ impl std::ops::DerefMut for Code {
 
 // I *could* make this generic over <Expr, Insn, Label, LabelDef>, but honestly, no.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FlatInsn {
 	Unless(Expr, Label),
 	Goto(Label),
@@ -256,6 +259,7 @@ fn write_raw_insn(f: &mut Writer, game: Game, insn: RawOInsn) -> Result<(), Writ
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum OpKind {
 	Unary,
 	Binary, // includes comparisons
@@ -264,6 +268,7 @@ pub enum OpKind {
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[derive(num_enum::TryFromPrimitive, num_enum::IntoPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum ExprOp {
 	Eq      = 0x02, // ==
@@ -308,6 +313,7 @@ impl ExprOp {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum ExprTerm {
 	Const(u32)         = 0x00,
@@ -322,6 +328,7 @@ pub enum ExprTerm {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Expr(pub Vec<ExprTerm>);
 
 impl Expr {
@@ -33,6 +33,7 @@ fn iset(g: Game) -> ISet {
 themelios_macros::bytecode! {
 	(game: Game)
 	#[games(iset(game) => ISet::{Fc, FcEvo, Sc, ScEvo, Tc, TcEvo, Zero, ZeroEvo, Ao, AoEvo})]
+	#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 	[
 		skip!(1), // null
 		Return(), // [return]
@@ -3,10 +3,14 @@ use gospel::write::{Writer, Le as _};
 use crate::util::*;
 use crate::types::ItemId;
 
+pub mod layout;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Text(pub Vec<Vec<TextSegment>>);
 
 #[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TextSegment {
 	String(String),
 	Line,
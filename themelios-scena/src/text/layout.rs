@@ -0,0 +1,340 @@
+//! Measuring and wrapping dialogue text.
+//!
+//! All widths are in pixels. `#`-codes such as `#0040F` are not drawn by the game, so they are
+//! treated as having no width, as are item names, whose length is not known here.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::types::Game;
+use super::{Text, TextSegment};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Metrics {
+	/// Width of half-width characters, such as ASCII.
+	pub half: u32,
+	/// Width of full-width characters.
+	pub full: u32,
+	/// Characters whose width differs from the above, for fonts that are not monospaced.
+	pub glyphs: BTreeMap<char, u32>,
+	/// Maximum width of a line.
+	pub line_width: u32,
+	/// Maximum number of lines on a page.
+	pub page_lines: usize,
+}
+
+/// Where a text does not fit in its text box.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+	Line { page: usize, line: usize, width: u32 },
+	Page { page: usize, lines: usize },
+}
+
+impl Metrics {
+	/// Metrics for the standard dialogue box in the game's original font.
+	///
+	/// These are approximations; fan translations with different fonts will want to fill in
+	/// [`glyphs`](Self::glyphs), for example with [`parse_glyphs`].
+	pub fn for_game(game: Game) -> Metrics {
+		let chars = if game.is_ed7() { 26 } else { 22 };
+		Metrics {
+			half: 8,
+			full: 16,
+			glyphs: BTreeMap::new(),
+			line_width: chars * 16,
+			page_lines: 3,
+		}
+	}
+
+	pub fn char_width(&self, c: char) -> u32 {
+		if let Some(&w) = self.glyphs.get(&c) {
+			w
+		} else if is_half(c) {
+			self.half
+		} else {
+			self.full
+		}
+	}
+
+	pub fn str_width(&self, s: &str) -> u32 {
+		let mut units = Vec::new();
+		str_units(s, &mut units);
+		units.iter().filter_map(Unit::char).map(|c| self.char_width(c)).sum()
+	}
+
+	/// Widths of each line on a page. Trailing empty lines are not included.
+	pub fn line_widths(&self, page: &[TextSegment]) -> Vec<u32> {
+		let mut out = vec![0];
+		for seg in page {
+			match seg {
+				TextSegment::String(s) => *out.last_mut().unwrap() += self.str_width(s),
+				TextSegment::Line => out.push(0),
+				_ => {}
+			}
+		}
+		while out.len() > 1 && out.last() == Some(&0) {
+			out.pop();
+		}
+		out
+	}
+
+	pub fn check(&self, text: &Text) -> Vec<Overflow> {
+		let mut out = Vec::new();
+		for (page, segs) in text.0.iter().enumerate() {
+			let widths = self.line_widths(segs);
+			for (line, &width) in widths.iter().enumerate() {
+				if width > self.line_width {
+					out.push(Overflow::Line { page, line, width });
+				}
+			}
+			if widths.len() > self.page_lines {
+				out.push(Overflow::Page { page, lines: widths.len() });
+			}
+		}
+		out
+	}
+
+	/// Reflows each page so that its lines fit, spilling over onto new pages as needed.
+	///
+	/// Existing line breaks are replaced with a space between half-width words and removed
+	/// elsewhere. Lines are broken at spaces, or between full-width characters. A single word that
+	/// is wider than a line is left as is.
+	pub fn wrap(&self, text: &Text) -> Text {
+		let mut pages = Vec::new();
+		for page in &text.0 {
+			let mut units = Vec::new();
+			for seg in page {
+				match seg {
+					TextSegment::String(s) => str_units(s, &mut units),
+					TextSegment::Line => units.push(Unit::Line),
+					seg => units.push(Unit::Seg(seg.clone())),
+				}
+			}
+			let lines = self.reflow(join_lines(units));
+			// New pages need a wait before them, just like the original ones do.
+			let wait = page.last() == Some(&TextSegment::Wait);
+			let n = lines.len().div_ceil(self.page_lines.max(1));
+			for (i, chunk) in lines.chunks(self.page_lines.max(1)).enumerate() {
+				let mut segs = Vec::new();
+				for (j, line) in chunk.iter().enumerate() {
+					if j != 0 {
+						segs.push(TextSegment::Line);
+					}
+					push_units(&mut segs, line);
+				}
+				if wait && i + 1 != n {
+					segs.push(TextSegment::Wait);
+				}
+				pages.push(segs);
+			}
+		}
+		Text(pages)
+	}
+
+	/// Like [`wrap`](Self::wrap), but for plain strings with `\n` line breaks, which have no pages.
+	pub fn wrap_str(&self, s: &str) -> String {
+		let mut units = Vec::new();
+		for (i, line) in s.split('\n').enumerate() {
+			if i != 0 {
+				units.push(Unit::Line);
+			}
+			str_units(line, &mut units);
+		}
+		let mut out = String::new();
+		for (i, line) in self.reflow(join_lines(units)).iter().enumerate() {
+			if i != 0 {
+				out.push('\n');
+			}
+			for u in line {
+				match u {
+					Unit::Char(c) => out.push(*c),
+					Unit::Code(s) => out.push_str(s),
+					Unit::Line | Unit::Seg(_) => unreachable!(),
+				}
+			}
+		}
+		out
+	}
+
+	fn reflow(&self, units: Vec<Unit>) -> Vec<Vec<Unit>> {
+		let mut lines = Vec::new();
+		let mut line = Vec::new();
+		let mut width = 0;
+		// Index in `line` where it may be broken, and whether that unit is a space to be dropped.
+		let mut brk = None;
+		let mut queue = VecDeque::from(units);
+		while let Some(u) = queue.pop_front() {
+			let Unit::Char(c) = u else {
+				line.push(u);
+				continue
+			};
+			let prev = line.iter().rev().find_map(Unit::char);
+			if c == ' ' && prev.is_none() && !lines.is_empty() {
+				continue
+			}
+			match prev {
+				Some(_) if c == ' ' => brk = Some((line.len(), true)),
+				Some(p) if can_break(p, c) => brk = Some((line.len(), false)),
+				_ => {}
+			}
+			width += self.char_width(c);
+			line.push(u);
+
+			if width > self.line_width && c != ' ' {
+				if let Some((i, space)) = brk.take() {
+					let mut rest = line.split_off(i).into_iter();
+					if space {
+						rest.next();
+					}
+					for u in rest.rev() {
+						queue.push_front(u);
+					}
+					while line.last() == Some(&Unit::Char(' ')) {
+						line.pop();
+					}
+					lines.push(std::mem::take(&mut line));
+					width = 0;
+				}
+			}
+		}
+		lines.push(line);
+		lines
+	}
+}
+
+/// Parses a table of glyph widths, one `char width` pair per line.
+///
+/// The character can also be written as `U+XXXX`, which is needed for whitespace. On failure,
+/// returns the (one-based) number of the offending line.
+pub fn parse_glyphs(src: &str) -> Result<BTreeMap<char, u32>, usize> {
+	let mut out = BTreeMap::new();
+	for (i, line) in src.lines().enumerate() {
+		if line.trim().is_empty() {
+			continue
+		}
+		let parse = || {
+			let (c, w) = line.trim_end().rsplit_once(' ')?;
+			let c = match c.strip_prefix("U+") {
+				Some(hex) => char::from_u32(u32::from_str_radix(hex, 16).ok()?)?,
+				None => {
+					let mut it = c.chars();
+					let c = it.next()?;
+					if it.next().is_some() {
+						return None
+					}
+					c
+				}
+			};
+			Some((c, w.parse().ok()?))
+		};
+		let (c, w) = parse().ok_or(i + 1)?;
+		out.insert(c, w);
+	}
+	Ok(out)
+}
+
+fn is_half(c: char) -> bool {
+	c.is_ascii() || ('\u{FF61}'..='\u{FF9F}').contains(&c)
+}
+
+// Japanese line breaking rules (kinsoku shori), simplified.
+fn can_break(prev: char, next: char) -> bool {
+	(!is_half(prev) || !is_half(next))
+		&& !"、。，．・：；？！ー…‥」』）】〉》］ぁぃぅぇぉっゃゅょァィゥェォッャュョ".contains(next)
+		&& !"「『（【〈《［".contains(prev)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Unit {
+	Char(char),
+	Code(String),
+	Line,
+	Seg(TextSegment),
+}
+
+impl Unit {
+	fn char(&self) -> Option<char> {
+		match self {
+			Unit::Char(c) => Some(*c),
+			_ => None,
+		}
+	}
+}
+
+fn str_units(mut s: &str, out: &mut Vec<Unit>) {
+	while let Some(c) = s.chars().next() {
+		let n = code_len(s);
+		if n != 0 {
+			out.push(Unit::Code(s[..n].to_owned()));
+			s = &s[n..];
+		} else {
+			out.push(Unit::Char(c));
+			s = &s[c.len_utf8()..];
+		}
+	}
+}
+
+fn code_len(s: &str) -> usize {
+	let Some(rest) = s.strip_prefix('#') else { return 0 };
+	let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+	if rest.as_bytes().get(digits).is_some_and(u8::is_ascii_alphabetic) {
+		digits + 2
+	} else {
+		0
+	}
+}
+
+fn join_lines(units: Vec<Unit>) -> Vec<Unit> {
+	let mut out = Vec::with_capacity(units.len());
+	for (i, u) in units.iter().enumerate() {
+		if *u == Unit::Line {
+			let prev = units[..i].iter().rev().find_map(Unit::char);
+			let next = units[i+1..].iter().find_map(Unit::char);
+			if matches!((prev, next), (Some(a), Some(b)) if is_half(a) && is_half(b) && a != ' ' && b != ' ') {
+				out.push(Unit::Char(' '));
+			}
+		} else {
+			out.push(u.clone());
+		}
+	}
+	out
+}
+
+fn push_units(out: &mut Vec<TextSegment>, line: &[Unit]) {
+	for u in line {
+		let s = match u {
+			Unit::Char(c) => c.to_string(),
+			Unit::Code(s) => s.clone(),
+			Unit::Seg(seg) => {
+				out.push(seg.clone());
+				continue
+			}
+			Unit::Line => unreachable!(),
+		};
+		if let Some(TextSegment::String(prev)) = out.last_mut() {
+			prev.push_str(&s);
+		} else {
+			out.push(TextSegment::String(s));
+		}
+	}
+}
+
+#[test]
+fn wrap() {
+	let m = Metrics { line_width: 80, page_lines: 2, ..Metrics::for_game(Game::Fc) };
+	let s = |s: &str| TextSegment::String(s.to_owned());
+
+	let text = Text(vec![vec![
+		s("#0040FThe quick brown"), TextSegment::Line, s("fox jumps over"), TextSegment::Color(5),
+		s(" the lazy dog."), TextSegment::Wait,
+	]]);
+	assert_eq!(m.check(&text), [Overflow::Line { page: 0, line: 0, width: 120 }, Overflow::Line { page: 0, line: 1, width: 224 }]);
+	let wrapped = m.wrap(&text);
+	assert_eq!(wrapped, Text(vec![
+		vec![s("#0040FThe quick"), TextSegment::Line, s("brown fox"), TextSegment::Wait],
+		vec![s("jumps over"), TextSegment::Color(5), TextSegment::Line, s("the lazy"), TextSegment::Wait],
+		vec![s("dog."), TextSegment::Wait],
+	]));
+	assert_eq!(m.check(&wrapped), []);
+
+	assert_eq!(m.wrap_str("「あいうえお」と言った。"), "「あいうえ\nお」と言っ\nた。");
+}
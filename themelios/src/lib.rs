@@ -1,4 +1,4 @@
-#![feature(array_try_map, array_try_from_fn, array_methods)]
+#![feature(array_try_map, array_try_from_fn, array_methods, decl_macro)]
 
 pub mod tables;
 #[doc(inline)]
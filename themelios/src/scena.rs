@@ -4,3 +4,104 @@ pub mod ed6;
 pub mod ed7;
 
 pub mod decompile;
+pub mod validate;
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde() -> Result<(), Box<dyn std::error::Error>> {
+	use code::*;
+	use crate::types::*;
+	let code = Code(vec![
+		FlatInsn::Unless(Expr(vec![ExprTerm::Flag(Flag(100)), ExprTerm::Op(ExprOp::Not)]), Label(0)),
+		FlatInsn::Insn(Insn::TextTalk(CharId::Local(LocalCharId(2)), crate::text::Text(vec![vec![
+			crate::text::TextSegment::String("Hi".into()),
+			crate::text::TextSegment::Item(ItemId(3)),
+		]]))),
+		FlatInsn::Insn(Insn::NewScene(FileId(0x10001), 1, 0, 0)),
+		FlatInsn::Label(Label(0)),
+		FlatInsn::Insn(Insn::Return()),
+	]);
+	let json = serde_json::to_string(&code)?;
+	assert_eq!(serde_json::from_str::<Code>(&json)?, code);
+	Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_ed6() -> Result<(), Box<dyn std::error::Error>> {
+	use crate::types::*;
+	let scena = ed6::Scena {
+		path: "t0100".into(),
+		map: "t0100".into(),
+		town: TownId(1),
+		bgm: BgmId(2),
+		item_use: FuncId(0, 3),
+		includes: [FileId(0x10001), FileId::NONE, FileId::NONE, FileId::NONE, FileId::NONE, FileId::NONE, FileId::NONE, FileId::NONE],
+		ch: vec![FileId(0x30002)],
+		cp: vec![FileId(0x30003)],
+		npcs: vec![ed6::Npc {
+			name: TString::from("Estelle"),
+			pos: Pos3::new(1000, 0, -2000),
+			angle: Angle(90),
+			x: 0,
+			cp: ChipId(0),
+			frame: 0,
+			ch: ChipId(0),
+			flags: CharFlags(0x101),
+			init: FuncId(0, 0),
+			talk: FuncId(0, 1),
+		}],
+		monsters: Vec::new(),
+		triggers: Vec::new(),
+		look_points: Vec::new(),
+		entries: Vec::new(),
+		functions: vec![code::Code(vec![code::FlatInsn::Insn(code::Insn::Return())])],
+	};
+	let json = serde_json::to_string(&scena)?;
+	assert_eq!(serde_json::from_str::<ed6::Scena>(&json)?, scena);
+	Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_ed7() -> Result<(), Box<dyn std::error::Error>> {
+	use crate::types::*;
+	let scena = ed7::Scena {
+		name1: "c0100".into(),
+		name2: "Crossbell".into(),
+		filename: "c0100".into(),
+		town: TownId(1),
+		bgm: BgmId(2),
+		flags: 0,
+		includes: [FileId(0x10001), FileId::NONE, FileId::NONE, FileId::NONE, FileId::NONE, FileId::NONE],
+		chips: vec![FileId(0x30002)],
+		labels: None,
+		npcs: vec![ed7::Npc {
+			name: TString::from("Lloyd"),
+			pos: Pos3::new(1000, 0, -2000),
+			angle: Angle(90),
+			flags: CharFlags(0x101),
+			unk2: 0,
+			chip: ChipId(0),
+			init: FuncId(0, 0),
+			talk: FuncId(0, 1),
+			unk4: 0,
+		}],
+		monsters: Vec::new(),
+		triggers: Vec::new(),
+		look_points: Vec::new(),
+		animations: vec![ed7::Animation { speed: Time(100), frames: vec![0, 1, 2] }],
+		entry: None,
+		functions: vec![code::Code(vec![code::FlatInsn::Insn(code::Insn::Return())])],
+		sepith: Vec::new(),
+		at_rolls: Vec::new(),
+		placements: Vec::new(),
+		battles: Vec::new(),
+		item_use: FuncId(0, 3),
+		unk2: 0,
+		unk3: 0,
+	};
+	let json = serde_json::to_string(&scena)?;
+	assert_eq!(serde_json::from_str::<ed7::Scena>(&json)?, scena);
+	Ok(())
+}
@@ -3,6 +3,7 @@ use std::collections::{HashMap, HashSet};
 use super::code::{FlatInsn, Insn, Expr, Label, Code};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TreeInsn {
 	If(Vec<(Option<Expr>, Vec<TreeInsn>)>),
 	Switch(Expr, Vec<(Option<u16>, Vec<TreeInsn>)>),
@@ -5,6 +5,7 @@ use themelios_common::util::*;
 use super::code::Code;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Scena {
 	pub path: String, // [Path; フォルダ]
 	pub map: String, // [Map; マップファイル]
@@ -26,6 +27,7 @@ pub struct Scena {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Entry {  // [Entry]
 	pub pos: Pos3,  // [PlyX, PlyY, PlyZ; Ｘ/Ｙ/Ｚ座標(1m単位)]
 	pub chr: u16,   // [PlyChr; キャラパターン] Always 4
@@ -46,6 +48,7 @@ pub struct Entry {  // [Entry]
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Npc { // [Player]
 	// They put name last, but that sucks
 	pub name: TString, // [Name]
@@ -61,6 +64,7 @@ pub struct Npc { // [Player]
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Monster { // [Monster]
 	pub name: TString,
 	pub pos: Pos3,
@@ -74,6 +78,7 @@ pub struct Monster { // [Monster]
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Trigger { // [Event]
 	pub pos1: Pos3, // [X, Y, Z]
 	pub pos2: Pos3, // [X, Y, Z]
@@ -83,6 +88,7 @@ pub struct Trigger { // [Event]
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LookPoint { // [LookPoint]
 	pub pos: Pos3, // [X, Y, Z]
 	pub radius: Length, // [R],
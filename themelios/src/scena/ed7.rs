@@ -7,6 +7,7 @@ use themelios_common::util::*;
 use super::code::{Code, FlatInsn, Insn};
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Scena {
 	pub name1: String,
 	pub name2: String,
@@ -38,6 +39,7 @@ pub struct Scena {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Label {
 	pub name: TString,
 	pub pos: Vec3,
@@ -46,6 +48,7 @@ pub struct Label {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Npc {
 	pub name: TString,
 	pub pos: Pos3,
@@ -59,6 +62,7 @@ pub struct Npc {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Monster {
 	pub pos: Pos3,
 	pub angle: i16,
@@ -72,6 +76,7 @@ pub struct Monster {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Trigger {
 	pub pos: Vec3,
 	pub radius: f32,
@@ -86,6 +91,7 @@ pub struct Trigger {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LookPoint {
 	pub pos: Pos3,
 	pub radius: u32,
@@ -98,6 +104,7 @@ pub struct LookPoint {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Entry {
 	pub pos: Pos3,
 	pub unk1: u32,
@@ -118,12 +125,14 @@ pub struct Entry {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Animation {
 	pub speed: Time,
 	pub frames: Vec<u8>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Battle {
 	pub flags: u16,
 	pub level: u16,
@@ -139,6 +148,7 @@ pub struct Battle {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BattleSetup {
 	pub weight: u8,
 	pub enemies: [FileId; 8],
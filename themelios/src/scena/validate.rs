@@ -0,0 +1,354 @@
+//! Checks for mistakes that encode fine, but are likely to crash or misbehave in game.
+//!
+//! Function ids are checked against this file's own functions, and against included files if
+//! their function counts are known. Local character and chip ids are checked against the declared
+//! npcs, monsters and chips; chip slots loaded by `ED6LoadChip`/`ED7LoadChip` are assumed valid.
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::scena::code::{Code, FlatInsn, Insn, Label};
+use crate::scena::{ed6, ed7};
+use crate::types::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Location {
+	Header,
+	Entry(usize),
+	Npc(usize),
+	Monster(usize),
+	Trigger(usize),
+	LookPoint(usize),
+	/// A function, and a path of indices into its code, descending into nested code blocks.
+	Function(usize, Vec<usize>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Problem {
+	#[error("function fn[{0},{1}] does not exist")]
+	NoFunction(u16, u16),
+	#[error("include slot {0} is empty")]
+	NoInclude(u16),
+	#[error("char[{0}] does not exist, there are only {1} npcs and monsters")]
+	NoChar(u16, usize),
+	#[error("chip[{0}] does not exist, there are only {1} chips")]
+	NoChip(u16, usize),
+	#[error("duplicate case {0}")]
+	DuplicateCase(u16),
+	#[error("unreachable code")]
+	Unreachable,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{location}: {problem}")]
+pub struct Issue {
+	pub location: Location,
+	pub problem: Problem,
+}
+
+impl std::fmt::Display for Location {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Location::Header => write!(f, "scena"),
+			Location::Entry(n) => write!(f, "entry[{n}]"),
+			Location::Npc(n) => write!(f, "npc[{n}]"),
+			Location::Monster(n) => write!(f, "monster[{n}]"),
+			Location::Trigger(n) => write!(f, "trigger[{n}]"),
+			Location::LookPoint(n) => write!(f, "look_point[{n}]"),
+			Location::Function(n, path) => {
+				write!(f, "fn[{n}]")?;
+				for (i, p) in path.iter().enumerate() {
+					f.write_str(if i == 0 { ":" } else { "/" })?;
+					write!(f, "{p}")?;
+				}
+				Ok(())
+			}
+		}
+	}
+}
+
+/// Number of functions in an included file, if known.
+pub type Includes<'a> = &'a dyn Fn(FileId) -> Option<usize>;
+
+pub fn ed6(scena: &ed6::Scena, includes: Includes) -> Vec<Issue> {
+	let mut c = Check {
+		none: 0xFFFF,
+		includes: &scena.includes,
+		external: includes,
+		n_functions: scena.functions.len(),
+		n_chars: scena.npcs.len() + scena.monsters.len(),
+		n_chips: scena.ch.len(),
+		loaded_chips: loaded_chips(&scena.functions),
+		out: Vec::new(),
+	};
+
+	c.func(Location::Header, scena.item_use);
+	for (i, e) in scena.entries.iter().enumerate() {
+		c.func(Location::Entry(i), e.init);
+		c.func(Location::Entry(i), e.reinit);
+	}
+	for (i, n) in scena.npcs.iter().enumerate() {
+		c.chip(Location::Npc(i), n.ch);
+		if n.cp.0 as usize >= scena.cp.len() && n.cp.0 != c.none {
+			c.push(Location::Npc(i), Problem::NoChip(n.cp.0, scena.cp.len()));
+		}
+		c.func(Location::Npc(i), n.init);
+		c.func(Location::Npc(i), n.talk);
+	}
+	for (i, m) in scena.monsters.iter().enumerate() {
+		c.chip(Location::Monster(i), m.chip);
+	}
+	for (i, t) in scena.triggers.iter().enumerate() {
+		c.func(Location::Trigger(i), t.func);
+	}
+	for (i, l) in scena.look_points.iter().enumerate() {
+		c.func(Location::LookPoint(i), l.func);
+	}
+	for (i, f) in scena.functions.iter().enumerate() {
+		c.code(f, &mut vec![], i);
+	}
+	c.out
+}
+
+pub fn ed7(scena: &ed7::Scena, includes: Includes) -> Vec<Issue> {
+	let mut c = Check {
+		none: 0xFF,
+		includes: &scena.includes,
+		external: includes,
+		n_functions: scena.functions.len(),
+		n_chars: scena.npcs.len() + scena.monsters.len(),
+		n_chips: scena.chips.len(),
+		loaded_chips: loaded_chips(&scena.functions),
+		out: Vec::new(),
+	};
+
+	c.func(Location::Header, scena.item_use);
+	if let Some(e) = &scena.entry {
+		c.func(Location::Entry(0), e.init);
+		c.func(Location::Entry(0), e.reinit);
+	}
+	for (i, n) in scena.npcs.iter().enumerate() {
+		c.chip(Location::Npc(i), n.chip);
+		c.func(Location::Npc(i), n.init);
+		c.func(Location::Npc(i), n.talk);
+	}
+	for (i, m) in scena.monsters.iter().enumerate() {
+		c.chip(Location::Monster(i), m.chip);
+	}
+	for (i, t) in scena.triggers.iter().enumerate() {
+		c.func(Location::Trigger(i), t.function);
+	}
+	for (i, l) in scena.look_points.iter().enumerate() {
+		c.func(Location::LookPoint(i), l.function);
+	}
+	for (i, f) in scena.functions.iter().enumerate() {
+		c.code(f, &mut vec![], i);
+	}
+	c.out
+}
+
+struct Check<'a> {
+	/// Value used for "no function" or "no chip".
+	none: u16,
+	includes: &'a [FileId],
+	external: Includes<'a>,
+	n_functions: usize,
+	n_chars: usize,
+	n_chips: usize,
+	loaded_chips: BTreeSet<u16>,
+	out: Vec<Issue>,
+}
+
+impl Check<'_> {
+	fn push(&mut self, location: Location, problem: Problem) {
+		self.out.push(Issue { location, problem })
+	}
+
+	fn func(&mut self, loc: Location, FuncId(a, b): FuncId) {
+		if a == self.none || b == self.none {
+			return
+		}
+		let n = if a == 0 {
+			Some(self.n_functions)
+		} else {
+			match self.includes.get(a as usize) {
+				Some(&file) if file != FileId::NONE => (self.external)(file),
+				_ => return self.push(loc, Problem::NoInclude(a)),
+			}
+		};
+		if n.is_some_and(|n| b as usize >= n) {
+			self.push(loc, Problem::NoFunction(a, b))
+		}
+	}
+
+	fn char(&mut self, loc: &Location, c: CharId) {
+		if let CharId::Local(LocalCharId(n)) = c {
+			if n as usize >= self.n_chars {
+				self.push(loc.clone(), Problem::NoChar(n, self.n_chars))
+			}
+		}
+	}
+
+	fn chip(&mut self, loc: Location, c: ChipId) {
+		if c.0 as usize >= self.n_chips && c.0 != self.none && !self.loaded_chips.contains(&c.0) {
+			self.push(loc, Problem::NoChip(c.0, self.n_chips))
+		}
+	}
+
+	fn code(&mut self, code: &Code, path: &mut Vec<usize>, func: usize) {
+		let loc = |path: &Vec<usize>, i: usize| {
+			let mut path = path.clone();
+			path.push(i);
+			Location::Function(func, path)
+		};
+
+		for i in unreachable(code) {
+			self.push(loc(path, i), Problem::Unreachable);
+		}
+
+		for (i, insn) in code.iter().enumerate() {
+			match insn {
+				FlatInsn::Switch(_, cases, _) => {
+					let mut seen = BTreeSet::new();
+					for &(v, _) in cases {
+						if !seen.insert(v) {
+							self.push(loc(path, i), Problem::DuplicateCase(v));
+						}
+					}
+				}
+				FlatInsn::Insn(insn) => {
+					path.push(i);
+					self.insn(insn, path, func);
+					path.pop();
+				}
+				_ => {}
+			}
+		}
+	}
+
+	fn insn(&mut self, insn: &Insn, path: &mut Vec<usize>, func: usize) {
+		let loc = Location::Function(func, path.clone());
+
+		macro run {
+			([$(($ident:ident $(($_n:ident $($ty:tt)*))*))*]) => {
+				match insn {
+					$(Insn::$ident($($_n),*) => {
+						$(arg!($_n $($ty)*);)*
+					})*
+				}
+			}
+		}
+
+		macro arg {
+			($v:ident FuncId) => { self.func(loc.clone(), *$v) },
+			($v:ident CharId) => { self.char(&loc, *$v) },
+			($v:ident ChipId) => { self.chip(loc.clone(), *$v) },
+			($v:ident Code) => { self.code($v, path, func) },
+			($v:ident $($ty:tt)*) => { let _ = $v; },
+		}
+
+		crate::scena::code::introspect!(run);
+	}
+}
+
+fn loaded_chips(functions: &[Code]) -> BTreeSet<u16> {
+	fn walk(code: &Code, out: &mut BTreeSet<u16>) {
+		for insn in code.iter() {
+			match insn {
+				FlatInsn::Insn(Insn::ED6LoadChip(_, _, c) | Insn::ED7LoadChip(_, c)) => {
+					out.insert(c.0);
+				}
+				FlatInsn::Insn(Insn::Fork(_, _, c) | Insn::ForkLoop(_, _, c)) => walk(c, out),
+				_ => {}
+			}
+		}
+	}
+	let mut out = BTreeSet::new();
+	for f in functions {
+		walk(f, &mut out);
+	}
+	out
+}
+
+/// Indices of instructions that can never be executed.
+///
+/// Only the first instruction of each unreachable stretch is included. Stray `Return`s are
+/// ignored, since those are often generated after loops and `NewScene`.
+pub fn unreachable(code: &Code) -> Vec<usize> {
+	let labels = code.iter().enumerate()
+		.filter_map(|(i, a)| match a {
+			FlatInsn::Label(l) => Some((*l, i)),
+			_ => None,
+		})
+		.collect::<HashMap<Label, usize>>();
+
+	let mut seen = vec![false; code.len()];
+	let mut queue = vec![0];
+	while let Some(i) = queue.pop() {
+		if i >= code.len() || seen[i] {
+			continue
+		}
+		seen[i] = true;
+		let mut jump = |l: &Label| queue.extend(labels.get(l).copied());
+		match &code[i] {
+			FlatInsn::Unless(_, l) => {
+				jump(l);
+				queue.push(i + 1);
+			}
+			FlatInsn::Goto(l) => jump(l),
+			FlatInsn::Switch(_, cases, default) => {
+				for (_, l) in cases {
+					jump(l);
+				}
+				jump(default);
+			}
+			FlatInsn::Insn(Insn::Return() | Insn::Hcf()) => {}
+			FlatInsn::Insn(_) | FlatInsn::Label(_) => queue.push(i + 1),
+		}
+	}
+
+	let mut out = Vec::new();
+	let mut prev = true;
+	for (i, insn) in code.iter().enumerate() {
+		if seen[i] {
+			prev = true;
+		} else if prev && matches!(insn, FlatInsn::Insn(a) if !matches!(a, Insn::Return())) {
+			out.push(i);
+			prev = false;
+		}
+	}
+	out
+}
+
+#[test]
+fn issues() {
+	let includes = [FileId(0x10001), FileId(0x10002), FileId::NONE];
+	let mut c = Check {
+		none: 0xFF,
+		includes: &includes,
+		external: &|_| Some(2),
+		n_functions: 1,
+		n_chars: 0,
+		n_chips: 0,
+		loaded_chips: BTreeSet::new(),
+		out: Vec::new(),
+	};
+	c.code(&Code(vec![
+		FlatInsn::Switch(crate::scena::code::Expr(vec![]), vec![(1, Label(0)), (1, Label(0))], Label(0)),
+		FlatInsn::Label(Label(0)),
+		FlatInsn::Insn(Insn::Call(FuncId(0, 3))),
+		FlatInsn::Insn(Insn::TextTalk(CharId::Local(LocalCharId(2)), crate::text::Text(vec![]))),
+		FlatInsn::Insn(Insn::Return()),
+		FlatInsn::Insn(Insn::Call(FuncId(1, 2))),
+		FlatInsn::Insn(Insn::Call(FuncId(2, 0))),
+		FlatInsn::Insn(Insn::Return()),
+	]), &mut vec![], 0);
+	let issues = c.out.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+	assert_eq!(issues, [
+		"fn[0]:5: unreachable code",
+		"fn[0]:0: duplicate case 1",
+		"fn[0]:2: function fn[0,3] does not exist",
+		"fn[0]:3: char[2] does not exist, there are only 0 npcs and monsters",
+		"fn[0]:5: function fn[1,2] does not exist",
+		"fn[0]:6: include slot 2 is empty",
+	]);
+}
@@ -1,3 +1,6 @@
+use crate::types::Game;
+use crate::{ReadError, WriteError};
+
 pub mod quest;
 pub mod name;
 pub mod bgm;
@@ -6,5 +9,35 @@ pub mod town;
 pub mod world;
 pub mod mstqrt;
 pub mod quartz;
+pub mod item;
+pub mod magic;
+pub mod shop;
+pub mod recipe;
+pub mod monster;
+pub mod btlset;
 
 pub mod ent;
+
+pub mod registry;
+
+/// A whole table file, read and written through the same interface regardless of format.
+///
+/// Tables whose format differs between games take the game into account; the others ignore it.
+/// To find the right table for a file, see [`registry`].
+pub trait Table: Sized {
+	fn read(game: Game, data: &[u8]) -> Result<Self, ReadError>;
+	fn write(game: Game, table: &Self) -> Result<Vec<u8>, WriteError>;
+}
+
+/// Implements [`Table`] for `Vec<$t>`, for tables that are the same in all games.
+macro simple_table($t:ty) {
+	impl Table for Vec<$t> {
+		fn read(_: Game, data: &[u8]) -> Result<Self, ReadError> {
+			<$t>::read(data)
+		}
+
+		fn write(_: Game, table: &Self) -> Result<Vec<u8>, WriteError> {
+			<$t>::write(table)
+		}
+	}
+}
@@ -4,6 +4,7 @@ use crate::types::BgmId;
 use themelios_common::util::*;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ED7Bgm {
 	pub loop_start: u32,
 	pub loop_end: u32,
@@ -46,3 +47,210 @@ impl ED7Bgm {
 		Ok(f.finish()?)
 	}
 }
+
+/// An entry in ED6's `t_bgmtbl._dt`. Unlike ED7, there are no loop points; the game plays the
+/// whole file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ED6Bgm {
+	pub id: BgmId,
+	/// The file name without extension, such as `ed6001`.
+	pub file: String,
+	pub loops: bool,
+}
+
+impl ED6Bgm {
+	pub fn read(data: &[u8]) -> Result<Vec<ED6Bgm>, ReadError> {
+		let mut f = Reader::new(data);
+		let mut table = Vec::new();
+		while !f.is_empty() {
+			let id = BgmId(f.u16()?);
+			let loops = cast_bool(f.u16()?)?;
+			let file = f.sized_string::<8>()?;
+			table.push(ED6Bgm { id, file, loops });
+		}
+		Ok(table)
+	}
+
+	pub fn write(table: &[ED6Bgm]) -> Result<Vec<u8>, WriteError> {
+		let mut f = Writer::new();
+		for bgm in table {
+			f.u16(bgm.id.0);
+			f.u16(bgm.loops.into());
+			f.sized_string::<8>(&bgm.file)?;
+		}
+		Ok(f.finish()?)
+	}
+}
+
+/// The text form of the BGM table, as included in the Evolution versions of the ED6 games.
+///
+/// It has two kinds of lines: `#define BGM_Name 00010`, which gives a track number a name, and
+/// `bgmtbl BGM_Name ed6100 1 00021825 06145560`, which gives the file, whether it loops, and the
+/// loop points. Anything after a `;` or `//` is a comment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BgmTbl {
+	pub defines: Vec<(String, BgmId)>,
+	pub entries: Vec<BgmTblEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BgmTblEntry {
+	pub name: String,
+	pub file: String,
+	pub loops: bool,
+	pub loop_start: u32,
+	pub loop_end: u32,
+}
+
+impl BgmTbl {
+	pub fn parse(text: &str) -> Result<BgmTbl, ReadError> {
+		let mut defines = Vec::new();
+		let mut entries = Vec::new();
+		for (n, line) in text.lines().enumerate() {
+			let line = line.split(';').next().unwrap_or_default();
+			let line = line.split("//").next().unwrap_or_default();
+			let words = line.split_whitespace().collect::<Vec<_>>();
+			let num = |s: &str| s.parse::<u32>().map_err(|_| format!("line {}: invalid number: {s}", n + 1));
+			match words[..] {
+				[] => {}
+				["#define", name, id] => {
+					defines.push((name.to_owned(), BgmId(cast(num(id)?)?)));
+				}
+				["bgmtbl", name, file, loops, loop_start, loop_end] => {
+					entries.push(BgmTblEntry {
+						name: name.to_owned(),
+						file: file.to_owned(),
+						loops: cast_bool(num(loops)?)?,
+						loop_start: num(loop_start)?,
+						loop_end: num(loop_end)?,
+					});
+				}
+				_ => bail!("line {}: could not parse: {}", n + 1, line.trim()),
+			}
+		}
+		Ok(BgmTbl { defines, entries })
+	}
+
+	/// Writes the defines followed by the entries. Comments and the original order of lines are
+	/// not kept, so the text is not the same as what was parsed.
+	pub fn write(&self) -> String {
+		use std::fmt::Write;
+		let mut s = String::new();
+		for (name, id) in &self.defines {
+			writeln!(s, "#define\t\t{name}\t\t{:05}", id.0).unwrap();
+		}
+		writeln!(s).unwrap();
+		for e in &self.entries {
+			writeln!(s, "bgmtbl\t{}\t\t{}\t\t{}\t{:08}\t{:08}", e.name, e.file, e.loops as u8, e.loop_start, e.loop_end).unwrap();
+		}
+		s
+	}
+
+	fn id(&self, name: &str) -> Result<BgmId, WriteError> {
+		match self.defines.iter().find(|a| a.0 == name) {
+			Some((_, id)) => Ok(*id),
+			None => bail!("undefined track name: {name}"),
+		}
+	}
+
+	/// Defines each entry's name as its id.
+	fn from_ids(ids: impl IntoIterator<Item = (BgmId, BgmTblEntry)>) -> BgmTbl {
+		let mut defines = Vec::new();
+		let mut entries = Vec::new();
+		for (id, e) in ids {
+			defines.push((e.name.clone(), id));
+			entries.push(e);
+		}
+		BgmTbl { defines, entries }
+	}
+
+	/// The loop points are not included, since the ED6 table does not have them.
+	pub fn to_ed6(&self) -> Result<Vec<ED6Bgm>, WriteError> {
+		self.entries.iter().map(|e| Ok(ED6Bgm {
+			id: self.id(&e.name)?,
+			file: e.file.clone(),
+			loops: e.loops,
+		})).collect()
+	}
+
+	/// Names each track `BGM_` followed by its number, since the binary table has no names.
+	pub fn from_ed6(table: &[ED6Bgm]) -> BgmTbl {
+		Self::from_ids(table.iter().map(|b| (b.id, BgmTblEntry {
+			name: format!("BGM_{:05}", b.id.0),
+			file: b.file.clone(),
+			loops: b.loops,
+			loop_start: 0,
+			loop_end: 0,
+		})))
+	}
+
+	/// The file names must be `ed` followed by the file number, such as `ed7100`.
+	pub fn to_ed7(&self) -> Result<Vec<ED7Bgm>, WriteError> {
+		self.entries.iter().map(|e| {
+			let num = e.file.strip_prefix("ed").and_then(|a| a.parse().ok())
+				.ok_or_else(|| format!("invalid file name: {}", e.file))?;
+			Ok(ED7Bgm {
+				loop_start: e.loop_start,
+				loop_end: e.loop_end,
+				file_num: num,
+				id: self.id(&e.name)?,
+				loops: e.loops,
+			})
+		}).collect()
+	}
+
+	/// Names each track `BGM_` followed by its number, since the binary table has no names.
+	pub fn from_ed7(table: &[ED7Bgm]) -> BgmTbl {
+		Self::from_ids(table.iter().map(|b| (b.id, BgmTblEntry {
+			name: format!("BGM_{:05}", b.id.0),
+			file: format!("ed{}", b.file_num),
+			loops: b.loops,
+			loop_start: b.loop_start,
+			loop_end: b.loop_end,
+		})))
+	}
+}
+
+super::simple_table!(ED7Bgm);
+super::simple_table!(ED6Bgm);
+
+#[test]
+fn test_ed6() -> Result<(), Box<dyn std::error::Error>> {
+	for s in [
+		"../data/fc.extract/02/t_bgmtbl._dt",
+		"../data/sc.extract/22/t_bgmtbl._dt",
+		"../data/3rd.extract/22/t_bgmtbl._dt",
+	] {
+		let b = std::fs::read(s)?;
+		let b2 = ED6Bgm::write(&ED6Bgm::read(&b)?)?;
+		ensure!(b == b2, "{s}");
+	}
+	Ok(())
+}
+
+#[test]
+fn test_bgmtbl() -> Result<(), Box<dyn std::error::Error>> {
+	for s in [
+		"../book/src/bgmtbl/fc.txt",
+		"../book/src/bgmtbl/sc.txt",
+		"../book/src/bgmtbl/3rd.txt",
+	] {
+		let tbl = BgmTbl::parse(&std::fs::read_to_string(s)?)?;
+		ensure!(BgmTbl::parse(&tbl.write())? == tbl);
+
+		let ed6 = tbl.to_ed6()?;
+		ensure!(ed6.len() == tbl.entries.len());
+		ensure!(ED6Bgm::read(&ED6Bgm::write(&ed6)?)? == ed6);
+		ensure!(BgmTbl::from_ed6(&ed6).to_ed6()? == ed6);
+	}
+
+	let ed7 = vec![
+		ED7Bgm { loop_start: 21825, loop_end: 6145560, file_num: 7100, id: BgmId(10), loops: true },
+		ED7Bgm { loop_start: 0, loop_end: 0, file_num: 7999, id: BgmId(0), loops: false },
+	];
+	ensure!(BgmTbl::from_ed7(&ed7).to_ed7()? == ed7);
+	Ok(())
+}
@@ -0,0 +1,222 @@
+//! ED6's battle setups, `t_btlset._dt` in FC and `t_btset1._dt`/`t_btset2._dt` in SC and 3rd.
+//!
+//! Unlike ED7, where each scena has its own battles, ED6 keeps them all here, and
+//! `Insn::ED6Battle` refers to them by [`BattleId`], the index in [`BattleTable::battles`].
+//! Sepith, AT rolls and placements are shared between battles, like in ED7's scena files.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use gospel::read::{Reader, Le as _};
+use gospel::write::{Writer, Le as _, Label};
+use crate::types::*;
+use themelios_common::util::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BattleTable {
+	pub sepith: Vec<[u8; 8]>,
+	pub at_rolls: Vec<[u8; 16]>,
+	pub placements: Vec<[(u8, u8, Angle); 8]>,
+	pub battles: Vec<Battle>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Battle {
+	/// Whether the battle can be escaped from, and whether losing is a game over.
+	pub flags: u16,
+	/// The battlefield map.
+	pub battlefield: FileId,
+	pub sepith: Option<SepithId>,
+	pub setups: Vec<BattleSetup>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BattleSetup {
+	pub weight: u8,
+	/// The monsters' `ms*._dt` files. Unused slots are [`FileId::NONE`].
+	pub enemies: [FileId; 8],
+	pub placement: PlacementId,
+	pub placement_ambush: PlacementId,
+	pub bgm: BgmId,
+	pub bgm_ambush: BgmId,
+	pub at_roll: AtRollId,
+}
+
+/// A setup as it is in the file, with positions instead of ids.
+struct RawSetup {
+	weight: u8,
+	enemies: [FileId; 8],
+	placement: usize,
+	placement_ambush: usize,
+	bgm: BgmId,
+	bgm_ambush: BgmId,
+	at_roll: usize,
+}
+
+impl BattleTable {
+	pub fn read(data: &[u8]) -> Result<BattleTable, ReadError> {
+		let f = &mut Reader::new(data);
+		let end = f.clone().ptr16()?;
+
+		let mut raw = Vec::new();
+		while f.pos() < end.pos() {
+			let g = &mut f.ptr16()?;
+			let flags = g.u16()?;
+			let battlefield = FileId(g.u32()?);
+			let sepith = match g.u16()? {
+				0 => None,
+				n => Some(n as usize),
+			};
+			let mut setups = Vec::new();
+			for weight in g.array::<4>()? {
+				if weight == 0 {
+					continue
+				}
+				setups.push(RawSetup {
+					weight,
+					enemies: array(|| Ok(FileId(g.u32()?))).strict()?,
+					placement: g.u16()? as usize,
+					placement_ambush: g.u16()? as usize,
+					bgm: BgmId(g.u16()?),
+					bgm_ambush: BgmId(g.u16()?),
+					at_roll: g.u16()? as usize,
+				});
+			}
+			raw.push((flags, battlefield, sepith, setups));
+		}
+
+		// Number the shared structs in the order they are in the file, so that they are written
+		// back in the same order.
+		let sepith_pos = raw.iter().filter_map(|a| a.2).collect::<BTreeSet<_>>();
+		let at_roll_pos = raw.iter().flat_map(|a| &a.3).map(|s| s.at_roll).collect::<BTreeSet<_>>();
+		let placement_pos = raw.iter().flat_map(|a| &a.3)
+			.flat_map(|s| [s.placement, s.placement_ambush])
+			.collect::<BTreeSet<_>>();
+
+		let mut sepith = Vec::new();
+		for &pos in &sepith_pos {
+			sepith.push(f.clone().at(pos)?.array::<8>()?);
+		}
+		let mut at_rolls = Vec::new();
+		for &pos in &at_roll_pos {
+			at_rolls.push(f.clone().at(pos)?.array::<16>()?);
+		}
+		let mut placements = Vec::new();
+		for &pos in &placement_pos {
+			let g = &mut f.clone().at(pos)?;
+			placements.push(array::<8, _>(|| Ok((g.u8()?, g.u8()?, Angle(g.i16()?)))).strict()?);
+		}
+
+		let index = |set: &BTreeSet<usize>, pos: usize| set.range(..pos).count() as u16;
+		let battles = raw.into_iter().map(|(flags, battlefield, sepith, setups)| Battle {
+			flags,
+			battlefield,
+			sepith: sepith.map(|p| SepithId(index(&sepith_pos, p))),
+			setups: setups.into_iter().map(|s| BattleSetup {
+				weight: s.weight,
+				enemies: s.enemies,
+				placement: PlacementId(index(&placement_pos, s.placement)),
+				placement_ambush: PlacementId(index(&placement_pos, s.placement_ambush)),
+				bgm: s.bgm,
+				bgm_ambush: s.bgm_ambush,
+				at_roll: AtRollId(index(&at_roll_pos, s.at_roll)),
+			}).collect(),
+		}).collect();
+
+		Ok(BattleTable { sepith, at_rolls, placements, battles })
+	}
+
+	pub fn write(table: &BattleTable) -> Result<Vec<u8>, WriteError> {
+		let mut f = Writer::new();
+		let mut battles = Writer::new();
+		let mut at_rolls = Writer::new();
+		let mut placements = Writer::new();
+		let mut sepith = Writer::new();
+
+		let mut sepith_pos = BTreeMap::new();
+		for (i, sep) in table.sepith.iter().enumerate() {
+			sepith_pos.insert(i, sepith.here());
+			sepith.slice(sep);
+		}
+		let mut at_roll_pos = BTreeMap::new();
+		for (i, roll) in table.at_rolls.iter().enumerate() {
+			at_roll_pos.insert(i, at_rolls.here());
+			at_rolls.slice(roll);
+		}
+		let mut placement_pos = BTreeMap::new();
+		for (i, plac) in table.placements.iter().enumerate() {
+			placement_pos.insert(i, placements.here());
+			for p in plac {
+				placements.u8(p.0);
+				placements.u8(p.1);
+				placements.i16(p.2.0);
+			}
+		}
+		let get = |map: &BTreeMap<usize, Label>, id: u16, what: &str| -> Result<Label, WriteError> {
+			Ok(*map.get(&(id as usize)).ok_or_else(|| format!("{what} out of bounds"))?)
+		};
+
+		let g = &mut battles;
+		for battle in &table.battles {
+			f.delay16(g.here());
+			g.u16(battle.flags);
+			g.u32(battle.battlefield.0);
+			if let Some(s) = battle.sepith {
+				g.delay16(get(&sepith_pos, s.0, "sepith")?);
+			} else {
+				g.u16(0);
+			}
+			let mut weights = [0u8; 4];
+			let mut h = Writer::new();
+			ensure!(battle.setups.len() <= 4, "too many setups");
+			for (i, setup) in battle.setups.iter().enumerate() {
+				ensure!(setup.weight != 0, "setup weight must not be zero");
+				weights[i] = setup.weight;
+				for ms in &setup.enemies {
+					h.u32(ms.0);
+				}
+				h.delay16(get(&placement_pos, setup.placement.0, "placement")?);
+				h.delay16(get(&placement_pos, setup.placement_ambush.0, "placement")?);
+				h.u16(setup.bgm.0);
+				h.u16(setup.bgm_ambush.0);
+				h.delay16(get(&at_roll_pos, setup.at_roll.0, "at roll")?);
+			}
+			g.array(weights);
+			g.append(h);
+		}
+
+		f.append(battles);
+		f.append(at_rolls);
+		f.append(placements);
+		f.append(sepith);
+		Ok(f.finish()?)
+	}
+}
+
+impl super::Table for BattleTable {
+	fn read(_: Game, data: &[u8]) -> Result<Self, ReadError> {
+		BattleTable::read(data)
+	}
+
+	fn write(_: Game, table: &Self) -> Result<Vec<u8>, WriteError> {
+		BattleTable::write(table)
+	}
+}
+
+#[test]
+fn test() -> Result<(), Box<dyn std::error::Error>> {
+	for s in [
+		"../data/fc.extract/02/t_btlset._dt",
+		"../data/sc.extract/22/t_btset1._dt",
+		"../data/sc.extract/22/t_btset2._dt",
+		"../data/3rd.extract/22/t_btset1._dt",
+		"../data/3rd.extract/22/t_btset2._dt",
+	] {
+		let b = std::fs::read(s)?;
+		let b2 = BattleTable::write(&BattleTable::read(&b)?)?;
+		ensure!(b == b2, "{s}");
+	}
+	Ok(())
+}
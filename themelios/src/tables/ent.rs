@@ -5,6 +5,7 @@ use themelios_common::util::*;
 use crate::types::*;
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ED6Ent {
 	pub name: TString,
 	pub bbox: (Vec3, Vec3),
@@ -103,3 +104,5 @@ impl ED6Ent {
 		Ok(f.finish()?)
 	}
 }
+
+super::simple_table!(ED6Ent);
@@ -0,0 +1,197 @@
+use gospel::read::{Reader, Le as _};
+use gospel::write::{Writer, Le as _};
+use crate::types::*;
+use themelios_common::util::*;
+
+/// An entry in ED6's `t_item._dt`. The names and descriptions are in a separate file, see
+/// [`ItemText`].
+///
+/// ED7's `t_item._dt` is laid out differently, and is not supported yet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Item {
+	pub id: ItemId,
+	/// Where the item can be used, and whether it can be sold or discarded.
+	pub usage: ItemUsage,
+	/// Weapon, armor, boots, accessory, consumable, food, key item, and so on.
+	pub category: ItemCategory,
+	/// The area the item affects when used, and whom it can be used on.
+	pub target: [u8; 4],
+	/// Effect kinds and their values, for items that have effects when used.
+	pub effect: [i16; 4],
+	pub stats: Stats,
+	/// The maximum number that can be carried.
+	pub limit: u16,
+	pub price: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stats {
+	pub str: i16,
+	pub def: i16,
+	pub ats: i16,
+	pub adf: i16,
+	pub dex: i16,
+	pub agl: i16,
+	pub mov: i16,
+	pub spd: i16,
+}
+
+/// An entry in `t_item2._dt` in ED6, and in `t_ittxt._dt` in ED7.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ItemText {
+	pub id: ItemId,
+	pub name: TString,
+	pub desc: TString,
+}
+
+impl Item {
+	pub fn read_ed6(data: &[u8]) -> Result<Vec<Item>, ReadError> {
+		let f = &mut Reader::new(data);
+		let end = f.clone().ptr16()?;
+		let mut table = Vec::new();
+		while f.pos() < end.pos() {
+			table.push(Self::read_one(&mut f.ptr16()?)?);
+		}
+		Ok(table)
+	}
+
+	fn read_one(f: &mut Reader) -> Result<Item, ReadError> {
+		let id = ItemId(f.u16()?);
+		let usage = ItemUsage(f.u8()?);
+		let category = ItemCategory(f.u8()?);
+		let target = f.array()?;
+		let effect = std::array::try_from_fn(|_| f.i16())?;
+		let stats = Stats {
+			str: f.i16()?,
+			def: f.i16()?,
+			ats: f.i16()?,
+			adf: f.i16()?,
+			dex: f.i16()?,
+			agl: f.i16()?,
+			mov: f.i16()?,
+			spd: f.i16()?,
+		};
+		let limit = f.u16()?;
+		let price = f.u32()?;
+		Ok(Item { id, usage, category, target, effect, stats, limit, price })
+	}
+
+	pub fn write_ed6(table: &[Item]) -> Result<Vec<u8>, WriteError> {
+		let mut f = Writer::new();
+		let mut g = Writer::new();
+		for item in table {
+			f.delay16(g.here());
+			Self::write_one(&mut g, item);
+		}
+		f.append(g);
+		Ok(f.finish()?)
+	}
+
+	fn write_one(f: &mut Writer, item: &Item) {
+		f.u16(item.id.0);
+		f.u8(item.usage.0);
+		f.u8(item.category.0);
+		f.array(item.target);
+		for i in item.effect { f.i16(i) }
+		let s = &item.stats;
+		for i in [s.str, s.def, s.ats, s.adf, s.dex, s.agl, s.mov, s.spd] { f.i16(i) }
+		f.u16(item.limit);
+		f.u32(item.price);
+	}
+}
+
+impl ItemText {
+	pub fn read(data: &[u8]) -> Result<Vec<ItemText>, ReadError> {
+		let f = &mut Reader::new(data);
+		let end = f.clone().ptr16()?;
+		let mut table = Vec::new();
+		while f.pos() < end.pos() {
+			let g = &mut f.ptr16()?;
+			let id = ItemId(g.u16()?);
+			g.check_u16(0)?;
+			let name = TString(g.ptr16()?.string()?);
+			let desc = TString(g.ptr16()?.string()?);
+			table.push(ItemText { id, name, desc });
+		}
+		Ok(table)
+	}
+
+	pub fn write(table: &[ItemText]) -> Result<Vec<u8>, WriteError> {
+		let mut f = Writer::new();
+		let mut g = Writer::new();
+		for item in table {
+			f.delay16(g.here());
+			g.u16(item.id.0);
+			g.u16(0);
+			let mut h = Writer::new();
+			g.delay16(h.here());
+			h.string(&item.name.0)?;
+			g.delay16(h.here());
+			h.string(&item.desc.0)?;
+			g.append(h);
+		}
+		f.append(g);
+		Ok(f.finish()?)
+	}
+}
+
+impl super::Table for Vec<Item> {
+	fn read(_: Game, data: &[u8]) -> Result<Self, ReadError> {
+		Item::read_ed6(data)
+	}
+
+	fn write(_: Game, table: &Self) -> Result<Vec<u8>, WriteError> {
+		Item::write_ed6(table)
+	}
+}
+
+super::simple_table!(ItemText);
+
+/// An item with the given id and nothing else, for tests that only care about ids.
+#[cfg(test)]
+pub(crate) fn test_item(id: u16) -> Item {
+	Item {
+		id: ItemId(id),
+		usage: ItemUsage(0),
+		category: ItemCategory(0),
+		target: [0; 4],
+		effect: [0; 4],
+		stats: Stats { str: 0, def: 0, ats: 0, adf: 0, dex: 0, agl: 0, mov: 0, spd: 0 },
+		limit: 0,
+		price: 0,
+	}
+}
+
+#[test]
+fn test_ed6() -> Result<(), Box<dyn std::error::Error>> {
+	for dir in [
+		"../data/fc.extract/02",
+		"../data/sc.extract/22",
+		"../data/3rd.extract/22",
+	] {
+		let b = std::fs::read(format!("{dir}/t_item._dt"))?;
+		let b2 = Item::write_ed6(&Item::read_ed6(&b)?)?;
+		ensure!(b == b2);
+
+		let b = std::fs::read(format!("{dir}/t_item2._dt"))?;
+		let b2 = ItemText::write(&ItemText::read(&b)?)?;
+		ensure!(b == b2);
+	}
+	Ok(())
+}
+
+#[test]
+fn test_ed7() -> Result<(), Box<dyn std::error::Error>> {
+	for dir in [
+		"../data/zero/data/text",
+		"../data/ao/data/text",
+	] {
+		let b = std::fs::read(format!("{dir}/t_ittxt._dt"))?;
+		let b2 = ItemText::write(&ItemText::read(&b)?)?;
+		ensure!(b == b2);
+	}
+	Ok(())
+}
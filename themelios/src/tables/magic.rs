@@ -0,0 +1,144 @@
+use gospel::read::{Reader, Le as _};
+use gospel::write::{Writer, Le as _};
+use crate::types::*;
+use themelios_common::util::*;
+
+/// An entry in `t_magic._dt`, which holds arts, crafts, and S-crafts alike.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Magic {
+	pub id: MagicId,
+	/// Whether this is an art, craft or S-craft, and whether it can be used outside battle.
+	pub flags: u16,
+	pub element: u8,
+	/// Whom the magic can be aimed at: an enemy, an ally, a point on the ground, and so on.
+	pub target: u8,
+	pub effects: [Effect; 2],
+	pub area: Area,
+	/// How long it takes to cast, for arts. Crafts are instant.
+	pub cast_time: u8,
+	/// How long until the user's next turn afterwards.
+	pub delay: u8,
+	/// EP for arts, CP for crafts.
+	pub cost: u16,
+	pub name: TString,
+	pub desc: TString,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Effect {
+	pub kind: u8,
+	pub values: [i16; 2],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Area {
+	/// Single target, circle, line, and so on.
+	pub shape: u8,
+	pub size: u8,
+}
+
+impl Magic {
+	pub fn read(game: Game, data: &[u8]) -> Result<Vec<Magic>, ReadError> {
+		let f = &mut Reader::new(data);
+		let end = f.clone().ptr16()?;
+		let mut table = Vec::new();
+		while f.pos() < end.pos() {
+			let g = &mut f.ptr16()?;
+			let id = MagicId(g.u16()?);
+			let flags = g.u16()?;
+			let element = g.u8()?;
+			let target = g.u8()?;
+			let kinds = g.array::<2>()?;
+			let area = Area { shape: g.u8()?, size: g.u8()? };
+			let cast_time = g.u8()?;
+			let delay = g.u8()?;
+			let cost = g.u16()?;
+			let effects = kinds.try_map(|kind| Ok(Effect { kind, values: [g.i16()?, g.i16()?] })).strict()?;
+			let (name, desc) = if game.is_ed7() {
+				(g.ptr32()?.string()?, g.ptr32()?.string()?)
+			} else {
+				(g.ptr16()?.string()?, g.ptr16()?.string()?)
+			};
+			table.push(Magic {
+				id,
+				flags,
+				element,
+				target,
+				effects,
+				area,
+				cast_time,
+				delay,
+				cost,
+				name: TString(name),
+				desc: TString(desc),
+			});
+		}
+		Ok(table)
+	}
+
+	pub fn write(game: Game, table: &[Magic]) -> Result<Vec<u8>, WriteError> {
+		let mut f = Writer::new();
+		let mut g = Writer::new();
+		for m in table {
+			f.delay16(g.here());
+			g.u16(m.id.0);
+			g.u16(m.flags);
+			g.u8(m.element);
+			g.u8(m.target);
+			g.u8(m.effects[0].kind);
+			g.u8(m.effects[1].kind);
+			g.u8(m.area.shape);
+			g.u8(m.area.size);
+			g.u8(m.cast_time);
+			g.u8(m.delay);
+			g.u16(m.cost);
+			for e in &m.effects {
+				for v in e.values { g.i16(v) }
+			}
+			let mut h = Writer::new();
+			if game.is_ed7() {
+				g.delay32(h.here());
+				h.string(&m.name.0)?;
+				g.delay32(h.here());
+				h.string(&m.desc.0)?;
+			} else {
+				g.delay16(h.here());
+				h.string(&m.name.0)?;
+				g.delay16(h.here());
+				h.string(&m.desc.0)?;
+			}
+			g.append(h);
+		}
+		f.append(g);
+		Ok(f.finish()?)
+	}
+}
+
+impl super::Table for Vec<Magic> {
+	fn read(game: Game, data: &[u8]) -> Result<Self, ReadError> {
+		Magic::read(game, data)
+	}
+
+	fn write(game: Game, table: &Self) -> Result<Vec<u8>, WriteError> {
+		Magic::write(game, table)
+	}
+}
+
+#[test]
+fn test() -> Result<(), Box<dyn std::error::Error>> {
+	for (game, s) in [
+		(Game::Fc, "../data/fc.extract/02/t_magic._dt"),
+		(Game::Sc, "../data/sc.extract/22/t_magic._dt"),
+		(Game::Tc, "../data/3rd.extract/22/t_magic._dt"),
+		(Game::Zero, "../data/zero/data/text/t_magic._dt"),
+		(Game::Ao, "../data/ao/data/text/t_magic._dt"),
+	] {
+		let b = std::fs::read(s)?;
+		let b2 = Magic::write(game, &Magic::read(game, &b)?)?;
+		ensure!(b == b2, "{game:?}");
+	}
+	Ok(())
+}
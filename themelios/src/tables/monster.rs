@@ -0,0 +1,243 @@
+//! ED6's monster status files, `ms*._dt`, one per monster. These are what [`ED6Name::ms1`] and
+//! [`ED6Name::ms2`] refer to, as well as the monsters in a battle's placements.
+//!
+//! [`ED6Name::ms1`]: super::name::ED6Name::ms1
+//! [`ED6Name::ms2`]: super::name::ED6Name::ms2
+
+use std::path::{Path, PathBuf};
+
+use gospel::read::{Reader, Le as _};
+use gospel::write::{Writer, Le as _};
+use crate::lookup::Lookup;
+use crate::types::*;
+use themelios_common::util::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Monster {
+	/// The animation script, `as*._dt`. Its chip list, read by [`Monster::chips`], is what the
+	/// monster looks like.
+	pub ani: FileId,
+	pub level: u16,
+	/// Maximum and initial HP.
+	pub hp: (u32, u32),
+	/// Maximum and initial EP.
+	pub ep: (u16, u16),
+	/// Maximum and initial CP.
+	pub cp: (u16, u16),
+	pub stats: Stats,
+	pub exp: u16,
+	pub flags: u32,
+	/// Status ailments the monster is immune to.
+	pub resist: u32,
+	/// Damage taken from each element, in percent: earth, water, fire, wind, time, space, mirage.
+	pub elements: [u16; 7],
+	/// Sepith dropped, in the same order as `elements`.
+	pub sepith: [u8; 7],
+	/// Items that may be dropped, and the chance in percent.
+	pub drops: [(ItemId, u8); 2],
+	pub arts: Vec<Action>,
+	pub crafts: Vec<Action>,
+	pub scrafts: Vec<Action>,
+	pub name: TString,
+	pub desc: TString,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stats {
+	pub str: u16,
+	pub def: u16,
+	pub ats: u16,
+	pub adf: u16,
+	pub dex: u16,
+	pub agl: u16,
+	pub mov: u16,
+	pub spd: u16,
+	pub rng: u16,
+}
+
+/// An art or craft the monster may use, and when its AI chooses to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Action {
+	pub magic: MagicId,
+	/// What needs to hold for the action to be considered, such as the monster's HP being low.
+	pub condition: u8,
+	/// Chance in percent of choosing it when the condition holds.
+	pub probability: u8,
+	/// Which kind of character it is aimed at.
+	pub target: u8,
+	/// What needs to hold for the target.
+	pub target_condition: u8,
+	/// Parameters for the conditions, such as the HP threshold.
+	pub params: [u32; 2],
+}
+
+impl Monster {
+	pub fn read(data: &[u8]) -> Result<Monster, ReadError> {
+		let f = &mut Reader::new(data);
+		let ani = FileId(f.u32()?);
+		let level = f.u16()?;
+		let hp = (f.u32()?, f.u32()?);
+		let ep = (f.u16()?, f.u16()?);
+		let cp = (f.u16()?, f.u16()?);
+		let stats = Stats {
+			str: f.u16()?,
+			def: f.u16()?,
+			ats: f.u16()?,
+			adf: f.u16()?,
+			dex: f.u16()?,
+			agl: f.u16()?,
+			mov: f.u16()?,
+			spd: f.u16()?,
+			rng: f.u16()?,
+		};
+		let exp = f.u16()?;
+		let flags = f.u32()?;
+		let resist = f.u32()?;
+		let elements = std::array::try_from_fn(|_| f.u16())?;
+		let sepith = f.array()?;
+		let drops = [(ItemId(f.u16()?), f.u8()?), (ItemId(f.u16()?), f.u8()?)];
+		let arts = Self::read_actions(f)?;
+		let crafts = Self::read_actions(f)?;
+		let scrafts = Self::read_actions(f)?;
+		let name = TString(f.string()?);
+		let desc = TString(f.string()?);
+		ensure!(f.is_empty(), "trailing data");
+		Ok(Monster {
+			ani, level, hp, ep, cp, stats, exp, flags, resist, elements, sepith, drops,
+			arts, crafts, scrafts, name, desc,
+		})
+	}
+
+	fn read_actions(f: &mut Reader) -> Result<Vec<Action>, ReadError> {
+		let mut actions = Vec::new();
+		for _ in 0..f.u8()? {
+			actions.push(Action {
+				magic: MagicId(f.u16()?),
+				condition: f.u8()?,
+				probability: f.u8()?,
+				target: f.u8()?,
+				target_condition: f.u8()?,
+				params: [f.u32()?, f.u32()?],
+			});
+		}
+		Ok(actions)
+	}
+
+	pub fn write(m: &Monster) -> Result<Vec<u8>, WriteError> {
+		let mut f = Writer::new();
+		f.u32(m.ani.0);
+		f.u16(m.level);
+		f.u32(m.hp.0);
+		f.u32(m.hp.1);
+		f.u16(m.ep.0);
+		f.u16(m.ep.1);
+		f.u16(m.cp.0);
+		f.u16(m.cp.1);
+		let s = &m.stats;
+		for i in [s.str, s.def, s.ats, s.adf, s.dex, s.agl, s.mov, s.spd, s.rng] { f.u16(i) }
+		f.u16(m.exp);
+		f.u32(m.flags);
+		f.u32(m.resist);
+		for i in m.elements { f.u16(i) }
+		f.array(m.sepith);
+		for (item, chance) in m.drops {
+			f.u16(item.0);
+			f.u8(chance);
+		}
+		Self::write_actions(&mut f, &m.arts)?;
+		Self::write_actions(&mut f, &m.crafts)?;
+		Self::write_actions(&mut f, &m.scrafts)?;
+		f.string(&m.name.0)?;
+		f.string(&m.desc.0)?;
+		Ok(f.finish()?)
+	}
+
+	fn write_actions(f: &mut Writer, actions: &[Action]) -> Result<(), WriteError> {
+		f.u8(cast(actions.len())?);
+		for a in actions {
+			f.u16(a.magic.0);
+			f.u8(a.condition);
+			f.u8(a.probability);
+			f.u8(a.target);
+			f.u8(a.target_condition);
+			f.u32(a.params[0]);
+			f.u32(a.params[1]);
+		}
+		Ok(())
+	}
+}
+
+impl Monster {
+	/// Where the monster's animation script is.
+	///
+	/// `dir` is where the archives are extracted, with each archive in a directory named by its
+	/// number in hex, such as `10` for FC's monsters.
+	pub fn ani_path(&self, lookup: &dyn Lookup, dir: &Path) -> Result<PathBuf, ReadError> {
+		let name = lookup.name(self.ani.0).ok_or_else(|| format!("unknown file {:?}", self.ani))?;
+		Ok(dir.join(format!("{:02x}", self.ani.0 >> 16)).join(name))
+	}
+
+	/// Reads the `(ch, cp)` chips the monster is drawn with from its animation script, which is
+	/// found as in [`Monster::ani_path`].
+	pub fn chips(&self, lookup: &dyn Lookup, dir: &Path) -> Result<Vec<(FileId, FileId)>, ReadError> {
+		let path = self.ani_path(lookup, dir)?;
+		let data = std::fs::read(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+		themelios_ani::ed6::read_chips(&data)
+	}
+}
+
+impl super::Table for Monster {
+	fn read(_: Game, data: &[u8]) -> Result<Self, ReadError> {
+		Monster::read(data)
+	}
+
+	fn write(_: Game, table: &Self) -> Result<Vec<u8>, WriteError> {
+		Monster::write(table)
+	}
+}
+
+#[test]
+fn test() -> Result<(), Box<dyn std::error::Error>> {
+	for dir in [
+		"../data/fc.extract/10/",
+		"../data/sc.extract/30/",
+		"../data/3rd.extract/30/",
+	] {
+		let mut files = std::fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+		files.sort_by_key(|a| a.path());
+		for file in files {
+			let p = file.path();
+			let n = p.file_name().unwrap().to_str().unwrap();
+			if n.starts_with("ms") {
+				let b = std::fs::read(&p)?;
+				let b2 = Monster::write(&Monster::read(&b)?)?;
+				ensure!(b == b2, "{n}");
+			}
+		}
+	}
+	Ok(())
+}
+
+#[test]
+fn test_ani_path() -> Result<(), Box<dyn std::error::Error>> {
+	struct Ani;
+	impl Lookup for Ani {
+		fn name(&self, index: u32) -> Option<String> {
+			(index == 0x00100001).then(|| "as00001._dt".to_owned())
+		}
+		fn index(&self, _: &str) -> Option<u32> {
+			None
+		}
+	}
+
+	let mut data = vec![0; 82];
+	data[..4].copy_from_slice(&0x00100001u32.to_le_bytes());
+	let mut monster = Monster::read(&data)?;
+	assert_eq!(monster.ani_path(&Ani, Path::new("fc"))?, Path::new("fc/10/as00001._dt"));
+	monster.ani = FileId(0x00100002);
+	assert!(monster.ani_path(&Ani, Path::new("fc")).is_err());
+	Ok(())
+}
@@ -6,6 +6,7 @@ use crate::types::*;
 use themelios_common::util::*;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MstQrt {
 	pub stats: Stats,
 	pub eff: [u8; 4], // union of [u8; 4] and [u16; 2]
@@ -14,6 +15,7 @@ pub struct MstQrt {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Stats {
 	pub hp: u16,
 	pub ep: u16, // must be multiple of 10
@@ -149,6 +151,16 @@ pub fn show(strs: &[&str], eff: u32) -> String {
 	s2
 }
 
+impl super::Table for Vec<[MstQrt; 5]> {
+	fn read(_: Game, data: &[u8]) -> Result<Self, ReadError> {
+		MstQrt::read(data)
+	}
+
+	fn write(_: Game, table: &Self) -> Result<Vec<u8>, WriteError> {
+		MstQrt::write(table)
+	}
+}
+
 #[test]
 fn test_ao() -> Result<(), Box<dyn std::error::Error>> {
 	for s in [
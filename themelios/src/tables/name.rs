@@ -4,6 +4,7 @@ use crate::types::*;
 use themelios_common::util::*;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ED6Name {
 	pub id: NameId,
 	pub name: TString,
@@ -120,6 +121,7 @@ impl ED6Name {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ED7Name {
 	pub id: NameId,
 	pub name: TString,
@@ -171,3 +173,15 @@ impl ED7Name {
 		Ok(f.finish()?)
 	}
 }
+
+super::simple_table!(ED7Name);
+
+impl super::Table for (Vec<ED6Name>, Vec<ED6Name>) {
+	fn read(game: Game, data: &[u8]) -> Result<Self, ReadError> {
+		ED6Name::read(game, data)
+	}
+
+	fn write(game: Game, table: &Self) -> Result<Vec<u8>, WriteError> {
+		ED6Name::write(game, &table.0, &table.1)
+	}
+}
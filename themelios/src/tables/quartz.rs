@@ -1,11 +1,12 @@
 use std::collections::BTreeMap;
 
 use gospel::read::{Reader, Le as _};
-use gospel::write::{Writer, Le as _};
+use gospel::write::{Writer, Le as _, Label};
 use crate::types::*;
 use themelios_common::util::*;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Quartz {
 	pub id: u16,
 	pub element: u16,
@@ -14,6 +15,7 @@ pub struct Quartz {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MQuartz {
 	pub id: u16,
 	pub element: u16,
@@ -93,15 +95,70 @@ impl Quartz {
 
 	pub fn write_zero(table: &[Quartz]) -> Result<Vec<u8>, WriteError> {
 		let mut f = Writer::new();
+		Self::write_ed7(&mut f, table)?;
 		Ok(f.finish()?)
 	}
 
-	pub fn write_ao(quart: &[Quartz], mq: &[MQuartz]) -> Result<Vec<u8>, WriteError> {
+	fn write_ed7(f: &mut Writer, table: &[Quartz]) -> Result<(), WriteError> {
+		for q in table {
+			f.u16(q.id);
+			f.u16(q.element.checked_add(1).ok_or("invalid element")?);
+			f.u16(0);
+			for i in q.cost { f.u16(i) }
+			f.u8(0);
+			for i in q.value { f.u8(cast(i)?) }
+		}
+		Ok(())
+	}
+
+	pub fn write_ao(quartz: &[Quartz], mq: &[MQuartz]) -> Result<Vec<u8>, WriteError> {
 		let mut f = Writer::new();
+		let l = Label::new();
+		f.delay16(l);
+		Self::write_ed7(&mut f, quartz)?;
+		f.label(l);
+		for q in mq {
+			f.u16(q.id);
+			f.u16(q.element.checked_add(1).ok_or("invalid element")?);
+			for v in q.value {
+				f.u8(0);
+				f.array(v);
+			}
+		}
 		Ok(f.finish()?)
 	}
 }
 
+impl super::Table for Vec<Quartz> {
+	fn read(game: Game, data: &[u8]) -> Result<Self, ReadError> {
+		match game.base() {
+			BaseGame::Zero => Quartz::read_zero(data),
+			BaseGame::Ao => Err("Ao's quartz table also has master quartz".into()),
+			_ => Quartz::read_ed6(data),
+		}
+	}
+
+	fn write(game: Game, table: &Self) -> Result<Vec<u8>, WriteError> {
+		match game.base() {
+			BaseGame::Zero => Quartz::write_zero(table),
+			BaseGame::Ao => Err("Ao's quartz table also has master quartz".into()),
+			_ => Quartz::write_ed6(table),
+		}
+	}
+}
+
+impl super::Table for (Vec<Quartz>, Vec<MQuartz>) {
+	fn read(game: Game, data: &[u8]) -> Result<Self, ReadError> {
+		ensure!(game.base() == BaseGame::Ao, "only Ao has master quartz");
+		Quartz::read_ao(data)
+	}
+
+	fn write(game: Game, (quartz, mq): &Self) -> Result<Vec<u8>, WriteError> {
+		ensure!(game.base() == BaseGame::Ao, "only Ao has master quartz");
+		Quartz::write_ao(quartz, mq)
+	}
+}
+
 #[test]
 fn test_ed6() -> Result<(), Box<dyn std::error::Error>> {
 	for s in [
@@ -119,6 +176,23 @@ fn test_ed6() -> Result<(), Box<dyn std::error::Error>> {
 	Ok(())
 }
 
+#[test]
+fn test_ed7() -> Result<(), Box<dyn std::error::Error>> {
+	let quartz = vec![
+		Quartz { id: 1, element: 0, cost: [10, 20, 30, 0, 0, 0, 0], value: [1, 2, 3, 4, 5, 6, 7] },
+		Quartz { id: 2, element: 6, cost: [0; 7], value: [255, 0, 0, 0, 0, 0, 0] },
+	];
+	assert_eq!(Quartz::read_zero(&Quartz::write_zero(&quartz)?)?, quartz);
+
+	let mq = vec![MQuartz { id: 100, element: 3, value: [[1; 7], [2; 7], [3; 7], [4; 7], [5; 7]] }];
+	assert_eq!(Quartz::read_ao(&Quartz::write_ao(&quartz, &mq)?)?, (quartz.clone(), mq));
+
+	let mut too_big = quartz;
+	too_big[0].value[0] = 256;
+	assert!(Quartz::write_zero(&too_big).is_err());
+	Ok(())
+}
+
 // #[test]
 // fn test_zero() -> Result<(), Box<dyn std::error::Error>> {
 // 	let s = "../data/zero/data/text/t_quartz._dt";
@@ -6,6 +6,7 @@ use themelios_scena::text::Text;
 use themelios_common::util::*;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ED6Quest {
 	pub id: QuestId,
 	pub section: u16,
@@ -86,6 +87,7 @@ impl ED6Quest {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ED7Quest {
 	pub id: QuestId,
 	pub section: u8,
@@ -177,3 +179,6 @@ impl ED7Quest {
 		Ok(f.finish()?)
 	}
 }
+
+super::simple_table!(ED6Quest);
+super::simple_table!(ED7Quest);
@@ -0,0 +1,108 @@
+use gospel::read::{Reader, Le as _};
+use gospel::write::{Writer, Le as _};
+use crate::types::*;
+use themelios_common::util::*;
+use super::item::Item;
+
+/// An entry in `t_cook._dt`. A recipe has no name of its own; it is named after the dish, whose
+/// effects are in the item table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Recipe {
+	pub id: RecipeId,
+	/// The dish that is normally made, and the one that is sometimes made instead. Like for empty
+	/// ingredient slots, `ItemId(0)` means there is no second dish.
+	pub results: [ItemId; 2],
+	/// Items and how many of each are needed, at most eight.
+	pub ingredients: Vec<(ItemId, u16)>,
+}
+
+impl Recipe {
+	pub fn read(data: &[u8]) -> Result<Vec<Recipe>, ReadError> {
+		let f = &mut Reader::new(data);
+		let end = f.clone().ptr16()?;
+		let mut table = Vec::new();
+		while f.pos() < end.pos() {
+			let g = &mut f.ptr16()?;
+			let id = RecipeId(g.u16()?);
+			let results = [ItemId(g.u16()?), ItemId(g.u16()?)];
+			let mut ingredients = Vec::new();
+			let mut empty = false;
+			for _ in 0..8 {
+				let item = ItemId(g.u16()?);
+				let count = g.u16()?;
+				if count != 0 {
+					ensure!(!empty, "ingredient after empty slot");
+					ingredients.push((item, count));
+				} else {
+					ensure!(item == ItemId(0), "empty ingredient slot has an item");
+					empty = true;
+				}
+			}
+			table.push(Recipe { id, results, ingredients });
+		}
+		Ok(table)
+	}
+
+	pub fn write(table: &[Recipe]) -> Result<Vec<u8>, WriteError> {
+		let mut f = Writer::new();
+		let mut g = Writer::new();
+		for recipe in table {
+			ensure!(recipe.ingredients.len() <= 8, "too many ingredients");
+			f.delay16(g.here());
+			g.u16(recipe.id.0);
+			g.u16(recipe.results[0].0);
+			g.u16(recipe.results[1].0);
+			for i in 0..8 {
+				let (item, count) = recipe.ingredients.get(i).copied().unwrap_or((ItemId(0), 0));
+				ensure!(i >= recipe.ingredients.len() || count != 0, "ingredient count must not be zero");
+				g.u16(item.0);
+				g.u16(count);
+			}
+		}
+		f.append(g);
+		Ok(f.finish()?)
+	}
+
+	/// The dishes and ingredients of this recipe that are not in the item table.
+	pub fn missing_items(&self, items: &[Item]) -> Vec<ItemId> {
+		let [dish, alt] = self.results;
+		std::iter::once(dish)
+			.chain((alt != ItemId(0)).then_some(alt))
+			.chain(self.ingredients.iter().map(|a| a.0))
+			.filter(|id| !items.iter().any(|i| i.id == *id))
+			.collect()
+	}
+}
+
+super::simple_table!(Recipe);
+
+#[test]
+fn test() -> Result<(), Box<dyn std::error::Error>> {
+	for s in [
+		"../data/fc.extract/02/t_cook._dt",
+		"../data/sc.extract/22/t_cook._dt",
+		"../data/3rd.extract/22/t_cook._dt",
+		"../data/zero/data/text/t_cook._dt",
+		"../data/ao/data/text/t_cook._dt",
+	] {
+		let b = std::fs::read(s)?;
+		let b2 = Recipe::write(&Recipe::read(&b)?)?;
+		ensure!(b == b2, "{s}");
+	}
+	Ok(())
+}
+
+#[test]
+fn test_missing_items() {
+	let items = [10, 11, 20].map(super::item::test_item);
+	let mut recipe = Recipe {
+		id: RecipeId(0),
+		results: [ItemId(20), ItemId(0)],
+		ingredients: vec![(ItemId(10), 2), (ItemId(11), 1)],
+	};
+	assert_eq!(recipe.missing_items(&items), []);
+	recipe.results[1] = ItemId(21);
+	recipe.ingredients.push((ItemId(12), 1));
+	assert_eq!(recipe.missing_items(&items), [ItemId(21), ItemId(12)]);
+}
@@ -0,0 +1,170 @@
+//! Finds the right [`Table`] for a file, so that tools can handle every table without knowing
+//! about each of them.
+//!
+//! A file's kind is decided by its name and the game, since several tables share a name but
+//! differ between games. The table is read into an [`AnyTable`], which, with the `serde` feature,
+//! can be serialized and deserialized like any other value.
+
+use crate::lookup::Lookup;
+use crate::types::*;
+use crate::{ReadError, WriteError};
+use super::*;
+
+macro_rules! tables {
+	($($(#[$meta:meta])* $kind:ident($ty:ty)),* $(,)?) => {
+		/// The kinds of tables that can be read and written through [`AnyTable`].
+		#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+		#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+		pub enum Kind {
+			$($(#[$meta])* $kind,)*
+		}
+
+		/// Any table, as read by [`AnyTable::read`].
+		#[derive(Debug, Clone, PartialEq)]
+		#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+		pub enum AnyTable {
+			$($(#[$meta])* $kind($ty),)*
+		}
+
+		impl AnyTable {
+			pub fn read(kind: Kind, game: Game, data: &[u8]) -> Result<AnyTable, ReadError> {
+				Ok(match kind {
+					$(Kind::$kind => AnyTable::$kind(Table::read(game, data)?),)*
+				})
+			}
+
+			pub fn write(&self, game: Game) -> Result<Vec<u8>, WriteError> {
+				match self {
+					$(AnyTable::$kind(table) => Table::write(game, table),)*
+				}
+			}
+
+			pub fn kind(&self) -> Kind {
+				match self {
+					$(AnyTable::$kind(_) => Kind::$kind,)*
+				}
+			}
+		}
+	}
+}
+
+tables! {
+	/// `t_name._dt` in ED6. The two halves are the entries before and after 999.
+	ED6Name((Vec<name::ED6Name>, Vec<name::ED6Name>)),
+	ED7Name(Vec<name::ED7Name>),
+	ED6Quest(Vec<quest::ED6Quest>),
+	ED7Quest(Vec<quest::ED7Quest>),
+	Town(Vec<town::Town>),
+	ED6World(Vec<world::ED6World>),
+	ED6Bgm(Vec<bgm::ED6Bgm>),
+	ED7Bgm(Vec<bgm::ED7Bgm>),
+	ED6Sound(Vec<se::ED6Sound>),
+	ED7Sound(Vec<se::ED7Sound>),
+	Item(Vec<item::Item>),
+	ItemText(Vec<item::ItemText>),
+	Magic(Vec<magic::Magic>),
+	Shop(Vec<shop::Shop>),
+	Recipe(Vec<recipe::Recipe>),
+	Quartz(Vec<quartz::Quartz>),
+	/// `t_quartz._dt` in Ao, which also holds master quartz.
+	AoQuartz((Vec<quartz::Quartz>, Vec<quartz::MQuartz>)),
+	MstQrt(Vec<[mstqrt::MstQrt; 5]>),
+	Monster(monster::Monster),
+	BattleTable(btlset::BattleTable),
+	ED6Ent(Vec<ent::ED6Ent>),
+}
+
+impl Kind {
+	/// Finds the kind of table in a file with the given name, such as `t_item._dt`.
+	///
+	/// Any directories in the name are ignored, and case does not matter.
+	pub fn from_name(game: Game, name: &str) -> Option<Kind> {
+		let name = name.rsplit(['/', '\\']).next().unwrap_or(name).to_lowercase();
+		let (stem, ext) = name.split_once('.').unwrap_or((&name, ""));
+		let stem = stem.trim_end();
+
+		if ext == "_en" && !game.is_ed7() {
+			return Some(Kind::ED6Ent)
+		}
+		if ext != "_dt" {
+			return None
+		}
+
+		if let Some(num) = stem.strip_prefix("ms") {
+			if !game.is_ed7() && !num.is_empty() && num.bytes().all(|a| a.is_ascii_digit()) {
+				return Some(Kind::Monster)
+			}
+			return None
+		}
+
+		let ed7 = game.is_ed7();
+		Some(match stem {
+			"t_name" if ed7 => Kind::ED7Name,
+			"t_name" => Kind::ED6Name,
+			"t_quest" if ed7 => Kind::ED7Quest,
+			"t_quest" => Kind::ED6Quest,
+			"t_town" => Kind::Town,
+			"t_world" if !ed7 => Kind::ED6World,
+			"t_bgm" if ed7 => Kind::ED7Bgm,
+			"t_bgmtbl" if !ed7 => Kind::ED6Bgm,
+			"t_se" if ed7 => Kind::ED7Sound,
+			"t_se" => Kind::ED6Sound,
+			"t_item" if !ed7 => Kind::Item,
+			"t_ittxt" if ed7 => Kind::ItemText,
+			"t_item2" if !ed7 => Kind::ItemText,
+			"t_magic" => Kind::Magic,
+			"t_shop" => Kind::Shop,
+			"t_cook" => Kind::Recipe,
+			"t_quartz" if game.base() == BaseGame::Ao => Kind::AoQuartz,
+			"t_quartz" => Kind::Quartz,
+			"t_mstqrt" if game.base() == BaseGame::Ao => Kind::MstQrt,
+			"t_btlset" | "t_btset1" | "t_btset2" if !ed7 => Kind::BattleTable,
+			_ => return None,
+		})
+	}
+
+	/// Like [`Kind::from_name`], but with the name looked up from a file id.
+	pub fn from_file(game: Game, lookup: &dyn Lookup, file: FileId) -> Option<Kind> {
+		Kind::from_name(game, &lookup.name(file.0)?)
+	}
+}
+
+#[test]
+fn test_kind() {
+	assert_eq!(Kind::from_name(Game::Fc, "t_item._dt"), Some(Kind::Item));
+	assert_eq!(Kind::from_name(Game::Zero, "data/text/T_ITTXT._DT"), Some(Kind::ItemText));
+	assert_eq!(Kind::from_name(Game::Sc, "t_ittxt._dt"), None);
+	assert_eq!(Kind::from_name(Game::Zero, "t_item._dt"), None);
+	assert_eq!(Kind::from_name(Game::Tc, "ms30300._dt"), Some(Kind::Monster));
+	assert_eq!(Kind::from_name(Game::Ao, "t_quartz._dt"), Some(Kind::AoQuartz));
+	assert_eq!(Kind::from_name(Game::Fc, "c0100._en"), Some(Kind::ED6Ent));
+	assert_eq!(Kind::from_name(Game::Fc, "c0100._sn"), None);
+
+	let lookup = crate::lookup::default_for(Game::Fc);
+	assert_eq!(Kind::from_file(Game::Fc, lookup, FileId(0x00020000)), Some(Kind::ED6Name));
+}
+
+#[test]
+fn test_roundtrip() -> Result<(), Box<dyn std::error::Error>> {
+	let towns = AnyTable::Town(vec![
+		town::Town { id: TownId(0), name: TString::from("Rolent"), kind: 1 },
+		town::Town { id: TownId(1), name: TString::from("Bose"), kind: 1 },
+	]);
+	let data = towns.write(Game::Fc)?;
+	let kind = Kind::from_name(Game::Fc, "t_town._dt").unwrap();
+	assert_eq!(AnyTable::read(kind, Game::Fc, &data)?, towns);
+	Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde() -> Result<(), Box<dyn std::error::Error>> {
+	let towns = AnyTable::Town(vec![
+		town::Town { id: TownId(0), name: TString::from("Rolent"), kind: 1 },
+	]);
+	let json = serde_json::to_string(&towns)?;
+	assert_eq!(serde_json::from_str::<AnyTable>(&json)?, towns);
+	let kind = serde_json::to_string(&towns.kind())?;
+	assert_eq!(serde_json::from_str::<Kind>(&kind)?, Kind::Town);
+	Ok(())
+}
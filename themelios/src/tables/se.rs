@@ -1,9 +1,10 @@
 use gospel::read::{Reader, Le as _};
 use gospel::write::{Writer, Le as _};
-use crate::types::SoundId;
+use crate::types::{SoundId, FileId};
 use themelios_common::util::*;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ED7Sound {
 	pub id: SoundId,
 	pub file_num: u16,
@@ -66,3 +67,58 @@ impl ED7Sound {
 		Ok(f.finish()?)
 	}
 }
+
+/// An entry in ED6's `t_se._dt`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ED6Sound {
+	pub id: SoundId,
+	/// The sound file in the archives.
+	pub file: FileId,
+	pub unk1: u16,
+	pub unk2: u16,
+}
+
+impl ED6Sound {
+	pub fn read(data: &[u8]) -> Result<Vec<ED6Sound>, ReadError> {
+		let mut f = Reader::new(data);
+		let mut table = Vec::new();
+		while !f.is_empty() {
+			table.push(ED6Sound {
+				id: SoundId(f.u32()?),
+				file: FileId(f.u32()?),
+				unk1: f.u16()?,
+				unk2: f.u16()?,
+			});
+		}
+		Ok(table)
+	}
+
+	pub fn write(table: &[ED6Sound]) -> Result<Vec<u8>, WriteError> {
+		let mut f = Writer::new();
+		for se in table {
+			f.u32(se.id.0);
+			f.u32(se.file.0);
+			f.u16(se.unk1);
+			f.u16(se.unk2);
+		}
+		Ok(f.finish()?)
+	}
+}
+
+super::simple_table!(ED7Sound);
+super::simple_table!(ED6Sound);
+
+#[test]
+fn test_ed6() -> Result<(), Box<dyn std::error::Error>> {
+	for s in [
+		"../data/fc.extract/02/t_se._dt",
+		"../data/sc.extract/22/t_se._dt",
+		"../data/3rd.extract/22/t_se._dt",
+	] {
+		let b = std::fs::read(s)?;
+		let b2 = ED6Sound::write(&ED6Sound::read(&b)?)?;
+		ensure!(b == b2, "{s}");
+	}
+	Ok(())
+}
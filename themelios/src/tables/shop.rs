@@ -0,0 +1,82 @@
+use gospel::read::{Reader, Le as _};
+use gospel::write::{Writer, Le as _};
+use crate::types::*;
+use themelios_common::util::*;
+use super::item::Item;
+
+/// An entry in `t_shop._dt`: the items a shop sells. The shop's name is given by the scena that opens it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Shop {
+	pub id: ShopId,
+	pub items: Vec<ItemId>,
+}
+
+impl Shop {
+	pub fn read(data: &[u8]) -> Result<Vec<Shop>, ReadError> {
+		let f = &mut Reader::new(data);
+		let end = f.clone().ptr16()?;
+		let mut table = Vec::new();
+		while f.pos() < end.pos() {
+			let id = ShopId(cast(table.len())?);
+			let g = &mut f.ptr16()?;
+			let mut items = Vec::new();
+			loop {
+				match g.u16()? {
+					0xFFFF => break,
+					item => items.push(ItemId(item)),
+				}
+			}
+			table.push(Shop { id, items });
+		}
+		Ok(table)
+	}
+
+	pub fn write(table: &[Shop]) -> Result<Vec<u8>, WriteError> {
+		let mut f = Writer::new();
+		let mut g = Writer::new();
+		for (i, shop) in table.iter().enumerate() {
+			ensure!(shop.id.0 as usize == i, "shop ids must be consecutive");
+			f.delay16(g.here());
+			for item in &shop.items {
+				ensure!(item.0 != 0xFFFF);
+				g.u16(item.0);
+			}
+			g.u16(0xFFFF);
+		}
+		f.append(g);
+		Ok(f.finish()?)
+	}
+
+	/// The items sold here that are not in the item table.
+	pub fn missing_items(&self, items: &[Item]) -> Vec<ItemId> {
+		self.items.iter().copied()
+			.filter(|id| !items.iter().any(|i| i.id == *id))
+			.collect()
+	}
+}
+
+super::simple_table!(Shop);
+
+#[test]
+fn test() -> Result<(), Box<dyn std::error::Error>> {
+	for s in [
+		"../data/fc.extract/02/t_shop._dt",
+		"../data/sc.extract/22/t_shop._dt",
+		"../data/3rd.extract/22/t_shop._dt",
+		"../data/zero/data/text/t_shop._dt",
+		"../data/ao/data/text/t_shop._dt",
+	] {
+		let b = std::fs::read(s)?;
+		let b2 = Shop::write(&Shop::read(&b)?)?;
+		ensure!(b == b2, "{s}");
+	}
+	Ok(())
+}
+
+#[test]
+fn test_missing_items() {
+	let items = [10, 11].map(super::item::test_item);
+	let shop = Shop { id: ShopId(0), items: vec![ItemId(10), ItemId(12), ItemId(11), ItemId(0)] };
+	assert_eq!(shop.missing_items(&items), [ItemId(12), ItemId(0)]);
+}
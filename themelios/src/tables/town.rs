@@ -6,6 +6,7 @@ use crate::types::*;
 use themelios_common::util::*;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Town {
 	pub id: TownId,
 	pub name: TString,
@@ -60,3 +61,13 @@ impl Town {
 		Ok(f.finish()?)
 	}
 }
+
+impl super::Table for Vec<Town> {
+	fn read(game: Game, data: &[u8]) -> Result<Self, ReadError> {
+		Town::read(game, data)
+	}
+
+	fn write(game: Game, table: &Self) -> Result<Vec<u8>, WriteError> {
+		Town::write(game, table)
+	}
+}
@@ -5,6 +5,7 @@ use themelios_common::util::*;
 use crate::types::*;
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ED6World {
 	pub scena: FileId,
 	pub pos: IVec2,
@@ -36,6 +37,8 @@ impl ED6World {
 	}
 }
 
+super::simple_table!(ED6World);
+
 #[test]
 fn test() -> Result<(), Box<dyn std::error::Error>> {
 	ED6World::read(&std::fs::read("../data/fc.extract/02/t_world._dt")?)?;